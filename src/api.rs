@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use crate::{
     DATA_DIR, PathBuf, db,
     error::{AiterError, AiterResult},
@@ -9,6 +11,7 @@ pub mod config;
 pub mod learn;
 pub mod llm;
 pub mod mem;
+pub mod prompt;
 pub mod sys;
 pub mod tool;
 
@@ -26,6 +29,24 @@ async fn get_docs_dir_path(name: Option<&str>) -> AiterResult<PathBuf> {
     Ok(DATA_DIR.join(dir_name))
 }
 
+/// Get the docs directory of the AI that the mem path belongs to
+fn get_docs_dir_path_by_mem_path(mem_path: &Path) -> Option<PathBuf> {
+    match get_ai_id_by_mem_path(mem_path)?.as_str() {
+        "~" => Some(DATA_DIR.join("docs")),
+        ai_id => Some(DATA_DIR.join(format!("docs_{ai_id}"))),
+    }
+}
+
+/// Get AI's id from its mem path, `~` for the default AI
+fn get_ai_id_by_mem_path(mem_path: &Path) -> Option<String> {
+    let stem = mem_path.file_stem()?.to_str()?;
+    if stem == "mem" {
+        Some("~".to_string())
+    } else {
+        stem.strip_prefix("mem_").map(|id| id.to_string())
+    }
+}
+
 async fn get_mem_path(name: Option<&str>) -> AiterResult<PathBuf> {
     let mem_db_filename = if let Some(name) = name {
         if let Some(ai) = db::core::ai::get_by_name(name).await? {
@@ -1,7 +1,11 @@
+use std::sync::LazyLock;
+
+use dashmap::DashMap;
 use tokio::sync::{mpsc::Sender, oneshot};
+use tokio_util::sync::CancellationToken;
 
 use crate::{
-    CHAT_HISTORY_LIMIT,
+    CHAT_HISTORY_LIMIT, VecOptions,
     api::get_mem_path,
     chat,
     chat::stream_chat,
@@ -9,19 +13,26 @@ use crate::{
     db::mem::MemWriteEvent,
     error::AiterResult,
     llm,
-    llm::{ChatMessage, Role},
+    llm::{ChatCompletionOptions, ChatMessage, Role},
 };
 
 pub type ChatCompletionStream = llm::ChatCompletionStream;
 pub type ChatOptions = chat::ChatOptions;
 pub type HistoryChatEntity = db::mem::history_chat::HistoryChatEntity;
 
+static CHAT_CANCEL_TOKENS: LazyLock<DashMap<String, CancellationToken>> =
+    LazyLock::new(DashMap::new);
+
 pub async fn chat(
     ai_name: Option<&str>,
     question: &str,
     chat_options: &ChatOptions,
     mem_write_event_sender: Sender<MemWriteEvent>,
 ) -> AiterResult<ChatCompletionStream> {
+    // Validate options before saving the question, so that no empty answer is left in history
+    ChatCompletionOptions::default()
+        .with_llm_options(&VecOptions(&chat_options.llm_options).into_map())?;
+
     let mem_path = get_mem_path(ai_name).await?;
 
     let max_history = chat_options.retrace.min(CHAT_HISTORY_LIMIT);
@@ -63,7 +74,20 @@ pub async fn chat(
         resp_receiver.await??
     };
 
-    stream_chat(
+    // Register before starting, so the chat can be stopped by exchange until the stream is closed or dropped
+    let cancel_token = chat_options.cancel_token.clone();
+    if let Some(exchange) = &chat_options.exchange {
+        CHAT_CANCEL_TOKENS.insert(exchange.clone(), cancel_token.clone());
+
+        let cancel_token = cancel_token.clone();
+        let exchange = exchange.clone();
+        tokio::spawn(async move {
+            cancel_token.cancelled().await;
+            CHAT_CANCEL_TOKENS.remove_if(&exchange, |_, token| token.is_cancelled());
+        });
+    }
+
+    let stream = stream_chat(
         &mem_path,
         answer_rowid,
         question,
@@ -72,6 +96,9 @@ pub async fn chat(
         mem_write_event_sender,
     )
     .await
+    .inspect_err(|_| cancel_token.cancel())?;
+
+    Ok(stream)
 }
 
 pub async fn clear(
@@ -118,3 +145,109 @@ pub async fn history(
 
     db::mem::history_chat::retrace(&mem_path, CHAT_HISTORY_LIMIT, session).await
 }
+
+/// Stop generating the answer of the exchange, returns false if the chat is not in progress
+pub async fn stop(exchange: &str) -> bool {
+    if let Some((_, cancel_token)) = CHAT_CANCEL_TOKENS.remove(exchange) {
+        cancel_token.cancel();
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::Value;
+    use ulid::Ulid;
+
+    use super::*;
+    use crate::{
+        api::mem::spawn_mem_write,
+        db::ensure_test_tables,
+        llm::provider::mock::{mock_response, mock_server_with_responses},
+    };
+
+    #[tokio::test]
+    async fn test_chat_invalid_options() {
+        ensure_test_tables().await;
+
+        let session = Ulid::new().to_string();
+        let chat_options = ChatOptions::default()
+            .with_llm_options(vec!["top_p:5".to_string()])
+            .with_session(Some(session.clone()));
+
+        let result = chat(
+            None,
+            "Hi",
+            &chat_options,
+            spawn_mem_write(None).await.unwrap(),
+        )
+        .await;
+        assert!(result.is_err());
+        assert!(history(None, Some(&session)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stop() {
+        ensure_test_tables().await;
+
+        // Served to extract and simplify queries, the answer is not requested once stopped
+        let completion_response = |content: &str| {
+            let body = format!(
+                "data: {}\n\ndata: [DONE]\n\n",
+                serde_json::json!({"choices": [{"delta": {"content": content}}]})
+            );
+            mock_response("200 OK", &[], "text/event-stream", &body)
+        };
+        let base_url = mock_server_with_responses(vec![
+            completion_response(r#"{"queries":[]}"#),
+            completion_response("[]"),
+            completion_response("Answer"),
+        ]);
+        let llm_name = Ulid::new().to_string();
+        db::core::llm::upsert(
+            &llm_name,
+            "chat",
+            "openai",
+            &HashMap::from([
+                ("base_url".to_string(), base_url),
+                ("model".to_string(), "gpt".to_string()),
+            ]),
+        )
+        .await
+        .unwrap();
+
+        let exchange = Ulid::new().to_string();
+        let session = Ulid::new().to_string();
+        let chat_options = ChatOptions::default()
+            .with_exchange(Some(exchange.clone()))
+            .with_llm_for_chat(Some(llm_name))
+            .with_session(Some(session.clone()));
+
+        let mut stream = chat(
+            None,
+            "Hi",
+            &chat_options,
+            spawn_mem_write(None).await.unwrap(),
+        )
+        .await
+        .unwrap();
+        assert!(stop(&exchange).await);
+        assert!(!stop(&exchange).await);
+        assert!(chat_options.cancel_token.is_cancelled());
+        while stream.next().await.is_some() {}
+
+        let answer = history(None, Some(&session))
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|m| m.role == Role::Bot.to_string())
+            .unwrap();
+        let answer: Value = serde_json::from_str(&answer.content).unwrap();
+        assert_eq!(answer["interrupted"], true);
+        assert_eq!(answer["content"], "");
+    }
+}
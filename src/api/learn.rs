@@ -1,20 +1,24 @@
 use std::{
-    fs::{copy, create_dir_all},
+    fs::{copy, create_dir_all, remove_file, write},
     path::{Path, PathBuf},
 };
 
 use tokio::sync::mpsc::Sender;
+use ulid::Ulid;
+use url::Url;
 
 use crate::{
+    api,
     api::{get_docs_dir_path, get_mem_path},
     content::{
         doc::DocContent,
-        parsers::{csv, docx, epub, md, pdf, txt, xlsx},
+        parsers::{code, csv, docx, epub, geojson, html, md, odp, pdf, pptx, txt, xlsx},
     },
+    db,
     db::mem::MemWriteEvent,
     error::{AiterError, AiterResult},
     learn,
-    utils::fs,
+    utils::{code::detect_language, fs, net},
 };
 
 pub enum DigestEvent {
@@ -25,6 +29,7 @@ pub struct DigestOptions {
     pub batch: usize,
     pub concurrent: usize,
     pub deep: bool,
+    pub llm: Option<String>, // LLM for digesting, the active digest LLM is used if not specified, and then the active chat LLM
     pub retry: bool,
 }
 
@@ -78,6 +83,38 @@ pub async fn digest_doc(
     .await
 }
 
+/// Whether the format can be read by `read_doc`, source code is detected by the extension
+pub fn is_format_supported(format: &str) -> bool {
+    matches!(
+        format.to_lowercase().as_str(),
+        "csv"
+            | "docx"
+            | "epub"
+            | "geojson"
+            | "html"
+            | "htm"
+            | "xhtml"
+            | "mhtml"
+            | "md"
+            | "odp"
+            | "pdf"
+            | "pptx"
+            | "txt"
+            | "xlsx"
+            | "xls"
+            | "xlsm"
+            | "xlsb"
+            | "xla"
+            | "xlam"
+            | "ods"
+    ) || detect_language(format).is_some()
+}
+
+/// Whether the source should be read by `read_url` rather than `read_doc`
+pub fn is_url_source(source: &str) -> bool {
+    net::is_http_url(source)
+}
+
 pub async fn read_doc(
     ai_name: Option<&str>,
     path: &Path,
@@ -106,17 +143,24 @@ pub async fn read_doc(
             "csv" => Box::new(csv::to_sheet_doc(path, &source)?),
             "docx" => Box::new(docx::to_text_doc(path, &source)?),
             "epub" => Box::new(epub::to_text_doc(path, &source)?),
+            "geojson" => Box::new(geojson::to_sheet_doc(path, &source)?),
+            "html" | "htm" | "xhtml" | "mhtml" => Box::new(html::to_markdown_doc(path, &source)?),
             "md" => Box::new(md::to_markdown_doc(path, &source)?),
+            "odp" => Box::new(odp::to_text_doc(path, &source)?),
             "pdf" => Box::new(pdf::to_text_doc(path, &source)?),
+            "pptx" => Box::new(pptx::to_text_doc(path, &source)?),
             "txt" => Box::new(txt::to_text_doc(path, &source)?),
             "xlsx" | "xls" | "xlsm" | "xlsb" | "xla" | "xlam" | "ods" => {
                 Box::new(xlsx::to_sheet_doc(path, &source)?)
             }
-            _ => {
-                return Err(AiterError::Unsupported(format!(
-                    "Format '{suffix}' is not currently supported"
-                )));
-            }
+            _ => match detect_language(&suffix) {
+                Some(language) => Box::new(code::to_code_doc(path, &source, language)?),
+                None => {
+                    return Err(AiterError::Unsupported(format!(
+                        "Format '{suffix}' is not currently supported"
+                    )));
+                }
+            },
         };
 
         let read_result = learn::read_doc(
@@ -128,6 +172,8 @@ pub async fn read_doc(
         )
         .await?;
 
+        // Geometries are not in the sheet, so the original is always kept for spatial lookup
+        let keep = keep || suffix == "geojson";
         if keep && !read_result.doc_exists {
             let docs_path = get_docs_dir_path(ai_name).await?;
             create_dir_all(&docs_path)?;
@@ -145,12 +191,99 @@ pub async fn read_doc(
     }
 }
 
+/// Read the resource of URL and keep the URL as the source, docs previously read from the same URL are
+/// replaced if the content has changed
+pub async fn read_url(
+    ai_name: Option<&str>,
+    url: &str,
+    format: Option<&str>,
+    keep: bool,
+    mem_write_event_sender: Sender<MemWriteEvent>,
+    read_event_sender: Option<Sender<ReadEvent>>,
+) -> AiterResult<ReadResult> {
+    if let Some(read_event_sender) = &read_event_sender {
+        let _ = read_event_sender
+            .send(ReadEvent::Progress("Fetching".to_string()))
+            .await;
+    }
+
+    let (bytes, content_type) = net::fetch_url(url).await?;
+
+    let Some(format) = format
+        .map(|format| format.to_lowercase())
+        .or_else(|| detect_url_format(url, content_type.as_deref()))
+    else {
+        return Err(AiterError::Unsupported(format!(
+            "Unknown format and not specified: {url}"
+        )));
+    };
+
+    let temp_path = std::env::temp_dir().join(format!("aiter-{}", Ulid::new()));
+    write(&temp_path, &bytes)?;
+
+    let read_result = read_doc(
+        ai_name,
+        &temp_path,
+        Some(url),
+        Some(&format),
+        keep,
+        mem_write_event_sender.clone(),
+        read_event_sender,
+    )
+    .await;
+    let _ = remove_file(&temp_path);
+    let read_result = read_result?;
+
+    // Content is deduplicated by hash, so a new doc means the content of the URL has changed
+    if !read_result.doc_exists {
+        let mem_path = get_mem_path(ai_name).await?;
+        for doc in db::mem::doc::list_by_source(&mem_path, url).await? {
+            if doc.id != read_result.doc_id {
+                api::mem::doc::delete(ai_name, &doc.id, mem_write_event_sender.clone()).await?;
+            }
+        }
+    }
+
+    Ok(read_result)
+}
+
+/// Detect by Content-Type, the extension of URL path is used if the Content-Type is missing or too generic
+fn detect_url_format(url: &str, content_type: Option<&str>) -> Option<String> {
+    let extension = Url::parse(url).ok().and_then(|url| {
+        PathBuf::from(url.path())
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+    });
+
+    let format = match content_type {
+        Some("application/epub+zip") => "epub",
+        Some("application/geo+json") => "geojson",
+        Some("application/pdf") => "pdf",
+        Some("application/vnd.ms-excel") => "xls",
+        Some("application/vnd.oasis.opendocument.presentation") => "odp",
+        Some("application/vnd.oasis.opendocument.spreadsheet") => "ods",
+        Some("application/vnd.openxmlformats-officedocument.presentationml.presentation") => "pptx",
+        Some("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet") => "xlsx",
+        Some("application/vnd.openxmlformats-officedocument.wordprocessingml.document") => "docx",
+        Some("application/xhtml+xml") => "xhtml",
+        Some("message/rfc822" | "multipart/related") => "mhtml",
+        Some("text/csv") => "csv",
+        Some("text/html") => "html",
+        Some("text/markdown" | "text/x-markdown") => "md",
+        Some("text/plain") => return extension.or(Some("txt".to_string())),
+        _ => return extension,
+    };
+
+    Some(format.to_string())
+}
+
 impl Default for DigestOptions {
     fn default() -> Self {
         Self {
             batch: 1,
             concurrent: 1,
             deep: false,
+            llm: None,
             retry: false,
         }
     }
@@ -172,8 +305,110 @@ impl DigestOptions {
         self
     }
 
+    pub fn with_llm(mut self, llm: Option<String>) -> Self {
+        self.llm = llm;
+        self
+    }
+
     pub fn with_retry(mut self, retry: bool) -> Self {
         self.retry = retry;
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::mem::spawn_mem_write,
+        db::ensure_test_tables,
+        llm::provider::mock::{mock_response, mock_server_with_responses},
+    };
+
+    #[tokio::test]
+    async fn test_read_url() {
+        ensure_test_tables().await;
+
+        let base_url = mock_server_with_responses(vec![
+            mock_response(
+                "200 OK",
+                &[],
+                "text/plain",
+                &format!("First {}", Ulid::new()),
+            ),
+            mock_response(
+                "200 OK",
+                &[],
+                "text/plain",
+                &format!("Second {}", Ulid::new()),
+            ),
+        ]);
+        let url = format!("{base_url}/notes-{}", Ulid::new());
+
+        let first = read_url(
+            None,
+            &url,
+            None,
+            false,
+            spawn_mem_write(None).await.unwrap(),
+            None,
+        )
+        .await
+        .unwrap();
+        let second = read_url(
+            None,
+            &url,
+            None,
+            false,
+            spawn_mem_write(None).await.unwrap(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(!second.doc_exists);
+        assert_ne!(first.doc_id, second.doc_id);
+
+        let mem_path = get_mem_path(None).await.unwrap();
+        assert!(
+            db::mem::doc::get(&mem_path, &first.doc_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let docs = db::mem::doc::list_by_source(&mem_path, &url).await.unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].id, second.doc_id);
+        assert_eq!(docs[0].source, url);
+    }
+
+    #[test]
+    fn test_detect_url_format() {
+        assert_eq!(
+            detect_url_format("https://example.com/", Some("text/html")),
+            Some("html".to_string())
+        );
+        assert_eq!(
+            detect_url_format("https://example.com/a/README.md", Some("text/plain")),
+            Some("md".to_string())
+        );
+        assert_eq!(
+            detect_url_format("https://example.com/notes", Some("text/plain")),
+            Some("txt".to_string())
+        );
+        assert_eq!(
+            detect_url_format("https://example.com/layer", Some("application/geo+json")),
+            Some("geojson".to_string())
+        );
+        assert_eq!(
+            detect_url_format("https://example.com/book.PDF?v=1", None),
+            Some("pdf".to_string())
+        );
+        assert_eq!(
+            detect_url_format(
+                "https://example.com/download",
+                Some("application/octet-stream")
+            ),
+            None
+        );
+    }
+}
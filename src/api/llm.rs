@@ -1,131 +1,216 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use serde_json::json;
+use time::{Date, macros::format_description};
+use tokio::sync::mpsc;
 
 use crate::{
+    CHANNEL_BUFFER_DEFAULT, CURRENT_TOKENIZER, LLM_CHAT_TEMPERATURE_STABLE,
+    LLM_COMPLETION_RESERVED_TOKENS, LLM_MAX_RETRY_DEFAULT, LLM_TIMEOUT_SECS_DEFAULT,
+    api::get_ai_id_by_mem_path,
     db,
     error::*,
     llm,
-    llm::provider::{open_ai::OpenAiProvider, *},
+    llm::provider::{
+        anthropic::{ANTHROPIC_BASE_URL_DEFAULT, AnthropicProvider},
+        ollama::{OLLAMA_BASE_URL_DEFAULT, OllamaProvider},
+        open_ai::OpenAiProvider,
+        record::RecordProvider,
+        replay::ReplayProvider,
+        *,
+    },
+    llm::{ChatUsage, limiter, limiter::LlmPermit},
+    utils::{crypto::sha256, text::to_tokens},
 };
 
-pub static SUPPORTED_TYPES: &[&str] = &["chat", "reasoning"];
-pub static SUPPORTED_PROTOCOLS: &[&str] = &["openai"];
+pub static SUPPORTED_TYPES: &[&str] = &["chat", "digest", "embedding", "reasoning", "rerank"];
+pub static SUPPORTED_PROTOCOLS: &[&str] = &["anthropic", "ollama", "openai", "record", "replay"];
 
 pub type ChatCompletionEvent = llm::ChatCompletionEvent;
 pub type ChatCompletionOptions = llm::ChatCompletionOptions;
 pub type ChatCompletionStream = llm::ChatCompletionStream;
+pub type ChatContentPart = llm::ChatContentPart;
 pub type ChatFunction = llm::ChatFunction;
 pub type ChatFunctionCall = llm::ChatFunctionCall;
 pub type ChatMessage = llm::ChatMessage;
 pub type Role = llm::Role;
+pub type UsageTag = llm::UsageTag;
 
+pub type LlmCacheStatsEntity = db::core::llm_cache::LlmCacheStatsEntity;
+pub type LlmCheckItem = llm::check::LlmCheckItem;
 pub type LlmEntity = db::core::llm::LlmEntity;
+pub type LlmUsageGroup = db::core::llm_usage::LlmUsageGroup;
+pub type LlmUsageStatsEntity = db::core::llm_usage::LlmUsageStatsEntity;
+pub type LlmUtilizationEntity = llm::limiter::LlmUtilizationEntity;
+
+static LLM_CACHE_BYPASS: AtomicBool = AtomicBool::new(false);
 
 pub async fn active(r#type: &str, name: &str) -> AiterResult<()> {
-    match r#type {
-        "chat" => db::core::config::set(&db::core::config::ConfigKey::ActiveChatLlm, name).await,
-        "reasoning" => {
-            db::core::config::set(&db::core::config::ConfigKey::ActiveReasoningLlm, name).await
-        }
-        _ => Err(AiterError::Invalid(format!("Invalid LLM type '{type}'"))),
+    if let Some(active_key) = get_active_config_key(r#type) {
+        db::core::config::set(&active_key, name).await
+    } else {
+        Err(AiterError::Invalid(format!("Invalid LLM type '{type}'")))
     }
 }
 
+pub async fn cache_clear(llm_name: Option<&str>) -> AiterResult<u64> {
+    db::core::llm_cache::clear(llm_name).await
+}
+
+pub async fn cache_stats() -> AiterResult<Vec<LlmCacheStatsEntity>> {
+    db::core::llm_cache::stats().await
+}
+
 pub async fn chat_completion(
     message: &str,
     history: &[ChatMessage],
     chat_completion_options: &ChatCompletionOptions,
     chat_llm_name: Option<&str>,
 ) -> AiterResult<ChatMessage> {
-    let name = if chat_llm_name.is_none() {
-        get_actived_name("chat").await?
-    } else {
-        chat_llm_name.map(|s| s.to_string())
-    };
+    let mut messages = history.to_vec();
+    messages.push(ChatMessage::new(Role::User, message));
 
-    if let Some(name) = name {
-        if let Some(LlmEntity {
-            protocol, options, ..
-        }) = get_by_name(&name).await?
-        {
-            if protocol == "openai" {
-                if let (Some(base_url), Some(model)) =
-                    (options.get("base_url"), options.get("model"))
-                {
-                    let api_key = options.get("api_key").map_or("", |v| v);
-
-                    let mut messages = history.to_vec();
-                    messages.push(ChatMessage {
-                        role: Role::User,
-                        content: message.to_string(),
-                        reasoning: None,
-                    });
-
-                    return OpenAiProvider::new(base_url, api_key, model)
-                        .chat_completion(&messages, chat_completion_options)
-                        .await;
-                } else {
-                    for k in ["base_url", "model"] {
-                        if !options.contains_key(k) {
-                            return Err(AiterError::Invalid(format!(
-                                "Missing required option {k}"
-                            )));
-                        }
-                    }
-                }
-            }
-        }
+    let llm = get_chat_llm(chat_llm_name).await?;
+
+    if !is_cacheable(
+        chat_completion_options,
+        LLM_CACHE_BYPASS.load(Ordering::Relaxed),
+    ) {
+        let (message, _served_llm) = complete(llm, &messages, chat_completion_options).await?;
+        return Ok(message);
     }
 
-    Err(AiterError::Invalid("No Chat LLM".to_string()))
+    // Sampling defaults of the LLM affect the response as well
+    let prompt_hash = hash_prompt(
+        &messages,
+        &chat_completion_options
+            .clone()
+            .with_llm_defaults(&llm.options)?,
+    );
+
+    if let Some(cached) = db::core::llm_cache::get(
+        &llm.name,
+        llm.options.get("model").map_or("", |v| v),
+        chat_completion_options.temperature,
+        &prompt_hash,
+    )
+    .await?
+    {
+        return Ok(ChatMessage {
+            content: cached.content,
+            reasoning: cached.reasoning,
+            ..ChatMessage::new(Role::Bot, "")
+        });
+    }
+
+    let llm_name = llm.name.clone();
+    let (message, served_llm) = complete(llm, &messages, chat_completion_options).await?;
+
+    // Responses of fallbacks are not cached, otherwise they would be served for the LLM after it recovers
+    if served_llm.name != llm_name {
+        return Ok(message);
+    }
+
+    if let Err(err) = db::core::llm_cache::upsert(
+        &served_llm.name,
+        served_llm.options.get("model").map_or("", |v| v),
+        chat_completion_options.temperature,
+        &prompt_hash,
+        &message.content,
+        message.reasoning.as_deref(),
+    )
+    .await
+    {
+        log::warn!("Save LLM cache error: {err}");
+    }
+
+    Ok(message)
 }
 
+/// `tool_turns` are calls of previous rounds and their results, which follow the message
 pub async fn chat_function_calls(
     functions: &[ChatFunction],
     message: &str,
     history: &[ChatMessage],
+    tool_turns: &[ChatMessage],
+    usage_tag: &UsageTag,
     chat_llm_name: Option<&str>,
 ) -> AiterResult<Vec<ChatFunctionCall>> {
-    let name = if chat_llm_name.is_none() {
-        get_actived_name("chat").await?
-    } else {
-        chat_llm_name.map(|s| s.to_string())
-    };
+    let mut messages = history.to_vec();
+    messages.push(ChatMessage::new(Role::User, message));
+    messages.extend_from_slice(tool_turns);
 
-    if let Some(name) = name {
-        if let Some(LlmEntity {
-            protocol, options, ..
-        }) = get_by_name(&name).await?
-        {
-            if protocol == "openai" {
-                if let (Some(base_url), Some(model)) =
-                    (options.get("base_url"), options.get("model"))
-                {
-                    let api_key = options.get("api_key").map_or("", |v| v);
-
-                    let mut messages = history.to_vec();
-                    messages.push(ChatMessage {
-                        role: Role::User,
-                        content: message.to_string(),
-                        reasoning: None,
-                    });
-
-                    return OpenAiProvider::new(base_url, api_key, model)
-                        .chat_function_calls(&messages, functions)
-                        .await;
-                } else {
-                    for k in ["base_url", "model"] {
-                        if !options.contains_key(k) {
-                            return Err(AiterError::Invalid(format!(
-                                "Missing required option {k}"
-                            )));
-                        }
-                    }
-                }
-            }
+    let llm = get_chat_llm(chat_llm_name).await?;
+    let (calls, llm) = with_fallback(llm, |llm| {
+        let messages = &messages;
+        async move {
+            let provider = make_provider(&llm.protocol, &llm.options)?;
+            let permit = limiter::acquire(&llm.name, &llm.options).await;
+            let calls = permit
+                .scope(provider.chat_function_calls(messages, functions))
+                .await?;
+            Ok((calls, llm))
         }
+    })
+    .await?;
+
+    // Function calls are not streamed to caller, so usage is always estimated
+    let prompt_tokens = estimate_tokens(messages.iter().map(|m| &m.content))
+        + estimate_tokens(
+            functions
+                .iter()
+                .map(|f| format!("{} {} {}", f.name, f.description, f.parameters)),
+        );
+    let completion_tokens =
+        estimate_tokens(calls.iter().map(|c| format!("{} {}", c.name, c.arguments)));
+    record_usage(
+        &llm,
+        usage_tag,
+        ChatUsage {
+            prompt_tokens,
+            completion_tokens,
+        },
+        true,
+    )
+    .await;
+
+    Ok(calls)
+}
+
+/// Run diagnostics of the LLM, capabilities detected are saved to its options
+pub async fn check(name: &str) -> AiterResult<Vec<LlmCheckItem>> {
+    let llm = get_by_name(name)
+        .await?
+        .ok_or(AiterError::NotExists(format!("LLM '{name}' not exists")))?;
+
+    let options = ChatCompletionOptions::default()
+        .with_usage_tag(UsageTag::new("check"))
+        .with_llm_defaults(&llm.options)?;
+    let provider = make_provider(&llm.protocol, &llm.options)?;
+    let items = {
+        let permit = limiter::acquire(&llm.name, &llm.options).await;
+        permit
+            .scope(llm::check::check(&provider, &llm.r#type, &options))
+            .await
+    };
+
+    let capabilities: HashMap<String, String> = items
+        .iter()
+        .filter_map(|item| {
+            item.capability
+                .as_ref()
+                .map(|capability| (format!("supports_{capability}"), item.passed.to_string()))
+        })
+        .collect();
+    if !capabilities.is_empty() {
+        config(name, None, None, &capabilities).await?;
     }
 
-    Err(AiterError::Invalid("No Chat LLM".to_string()))
+    Ok(items)
 }
 
 pub async fn config(
@@ -142,6 +227,9 @@ pub async fn config(
         ));
     }
 
+    // Options such as top_p are defaults of sampling, validate them in advance
+    ChatCompletionOptions::default().with_llm_options(options)?;
+
     if let Some(row) = db::core::llm::get_by_name(name).await? {
         let mut merged_options = row.options.clone();
         for (k, v) in options {
@@ -158,29 +246,10 @@ pub async fn config(
         if let (Some(r#type), Some(protocol)) = (r#type, protocol) {
             db::core::llm::upsert(name, r#type, protocol, options).await?;
 
-            match r#type {
-                "chat" => {
-                    if db::core::config::get(&db::core::config::ConfigKey::ActiveChatLlm)
-                        .await?
-                        .is_none()
-                    {
-                        db::core::config::set(&db::core::config::ConfigKey::ActiveChatLlm, name)
-                            .await?;
-                    }
+            if let Some(active_key) = get_active_config_key(r#type) {
+                if db::core::config::get(&active_key).await?.is_none() {
+                    db::core::config::set(&active_key, name).await?;
                 }
-                "reasoning" => {
-                    if db::core::config::get(&db::core::config::ConfigKey::ActiveReasoningLlm)
-                        .await?
-                        .is_none()
-                    {
-                        db::core::config::set(
-                            &db::core::config::ConfigKey::ActiveReasoningLlm,
-                            name,
-                        )
-                        .await?;
-                    }
-                }
-                _ => (),
             }
         } else {
             return Err(AiterError::Invalid(
@@ -200,16 +269,78 @@ pub async fn delete(name: &str) -> AiterResult<Option<LlmEntity>> {
     Ok(item)
 }
 
-pub async fn get_actived_name(r#type: &str) -> AiterResult<Option<String>> {
-    match r#type {
-        "chat" => db::core::config::get(&db::core::config::ConfigKey::ActiveChatLlm).await,
-        "reasoning" => {
-            db::core::config::get(&db::core::config::ConfigKey::ActiveReasoningLlm).await
+pub async fn embeddings(
+    texts: &[String],
+    embedding_llm_name: Option<&str>,
+) -> AiterResult<Vec<Vec<f32>>> {
+    if texts.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let name = if embedding_llm_name.is_none() {
+        get_actived_name("embedding").await?
+    } else {
+        embedding_llm_name.map(|s| s.to_string())
+    };
+
+    if let Some(name) = name {
+        if let Some(llm) = get_by_name(&name).await? {
+            let provider = make_provider(&llm.protocol, &llm.options)?;
+            let permit = limiter::acquire(&llm.name, &llm.options).await;
+            let embeddings = permit.scope(provider.embeddings(texts)).await?;
+
+            record_usage(
+                &llm,
+                &UsageTag::new("embedding"),
+                ChatUsage {
+                    prompt_tokens: estimate_tokens(texts),
+                    completion_tokens: 0,
+                },
+                true,
+            )
+            .await;
+            if embeddings.len() != texts.len() {
+                return Err(AiterError::Invalid(format!(
+                    "Expect {} embeddings but got {}",
+                    texts.len(),
+                    embeddings.len()
+                )));
+            }
+
+            return Ok(embeddings);
         }
-        _ => Ok(None),
+    }
+
+    Err(AiterError::Invalid("No Embedding LLM".to_string()))
+}
+
+pub async fn get_actived_name(r#type: &str) -> AiterResult<Option<String>> {
+    if let Some(active_key) = get_active_config_key(r#type) {
+        db::core::config::get(&active_key).await
+    } else {
+        Ok(None)
     }
 }
 
+/// Tokens left for the prompt in the context window, i.e. the `context_tokens` option minus tokens reserved for
+/// completion, none if the option is not specified
+pub async fn get_prompt_tokens_budget(chat_llm_name: Option<&str>) -> AiterResult<Option<usize>> {
+    let llm = get_chat_llm(chat_llm_name).await?;
+
+    Ok(llm
+        .options
+        .get("context_tokens")
+        .and_then(|v| v.parse::<usize>().ok())
+        .map(|context_tokens| {
+            let reserved_tokens = llm
+                .options
+                .get("max_tokens")
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(LLM_COMPLETION_RESERVED_TOKENS);
+            context_tokens.saturating_sub(reserved_tokens)
+        }))
+}
+
 pub async fn get_by_name(name: &str) -> AiterResult<Option<LlmEntity>> {
     db::core::llm::get_by_name(name).await
 }
@@ -218,22 +349,40 @@ pub async fn list() -> AiterResult<Vec<LlmEntity>> {
     db::core::llm::list().await
 }
 
-pub async fn list_actived_names() -> AiterResult<HashMap<String, String>> {
-    let mut map: HashMap<String, String> = HashMap::new();
+/// Current utilization of each LLM's limiter in current process, so it is only meaningful in a long-running server
+pub async fn list_utilizations() -> AiterResult<Vec<LlmUtilizationEntity>> {
+    let mut utilizations = vec![];
 
-    if let Some(name) = db::core::config::get(&db::core::config::ConfigKey::ActiveChatLlm).await? {
-        map.insert("chat".to_string(), name);
+    for llm in list().await? {
+        utilizations.push(limiter::utilization(&llm.name, &llm.options).await);
     }
 
-    if let Some(name) =
-        db::core::config::get(&db::core::config::ConfigKey::ActiveReasoningLlm).await?
-    {
-        map.insert("reasoning".to_string(), name);
+    Ok(utilizations)
+}
+
+pub async fn list_actived_names() -> AiterResult<HashMap<String, String>> {
+    let mut map: HashMap<String, String> = HashMap::new();
+
+    for r#type in SUPPORTED_TYPES {
+        if let Some(name) = get_actived_name(r#type).await? {
+            map.insert(r#type.to_string(), name);
+        }
     }
 
     Ok(map)
 }
 
+/// Models provided by the service of the LLM
+pub async fn models(name: &str) -> AiterResult<Vec<String>> {
+    let llm = get_by_name(name)
+        .await?
+        .ok_or(AiterError::NotExists(format!("LLM '{name}' not exists")))?;
+
+    let provider = make_provider(&llm.protocol, &llm.options)?;
+    let permit = limiter::acquire(&llm.name, &llm.options).await;
+    permit.scope(provider.models()).await
+}
+
 pub async fn rename(name: &str, new_name: &str) -> AiterResult<()> {
     if new_name.is_empty() {
         return Err(AiterError::Invalid("LLM name cannot be empty".to_string()));
@@ -245,24 +394,68 @@ pub async fn rename(name: &str, new_name: &str) -> AiterResult<()> {
 
     db::core::llm::rename(name, new_name).await?;
 
-    if let Some(active_name) =
-        db::core::config::get(&db::core::config::ConfigKey::ActiveChatLlm).await?
-    {
-        if active_name == name {
-            db::core::config::set(&db::core::config::ConfigKey::ActiveChatLlm, new_name).await?;
+    for r#type in SUPPORTED_TYPES {
+        if let Some(active_key) = get_active_config_key(r#type) {
+            if let Some(active_name) = db::core::config::get(&active_key).await? {
+                if active_name == name {
+                    db::core::config::set(&active_key, new_name).await?;
+                }
+            }
         }
     }
 
-    if let Some(active_name) =
-        db::core::config::get(&db::core::config::ConfigKey::ActiveReasoningLlm).await?
-    {
-        if active_name == name {
-            db::core::config::set(&db::core::config::ConfigKey::ActiveReasoningLlm, new_name)
-                .await?;
+    Ok(())
+}
+
+pub async fn rerank(
+    query: &str,
+    documents: &[String],
+    rerank_llm_name: Option<&str>,
+) -> AiterResult<Vec<f64>> {
+    if documents.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let name = if rerank_llm_name.is_none() {
+        get_actived_name("rerank").await?
+    } else {
+        rerank_llm_name.map(|s| s.to_string())
+    };
+
+    if let Some(name) = name {
+        if let Some(llm) = get_by_name(&name).await? {
+            let provider = make_provider(&llm.protocol, &llm.options)?;
+            let permit = limiter::acquire(&llm.name, &llm.options).await;
+            let scores = permit.scope(provider.rerank(query, documents)).await?;
+
+            record_usage(
+                &llm,
+                &UsageTag::new("rerank"),
+                ChatUsage {
+                    prompt_tokens: estimate_tokens([query]) + estimate_tokens(documents),
+                    completion_tokens: 0,
+                },
+                true,
+            )
+            .await;
+            if scores.len() != documents.len() {
+                return Err(AiterError::Invalid(format!(
+                    "Expect {} scores but got {}",
+                    documents.len(),
+                    scores.len()
+                )));
+            }
+
+            return Ok(scores);
         }
     }
 
-    Ok(())
+    Err(AiterError::Invalid("No Rerank LLM".to_string()))
+}
+
+/// Bypass the LLM response cache in current process, e.g. to force re-digesting with fresh responses
+pub fn set_cache_bypass(bypass: bool) {
+    LLM_CACHE_BYPASS.store(bypass, Ordering::Relaxed);
 }
 
 pub async fn stream_chat_completion(
@@ -271,47 +464,71 @@ pub async fn stream_chat_completion(
     chat_completion_options: &ChatCompletionOptions,
     chat_llm_name: Option<&str>,
 ) -> AiterResult<ChatCompletionStream> {
-    let name = if chat_llm_name.is_none() {
-        get_actived_name("chat").await?
-    } else {
-        chat_llm_name.map(|s| s.to_string())
-    };
+    stream_chat_completion_with_parts(
+        message,
+        &[],
+        history,
+        chat_completion_options,
+        chat_llm_name,
+    )
+    .await
+}
 
-    if let Some(name) = name {
-        if let Some(LlmEntity {
-            protocol, options, ..
-        }) = get_by_name(&name).await?
-        {
-            if protocol == "openai" {
-                if let (Some(base_url), Some(model)) =
-                    (options.get("base_url"), options.get("model"))
-                {
-                    let api_key = options.get("api_key").map_or("", |v| v);
-
-                    let mut messages = history.to_vec();
-                    messages.push(ChatMessage {
-                        role: Role::User,
-                        content: message.to_string(),
-                        reasoning: None,
-                    });
-
-                    return OpenAiProvider::new(base_url, api_key, model)
-                        .stream_chat_completion(&messages, chat_completion_options)
-                        .await;
-                } else {
-                    for k in ["base_url", "model"] {
-                        if !options.contains_key(k) {
-                            return Err(AiterError::Invalid(format!(
-                                "Missing required option {k}"
-                            )));
-                        }
-                    }
-                }
-            }
+/// Same as `stream_chat_completion`, with parts such as images following the message
+pub async fn stream_chat_completion_with_parts(
+    message: &str,
+    parts: &[ChatContentPart],
+    history: &[ChatMessage],
+    chat_completion_options: &ChatCompletionOptions,
+    chat_llm_name: Option<&str>,
+) -> AiterResult<ChatCompletionStream> {
+    let mut messages = history.to_vec();
+    messages.push(ChatMessage {
+        parts: parts.to_vec(),
+        ..ChatMessage::new(Role::User, message)
+    });
+
+    // Only errors before streaming can fall back, as content may have been delivered to caller after that
+    let llm = get_chat_llm(chat_llm_name).await?;
+    with_fallback(llm, |llm| {
+        let messages = &messages;
+        async move {
+            let chat_completion_options = &chat_completion_options
+                .clone()
+                .with_llm_defaults(&llm.options)?;
+            let provider = make_provider(&llm.protocol, &llm.options)?;
+            let permit = limiter::acquire(&llm.name, &llm.options).await;
+            let stream = permit
+                .scope(provider.stream_chat_completion(messages, chat_completion_options))
+                .await?;
+
+            Ok(track_stream(
+                stream,
+                permit,
+                llm,
+                messages.clone(),
+                chat_completion_options.usage_tag.clone(),
+            ))
+        }
+    })
+    .await
+}
+
+/// Report usage grouped by LLM, AI, purpose, doc or date, the date range is in local YYYY-MM-DD format
+pub async fn usage(
+    group_by: &[LlmUsageGroup],
+    since: Option<&str>,
+    until: Option<&str>,
+) -> AiterResult<Vec<LlmUsageStatsEntity>> {
+    for date in [since, until].into_iter().flatten() {
+        if Date::parse(date, format_description!("[year]-[month]-[day]")).is_err() {
+            return Err(AiterError::Invalid(format!(
+                "Invalid date '{date}', expect YYYY-MM-DD"
+            )));
         }
     }
 
-    Err(AiterError::Invalid("No Chat LLM".to_string()))
+    db::core::llm_usage::stats(group_by, since, until).await
 }
 
 pub async fn stream_test_chat_completion(
@@ -320,41 +537,562 @@ pub async fn stream_test_chat_completion(
     protocol: &str,
     options: &HashMap<String, String>,
 ) -> AiterResult<ChatCompletionStream> {
-    if protocol == "openai" {
-        if let (Some(base_url), Some(model)) = (options.get("base_url"), options.get("model")) {
-            let api_key = options.get("api_key").map_or("", |v| v);
-
-            // If api_key is all *, means it is masked, so read from database
-            let mut api_key_unmask = api_key.to_string();
-            if api_key.trim().trim_matches('*').is_empty() {
-                if let Ok(Some(llm)) = get_by_name(name).await {
-                    if let Some(api_key) = llm.options.get("api_key") {
-                        api_key_unmask = api_key.to_string();
-                    }
+    let mut options = options.clone();
+
+    // If api_key is all *, means it is masked, so read from database
+    if let Some(api_key) = options.get("api_key") {
+        if api_key.trim().trim_matches('*').is_empty() {
+            if let Ok(Some(llm)) = get_by_name(name).await {
+                if let Some(api_key) = llm.options.get("api_key") {
+                    options.insert("api_key".to_string(), api_key.to_string());
                 }
             }
+        }
+    }
 
-            let messages = vec![ChatMessage {
-                role: Role::User,
-                content: prompt.to_string(),
-                reasoning: None,
-            }];
+    let messages = vec![ChatMessage::new(Role::User, prompt)];
 
-            return OpenAiProvider::new(base_url, &api_key_unmask, model)
-                .stream_chat_completion(&messages, &ChatCompletionOptions::default())
-                .await;
+    let provider = make_provider(protocol, &options)?;
+    let permit = limiter::acquire(name, &options).await;
+    let stream = permit
+        .scope(provider.stream_chat_completion(&messages, &ChatCompletionOptions::default()))
+        .await?;
+
+    Ok(hold_permit(stream, permit))
+}
+
+/// Collect the whole stream, returns the message along with the LLM which served it
+async fn complete(
+    llm: LlmEntity,
+    messages: &[ChatMessage],
+    chat_completion_options: &ChatCompletionOptions,
+) -> AiterResult<(ChatMessage, LlmEntity)> {
+    with_fallback(llm, |llm| async move {
+        let message = complete_once(&llm, messages, chat_completion_options).await?;
+        Ok((message, llm))
+    })
+    .await
+}
+
+async fn complete_once(
+    llm: &LlmEntity,
+    messages: &[ChatMessage],
+    chat_completion_options: &ChatCompletionOptions,
+) -> AiterResult<ChatMessage> {
+    let chat_completion_options = &chat_completion_options
+        .clone()
+        .with_llm_defaults(&llm.options)?;
+    let provider = make_provider(&llm.protocol, &llm.options)?;
+    let permit = limiter::acquire(&llm.name, &llm.options).await;
+
+    let mut content = String::new();
+    let mut reasoning_content = String::new();
+    let mut usage: Option<ChatUsage> = None;
+
+    let mut stream = permit
+        .scope(provider.stream_chat_completion(messages, chat_completion_options))
+        .await?;
+    while let Some(event) = stream.next().await {
+        match event {
+            ChatCompletionEvent::Content(delta) => content.push_str(&delta),
+            ChatCompletionEvent::ReasoningContent(delta) => reasoning_content.push_str(&delta),
+            ChatCompletionEvent::Usage(reported) => usage = Some(reported),
+            ChatCompletionEvent::Error(err) => return Err(err),
+            _ => {}
+        }
+    }
+
+    record_stream_usage(
+        llm,
+        &chat_completion_options.usage_tag,
+        messages,
+        usage,
+        &[&content, &reasoning_content],
+    )
+    .await;
+
+    Ok(ChatMessage {
+        content,
+        reasoning: if reasoning_content.is_empty() {
+            None
         } else {
-            for k in ["base_url", "model"] {
-                if !options.contains_key(k) {
-                    return Err(AiterError::Invalid(format!("Missing required option {k}")));
-                }
+            Some(reasoning_content)
+        },
+        ..ChatMessage::new(Role::Bot, "")
+    })
+}
+
+fn estimate_tokens(texts: impl IntoIterator<Item = impl AsRef<str>>) -> u64 {
+    texts
+        .into_iter()
+        .map(|text| to_tokens(text.as_ref(), &CURRENT_TOKENIZER).len() as u64)
+        .sum()
+}
+
+/// The LLM followed by LLMs listed in its comma separated `fallback` option, fallbacks of fallbacks are not included
+async fn get_fallback_chain(llm: LlmEntity) -> AiterResult<Vec<LlmEntity>> {
+    let fallback_names: Vec<String> = llm
+        .options
+        .get("fallback")
+        .map(|v| {
+            v.split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut chain = vec![llm];
+    for name in fallback_names {
+        if chain.iter().any(|llm| llm.name == name) {
+            continue;
+        }
+
+        if let Some(fallback) = get_by_name(&name).await? {
+            chain.push(fallback);
+        } else {
+            log::warn!("Fallback LLM '{name}' not exists");
+        }
+    }
+
+    Ok(chain)
+}
+
+fn get_active_config_key(r#type: &str) -> Option<db::core::config::ConfigKey> {
+    match r#type {
+        "chat" => Some(db::core::config::ConfigKey::ActiveChatLlm),
+        "digest" => Some(db::core::config::ConfigKey::ActiveDigestLlm),
+        "embedding" => Some(db::core::config::ConfigKey::ActiveEmbeddingLlm),
+        "reasoning" => Some(db::core::config::ConfigKey::ActiveReasoningLlm),
+        "rerank" => Some(db::core::config::ConfigKey::ActiveRerankLlm),
+        _ => None,
+    }
+}
+
+async fn get_chat_llm(chat_llm_name: Option<&str>) -> AiterResult<LlmEntity> {
+    let name = if chat_llm_name.is_none() {
+        get_actived_name("chat").await?
+    } else {
+        chat_llm_name.map(|s| s.to_string())
+    };
+
+    if let Some(name) = name {
+        if let Some(llm) = get_by_name(&name).await? {
+            return Ok(llm);
+        }
+    }
+
+    Err(AiterError::Invalid("No Chat LLM".to_string()))
+}
+
+/// Connection errors, rate limits and server errors are worth retrying with another LLM
+fn is_fallback_error(err: &AiterError) -> bool {
+    match err {
+        AiterError::HttpRequestError(_) => true,
+        AiterError::HttpStatusError(message) => message
+            .split_whitespace()
+            .next()
+            .and_then(|code| code.parse::<u16>().ok())
+            .is_some_and(|code| code == 429 || code >= 500),
+        _ => false,
+    }
+}
+
+/// Forward the stream while holding the permit until it ends
+fn hold_permit(mut stream: ChatCompletionStream, permit: LlmPermit) -> ChatCompletionStream {
+    let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER_DEFAULT);
+    let held_stream = ChatCompletionStream::new(receiver);
+    let cancel_token = held_stream.cancel_token();
+
+    tokio::spawn(async move {
+        let _permit = permit;
+
+        while let Some(Some(event)) = cancel_token.run_until_cancelled(stream.next()).await {
+            if sender.send(event).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    held_stream
+}
+
+/// Only responses of stable temperature are reproducible enough to be reused
+fn is_cacheable(chat_completion_options: &ChatCompletionOptions, bypass: bool) -> bool {
+    chat_completion_options.temperature <= LLM_CHAT_TEMPERATURE_STABLE && !bypass
+}
+
+fn hash_prompt(messages: &[ChatMessage], options: &ChatCompletionOptions) -> String {
+    let mut prompt = json!({
+        "messages": messages.iter().map(|m| {
+            let mut message = json!({
+                "role": m.role.to_string(),
+                "content": m.content,
+            });
+            // Only present with parts, so that existing cached responses still match
+            if !m.parts.is_empty() {
+                message["parts"] = m.parts.iter().map(|part| part.to_key_value()).collect();
             }
+            message
+        }).collect::<Vec<_>>(),
+        "enable_think": options.enable_think,
+    });
+    if let Some(response_format) = &options.response_format {
+        prompt["response_format"] = response_format.schema.clone();
+    }
+    let sampling = options.sampling_key_value();
+    if !sampling.is_empty() {
+        prompt["sampling"] = sampling.into();
+    }
+
+    sha256(prompt.to_string().as_bytes())
+}
+
+async fn record_stream_usage(
+    llm: &LlmEntity,
+    usage_tag: &UsageTag,
+    messages: &[ChatMessage],
+    usage: Option<ChatUsage>,
+    completions: &[&str],
+) {
+    match usage {
+        Some(usage) => record_usage(llm, usage_tag, usage, false).await,
+        None => {
+            let usage = ChatUsage {
+                prompt_tokens: estimate_tokens(messages.iter().map(|m| &m.content)),
+                completion_tokens: estimate_tokens(completions),
+            };
+            record_usage(llm, usage_tag, usage, true).await
         }
+    }
+}
+
+/// Save usage with cost calculated by `prompt_token_price` and `completion_token_price` options, errors are only logged
+async fn record_usage(llm: &LlmEntity, usage_tag: &UsageTag, usage: ChatUsage, estimated: bool) {
+    let get_price = |key: &str| llm.options.get(key).and_then(|v| v.parse::<f64>().ok());
+    let prompt_token_price = get_price("prompt_token_price");
+    let completion_token_price = get_price("completion_token_price");
+    let cost = if prompt_token_price.is_some() || completion_token_price.is_some() {
+        Some(
+            usage.prompt_tokens as f64 * prompt_token_price.unwrap_or(0.0)
+                + usage.completion_tokens as f64 * completion_token_price.unwrap_or(0.0),
+        )
     } else {
-        return Err(AiterError::Invalid(format!(
+        None
+    };
+
+    let ai = usage_tag
+        .mem_path
+        .as_deref()
+        .and_then(get_ai_id_by_mem_path);
+
+    if let Err(err) = db::core::llm_usage::insert(
+        &llm.name,
+        llm.options.get("model").map_or("", |v| v),
+        ai.as_deref(),
+        &usage_tag.purpose,
+        usage_tag.doc_id.as_deref(),
+        usage.prompt_tokens,
+        usage.completion_tokens,
+        estimated,
+        cost,
+    )
+    .await
+    {
+        log::warn!("Save LLM usage error: {err}");
+    }
+}
+
+/// Forward the stream and record usage when it ends, the permit is held until then
+fn track_stream(
+    mut stream: ChatCompletionStream,
+    permit: LlmPermit,
+    llm: LlmEntity,
+    messages: Vec<ChatMessage>,
+    usage_tag: UsageTag,
+) -> ChatCompletionStream {
+    let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER_DEFAULT);
+    let tracked_stream = ChatCompletionStream::new(receiver).with_llm_name(&llm.name);
+    let cancel_token = tracked_stream.cancel_token();
+
+    tokio::spawn(async move {
+        let _permit = permit;
+
+        let mut completion = String::new();
+        let mut usage: Option<ChatUsage> = None;
+
+        // The source stream is dropped on cancellation, which cancels its producer in turn
+        while let Some(Some(event)) = cancel_token.run_until_cancelled(stream.next()).await {
+            match &event {
+                ChatCompletionEvent::Content(delta)
+                | ChatCompletionEvent::ReasoningContent(delta) => completion.push_str(delta),
+                ChatCompletionEvent::Usage(reported) => usage = Some(*reported),
+                _ => {}
+            }
+
+            // Stop forwarding if receiver dropped, the tokens consumed so far are still recorded
+            if sender.send(event).await.is_err() {
+                break;
+            }
+        }
+
+        drop(stream);
+
+        record_stream_usage(&llm, &usage_tag, &messages, usage, &[&completion]).await;
+    });
+
+    tracked_stream
+}
+
+/// Run the request with the LLM, then with its fallbacks in order while the error is eligible for fallback
+async fn with_fallback<T, F, Fut>(llm: LlmEntity, mut request: F) -> AiterResult<T>
+where
+    F: FnMut(LlmEntity) -> Fut,
+    Fut: Future<Output = AiterResult<T>>,
+{
+    let mut chain = get_fallback_chain(llm).await?.into_iter().peekable();
+    let mut result = Err(AiterError::Invalid("Fallback chain is empty".to_string()));
+    while let Some(llm) = chain.next() {
+        let name = llm.name.clone();
+        result = request(llm).await;
+        match &result {
+            Err(err) if is_fallback_error(err) && chain.peek().is_some() => {
+                log::warn!("LLM '{name}' failed, fall back to the next one: {err}");
+            }
+            _ => break,
+        }
+    }
+
+    result
+}
+
+fn make_provider(protocol: &str, options: &HashMap<String, String>) -> AiterResult<LlmProvider> {
+    let api_key = options.get("api_key").map_or("", |v| v);
+    let max_retry = options
+        .get("max_retry")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(LLM_MAX_RETRY_DEFAULT);
+    let timeout = options
+        .get("timeout")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(LLM_TIMEOUT_SECS_DEFAULT);
+
+    match protocol {
+        "anthropic" => {
+            let base_url = options
+                .get("base_url")
+                .map_or(ANTHROPIC_BASE_URL_DEFAULT, |v| v);
+            let model = get_required_option(options, "model")?;
+
+            let mut provider = AnthropicProvider::new(base_url, api_key, model)
+                .with_max_retry(max_retry)
+                .with_timeout(timeout);
+            if let Some(max_tokens) = options.get("max_tokens").and_then(|v| v.parse().ok()) {
+                provider = provider.with_max_tokens(max_tokens);
+            }
+            if let Some(thinking_budget) =
+                options.get("thinking_budget").and_then(|v| v.parse().ok())
+            {
+                provider = provider.with_thinking_budget(thinking_budget);
+            }
+
+            Ok(LlmProvider::Anthropic(provider))
+        }
+        "ollama" => {
+            let base_url = options
+                .get("base_url")
+                .map_or(OLLAMA_BASE_URL_DEFAULT, |v| v);
+            let model = get_required_option(options, "model")?;
+
+            Ok(LlmProvider::Ollama(
+                OllamaProvider::new(base_url, api_key, model)
+                    .with_max_retry(max_retry)
+                    .with_timeout(timeout),
+            ))
+        }
+        "openai" => {
+            let base_url = get_required_option(options, "base_url")?;
+            let model = get_required_option(options, "model")?;
+
+            Ok(LlmProvider::OpenAi(
+                OpenAiProvider::new(base_url, api_key, model)
+                    .with_max_retry(max_retry)
+                    .with_timeout(timeout),
+            ))
+        }
+        "record" => {
+            let cassette = get_required_option(options, "cassette")?;
+            let target = get_required_option(options, "target")?;
+            if target == "record" {
+                return Err(AiterError::Invalid(
+                    "Record target can not be a record provider".to_string(),
+                ));
+            }
+
+            Ok(LlmProvider::Record(RecordProvider::new(
+                Path::new(cassette),
+                make_provider(target, options)?.try_into()?,
+            )))
+        }
+        "replay" => {
+            let cassette = get_required_option(options, "cassette")?;
+
+            Ok(LlmProvider::Replay(ReplayProvider::new(Path::new(
+                cassette,
+            ))))
+        }
+        _ => Err(AiterError::Invalid(format!(
             "Unsupported protocol '{protocol}'"
+        ))),
+    }
+}
+
+fn get_required_option<'a>(
+    options: &'a HashMap<String, String>,
+    key: &str,
+) -> AiterResult<&'a str> {
+    options
+        .get(key)
+        .map(|v| v.as_str())
+        .ok_or(AiterError::Invalid(format!(
+            "Missing required option {key}"
+        )))
+}
+
+#[cfg(test)]
+mod tests {
+    use ulid::Ulid;
+
+    use super::*;
+    use crate::db::ensure_test_tables;
+
+    async fn add_llm(name: &str, fallback: &str) {
+        db::core::llm::upsert(
+            name,
+            "chat",
+            "openai",
+            &HashMap::from([("fallback".to_string(), fallback.to_string())]),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn test_is_cacheable() {
+        let stable = ChatCompletionOptions::default().with_temperature(LLM_CHAT_TEMPERATURE_STABLE);
+        assert!(is_cacheable(&stable, false));
+        assert!(!is_cacheable(&stable, true));
+
+        let unstable = ChatCompletionOptions::default().with_temperature(0.6);
+        assert!(!is_cacheable(&unstable, false));
+        assert!(!is_cacheable(&unstable, true));
+    }
+
+    #[tokio::test]
+    async fn test_is_fallback_error() {
+        let status_error = |status: &str| AiterError::HttpStatusError(status.to_string());
+        assert!(is_fallback_error(&status_error("429 Too Many Requests")));
+        assert!(is_fallback_error(&status_error(
+            "500 Internal Server Error"
+        )));
+        assert!(is_fallback_error(&status_error(
+            "503 Service Unavailable {}"
+        )));
+        assert!(!is_fallback_error(&status_error("400 Bad Request")));
+        assert!(!is_fallback_error(&status_error("404 Not Found")));
+        assert!(!is_fallback_error(&status_error("Unknown")));
+        assert!(!is_fallback_error(&AiterError::Invalid("Bad".to_string())));
+
+        // Nothing listens on port 1, so the connection is refused
+        let connection_error = reqwest::get("http://127.0.0.1:1").await.unwrap_err();
+        assert!(is_fallback_error(&AiterError::HttpRequestError(
+            connection_error
         )));
     }
 
-    Err(AiterError::Invalid("No Chat LLM".to_string()))
+    #[tokio::test]
+    async fn test_get_fallback_chain() {
+        ensure_test_tables().await;
+
+        let [a, b, c] = [(); 3].map(|_| Ulid::new().to_string());
+        add_llm(&a, &format!(" {b}, {a},missing, {b} ,,")).await;
+        add_llm(&b, &c).await; // Fallbacks of fallbacks are not included
+        add_llm(&c, "").await;
+
+        let chain = get_fallback_chain(get_by_name(&a).await.unwrap().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            chain
+                .iter()
+                .map(|llm| llm.name.as_str())
+                .collect::<Vec<_>>(),
+            vec![a.as_str(), b.as_str()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_with_fallback() {
+        ensure_test_tables().await;
+
+        let [a, b, c] = [(); 3].map(|_| Ulid::new().to_string());
+        add_llm(&a, &format!("{b},{c}")).await;
+        add_llm(&b, "").await;
+        add_llm(&c, "").await;
+        let llm = get_by_name(&a).await.unwrap().unwrap();
+
+        // Run the request with each LLM in order, failing with the error given for its name
+        let run = |errors: HashMap<String, AiterError>| {
+            let llm = llm.clone();
+            async move {
+                let mut requested: Vec<String> = vec![];
+                let result = with_fallback(llm, |llm| {
+                    requested.push(llm.name.clone());
+                    let result = match errors.get(&llm.name) {
+                        Some(AiterError::HttpStatusError(status)) => {
+                            Err(AiterError::HttpStatusError(status.clone()))
+                        }
+                        Some(_) => Err(AiterError::Invalid("Bad".to_string())),
+                        None => Ok(llm.name),
+                    };
+                    async move { result }
+                })
+                .await;
+                (result, requested)
+            }
+        };
+        let status_error = |status: &str| AiterError::HttpStatusError(status.to_string());
+
+        let (result, requested) = run(HashMap::new()).await;
+        assert_eq!(result.unwrap(), a);
+        assert_eq!(requested, vec![a.clone()]);
+
+        let (result, requested) = run(HashMap::from([
+            (a.clone(), status_error("429 Too Many Requests")),
+            (b.clone(), status_error("502 Bad Gateway")),
+        ]))
+        .await;
+        assert_eq!(result.unwrap(), c);
+        assert_eq!(requested, vec![a.clone(), b.clone(), c.clone()]);
+
+        let (result, requested) = run(HashMap::from([(
+            a.clone(),
+            status_error("400 Bad Request"),
+        )]))
+        .await;
+        assert!(matches!(result, Err(AiterError::HttpStatusError(_))));
+        assert_eq!(requested, vec![a.clone()]);
+
+        // The error of the last LLM is returned when all fail
+        let (result, requested) = run(HashMap::from([
+            (a.clone(), status_error("500 Internal Server Error")),
+            (b.clone(), status_error("500 Internal Server Error")),
+            (c.clone(), status_error("503 Service Unavailable")),
+        ]))
+        .await;
+        assert!(
+            matches!(result, Err(AiterError::HttpStatusError(status)) if status.starts_with("503"))
+        );
+        assert_eq!(requested, vec![a, b, c]);
+    }
 }
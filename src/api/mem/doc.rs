@@ -1,17 +1,19 @@
 use std::{
-    fs::{copy, remove_file},
+    fs::{copy, create_dir_all, remove_file},
     path::{Path, PathBuf},
 };
 
 use tokio::sync::{mpsc::Sender, oneshot};
 
 use crate::{
-    api::{get_docs_dir_path, get_mem_path},
+    api::{get_docs_dir_path, get_docs_dir_path_by_mem_path, get_mem_path},
     content,
     content::doc::{DocContent, DocContentType, sheet::SheetDoc},
     db,
     db::mem::MemWriteEvent,
     error::AiterResult,
+    tool::spatial::GeoLayer,
+    utils::geo::is_geojson_file,
 };
 
 pub type DocEntity = db::mem::doc::DocEntity;
@@ -140,7 +142,11 @@ pub async fn pull(
     if let Some(doc) = &doc {
         let keep_path = get_docs_dir_path(ai_name).await?.join(doc_id);
         if keep_path.exists() {
+            // Source may be a relative path of a directory read recursively
             let dest_path = dest_dir_path.join(&doc.source);
+            if let Some(parent) = dest_path.parent() {
+                create_dir_all(parent)?;
+            }
             copy(&keep_path, &dest_path)?;
 
             Ok(Some(dest_path))
@@ -162,3 +168,91 @@ pub async fn reset_not_digested_but_started(ai_name: Option<&str>) -> AiterResul
 
     Ok(())
 }
+
+/// Sheet docs whose kept original is GeoJSON, which can be looked up spatially whatever the source is
+pub(crate) async fn list_geo_layers_by_mem_path(mem_path: &Path) -> AiterResult<Vec<GeoLayer>> {
+    let Some(docs_path) = get_docs_dir_path_by_mem_path(mem_path) else {
+        return Ok(vec![]);
+    };
+
+    Ok(
+        db::mem::doc::list_by_content_type(mem_path, &DocContentType::Sheet.to_string())
+            .await?
+            .into_iter()
+            .filter_map(|doc| {
+                let path = docs_path.join(&doc.id);
+                is_geojson_file(&path).then(|| GeoLayer {
+                    context: doc.get_context(),
+                    source: doc.source,
+                    path,
+                })
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use ulid::Ulid;
+
+    use super::*;
+    use crate::{
+        DB_DEFAULT_MEM_PATH,
+        api::{learn::read_doc, mem::spawn_mem_write},
+        db::ensure_test_tables,
+    };
+
+    #[tokio::test]
+    async fn test_list_geo_layers() {
+        ensure_test_tables().await;
+
+        // Neither the source nor the path tells the format
+        let geojson_path = std::env::temp_dir().join(format!("aiter-{}", Ulid::new()));
+        write(
+            &geojson_path,
+            r#"{"type": "FeatureCollection", "features": [{"type": "Feature", "geometry": {"type": "Point", "coordinates": [121.5, 31.2]}, "properties": {"name": "A"}}]}"#,
+        )
+        .unwrap();
+        let geojson_source = format!("https://example.com/layers/{}", Ulid::new());
+        read_doc(
+            None,
+            &geojson_path,
+            Some(&geojson_source),
+            Some("geojson"),
+            false,
+            spawn_mem_write(None).await.unwrap(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let csv_path = std::env::temp_dir().join(format!("aiter-{}.csv", Ulid::new()));
+        write(&csv_path, format!("name\n{}\n", Ulid::new())).unwrap();
+        let csv_source = format!("{}.csv", Ulid::new());
+        read_doc(
+            None,
+            &csv_path,
+            Some(&csv_source),
+            None,
+            true,
+            spawn_mem_write(None).await.unwrap(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let sources: Vec<String> = list_geo_layers_by_mem_path(&DB_DEFAULT_MEM_PATH)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|layer| layer.source)
+            .collect();
+        assert!(sources.contains(&geojson_source));
+        assert!(!sources.contains(&csv_source));
+
+        remove_file(geojson_path).unwrap();
+        remove_file(csv_path).unwrap();
+    }
+}
@@ -2,7 +2,10 @@ use std::collections::HashMap;
 
 use tokio::sync::{mpsc::Sender, oneshot};
 
-use crate::{AiterError, api, api::get_mem_path, db, db::mem::MemWriteEvent, error::AiterResult};
+use crate::{
+    AiterError, api, api::get_mem_path, db, db::mem::MemWriteEvent, error::AiterResult,
+    learn::embed::embed_mem_silently,
+};
 
 pub type SkillEntity = db::mem::skill::SkillEntity;
 
@@ -30,6 +33,8 @@ pub async fn add(
             let _ = resp_receiver.await?;
         }
 
+        embed_mem_silently(&mem_path, mem_write_event_sender).await;
+
         db::mem::skill::get(&mem_path, &skill.id).await
     } else {
         Err(AiterError::NotExists(format!(
@@ -67,6 +72,8 @@ pub async fn adds(
         }
     }
 
+    embed_mem_silently(&mem_path, mem_write_event_sender).await;
+
     Ok(skills)
 }
 
@@ -0,0 +1,116 @@
+use std::{
+    fs::{create_dir_all, remove_file, write},
+    path::{Path, PathBuf},
+};
+
+use tabled::Tabled;
+
+use crate::{
+    api::get_mem_path,
+    db,
+    error::{AiterError, AiterResult},
+    llm::prompt,
+};
+
+#[derive(Tabled)]
+pub struct PromptTemplate {
+    #[tabled(rename = "Name")]
+    pub name: String,
+
+    #[tabled(rename = "Overridden")]
+    pub overridden: bool,
+}
+
+/// Copy the built-in template to the override file if not exists yet, the path is returned for editing
+pub async fn edit(name: &str, lang: &str) -> AiterResult<PathBuf> {
+    let builtin = get_builtin(name, lang)?;
+
+    let path = prompt::get_template_override_path(lang, name);
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        write(&path, builtin)?;
+    }
+
+    Ok(path)
+}
+
+/// Language of prompts used by the AI, the global one is used if the AI has not been set
+pub async fn get_lang(ai_name: Option<&str>) -> AiterResult<String> {
+    let mem_path = get_mem_path(ai_name).await?;
+    get_lang_by_mem_path(&mem_path).await
+}
+
+pub async fn list(lang: &str) -> AiterResult<Vec<PromptTemplate>> {
+    check_lang(lang)?;
+
+    Ok(prompt::list_template_names()
+        .into_iter()
+        .map(|name| PromptTemplate {
+            name: name.to_string(),
+            overridden: prompt::get_template_override_path(lang, name).exists(),
+        })
+        .collect())
+}
+
+/// Remove the override file, returns false if the template has not been overridden
+pub async fn reset(name: &str, lang: &str) -> AiterResult<bool> {
+    get_builtin(name, lang)?;
+
+    let path = prompt::get_template_override_path(lang, name);
+    if path.exists() {
+        remove_file(&path)?;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// Set language of prompts for the AI, or globally if `global` is true
+pub async fn set_lang(ai_name: Option<&str>, lang: &str, global: bool) -> AiterResult<()> {
+    check_lang(lang)?;
+
+    if global {
+        db::core::config::set(&db::core::config::ConfigKey::PromptLang, lang).await
+    } else {
+        let mem_path = get_mem_path(ai_name).await?;
+        db::mem::meta::set_prompt_lang(&mem_path, lang).await
+    }
+}
+
+pub async fn show(name: &str, lang: &str) -> AiterResult<String> {
+    get_builtin(name, lang)?;
+
+    Ok(prompt::get_template(lang, name))
+}
+
+pub(crate) async fn get_lang_by_mem_path(mem_path: &Path) -> AiterResult<String> {
+    if let Some(lang) = db::mem::meta::get_prompt_lang(mem_path).await? {
+        return Ok(lang);
+    }
+
+    if let Some(lang) = db::core::config::get(&db::core::config::ConfigKey::PromptLang).await? {
+        return Ok(lang);
+    }
+
+    Ok(prompt::PROMPT_LANG_DEFAULT.to_string())
+}
+
+fn check_lang(lang: &str) -> AiterResult<()> {
+    if prompt::is_lang_valid(lang) {
+        Ok(())
+    } else {
+        Err(AiterError::Invalid(format!(
+            "Prompt language '{lang}' is invalid, supported languages: {}",
+            prompt::PROMPT_LANGS.join("/")
+        )))
+    }
+}
+
+fn get_builtin(name: &str, lang: &str) -> AiterResult<&'static str> {
+    check_lang(lang)?;
+
+    prompt::get_builtin_template(lang, name)
+        .ok_or(AiterError::NotExists(format!("Prompt '{name}' not exists")))
+}
@@ -1,5 +1,7 @@
 use std::{collections::HashMap, str::FromStr};
 
+use serde_json::{Map, Value};
+
 use crate::{
     AiterError, VecOptions,
     api::get_mem_path,
@@ -8,8 +10,10 @@ use crate::{
     tool::{
         ToolType,
         ahp::{parse_ahp, run_ahp},
+        chat_function_from_tool,
         mcp::{parse_mcp, run_mcp},
     },
+    utils::json::string_to_json_value,
 };
 
 pub type Tool = db::core::tool::Tool;
@@ -136,7 +140,8 @@ pub async fn parse(
     }
 }
 
-pub async fn run(tool_id: &str, options: &HashMap<String, String>) -> AiterResult<String> {
+/// Run the tool with arguments conforming to its JSON Schema, as called by LLM
+pub async fn call(tool_id: &str, arguments: &Map<String, Value>) -> AiterResult<String> {
     let tool = db::core::tool::get(tool_id)
         .await?
         .ok_or(AiterError::NotExists(format!(
@@ -144,7 +149,32 @@ pub async fn run(tool_id: &str, options: &HashMap<String, String>) -> AiterResul
         )))?;
 
     match ToolType::from_str(&tool.r#type)? {
-        ToolType::Ahp => run_ahp(&tool, options).await,
-        ToolType::Mcp => run_mcp(&tool, options).await,
+        ToolType::Ahp => run_ahp(&tool, arguments).await,
+        ToolType::Mcp => run_mcp(&tool, arguments).await,
+    }
+}
+
+/// Run the tool with string options, which are converted by types declared in its JSON Schema
+pub async fn run(tool_id: &str, options: &HashMap<String, String>) -> AiterResult<String> {
+    let tool = db::core::tool::get(tool_id)
+        .await?
+        .ok_or(AiterError::NotExists(format!(
+            "Tool '{tool_id}' not exists"
+        )))?;
+
+    let parameters = chat_function_from_tool(&tool)?.parameters;
+
+    let mut arguments: Map<String, Value> = Map::new();
+    for (k, v) in options {
+        if let Some(property) = parameters["properties"].get(k) {
+            let value = if let Some(r#type) = property["type"].as_str() {
+                string_to_json_value(v, r#type)
+            } else {
+                serde_json::from_str(v).unwrap_or(Value::String(v.to_string()))
+            };
+            arguments.insert(k.to_string(), value);
+        }
     }
+
+    call(tool_id, &arguments).await
 }
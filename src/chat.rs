@@ -1,7 +1,7 @@
 use std::{
     collections::{HashMap, HashSet},
     path::Path,
-    str::FromStr,
+    sync::Arc,
 };
 
 use serde::{Deserialize, Serialize};
@@ -10,36 +10,54 @@ use tokio::{
     sync::{mpsc, mpsc::Sender, oneshot},
     task::JoinHandle,
 };
+use tokio_util::sync::CancellationToken;
 use ulid::Ulid;
 
 use crate::{
-    CHANNEL_BUFFER_DEFAULT, LLM_CHAT_TEMPERATURE_STABLE, VecOptions, api, db,
+    CHANNEL_BUFFER_DEFAULT, CHAT_TOOL_STEPS_DEFAULT, CURRENT_TOKENIZER,
+    LLM_CHAT_TEMPERATURE_STABLE, RERANK_TOKENS_BUDGET, RERANK_TOP_K, TRUNCATE_LOG_MESSAGE,
+    VecOptions, api, db,
     db::mem::MemWriteEvent,
-    error::AiterResult,
+    error::{AiterError, AiterResult},
     llm::{
-        ChatCompletionEvent, ChatCompletionOptions, ChatCompletionStream, ChatFunction,
-        ChatMessage, Role,
+        ChatCompletionEvent, ChatCompletionOptions, ChatCompletionStream, ChatContentPart,
+        ChatFunction, ChatMessage, Role, UsageTag,
         prompt::{
             generate::{make_answer_by_candidates_prompt, make_no_answer_prompt},
-            intent::{make_extract_queries_prompt, make_simplify_queries_prompt},
+            intent::{
+                ExtractQueriesResponse, make_extract_queries_prompt,
+                make_extract_queries_response_format, make_simplify_queries_prompt,
+            },
         },
     },
     retrieve::{
         RetrieveMethod,
         doc::{retrieve_doc_frag, retrieve_doc_implicit, retrieve_doc_knl},
+        rerank::rerank_candidates,
         skill::retrieve_skill,
     },
-    tool::{ToolType, ahp::chat_function_from_ahp, mcp::chat_function_from_mcp},
-    utils::{datetime::now_iso_datetime_string, markdown::extract_code_block},
+    tool::{
+        chat_function_from_tool,
+        spatial::{GeoLayer, SPATIAL_FUNCTION_NAME, chat_function_for_spatial, run_spatial},
+    },
+    utils::{
+        datetime::now_iso_datetime_string,
+        json::json_value_to_string,
+        markdown::extract_code_block,
+        text::{to_tokens, truncate_format},
+    },
 };
 
 #[derive(Default)]
 pub struct ChatOptions {
+    pub attachments: Vec<ChatContentPart>, // Sent to the LLM along with the question, such as images
+    pub cancel_token: CancellationToken, // Cancels the answer stream, also cancelled when it is dropped
     pub deep: bool,
     pub exchange: Option<String>,
     pub llm_for_chat: Option<String>,
     pub llm_for_reasoning: Option<String>,
     pub llm_options: Vec<String>,
+    pub max_tool_steps: Option<usize>,
     pub retrace: u64,
     pub session: Option<String>,
     pub strict: bool,
@@ -61,6 +79,12 @@ pub async fn stream_chat(
     chat_history: &[ChatMessage],
     mem_write_event_sender: Sender<MemWriteEvent>,
 ) -> AiterResult<ChatCompletionStream> {
+    // Validate options before any work
+    let chat_completion_options = ChatCompletionOptions::default()
+        .with_enable_think(chat_options.deep)
+        .with_llm_options(&VecOptions(&chat_options.llm_options).into_map())?
+        .with_usage_tag(UsageTag::new("answer").with_mem_path(mem_path));
+
     let history_questions = chat_history
         .iter()
         .filter(|m| m.role == Role::User)
@@ -70,20 +94,25 @@ pub async fn stream_chat(
     let mut related_queries: HashSet<String> = HashSet::new();
     let mut candidates: HashSet<String> = HashSet::new();
 
+    let lang = api::prompt::get_lang_by_mem_path(mem_path).await?;
+
     // Extract queries from user's question
     {
-        let prompt = make_extract_queries_prompt(question, &history_questions);
+        let prompt = make_extract_queries_prompt(question, &history_questions, &lang);
         let json_text = extract_code_block(
             &api::llm::chat_completion(
                 &prompt,
                 &[],
-                &ChatCompletionOptions::default().with_temperature(LLM_CHAT_TEMPERATURE_STABLE),
+                &ChatCompletionOptions::default()
+                    .with_temperature(LLM_CHAT_TEMPERATURE_STABLE)
+                    .with_response_format(make_extract_queries_response_format())
+                    .with_usage_tag(UsageTag::new("extract-queries").with_mem_path(mem_path)),
                 chat_options.llm_for_chat.as_deref(),
             )
             .await?
             .content,
         );
-        if let Ok(queries) = serde_json::from_str::<Vec<String>>(&json_text) {
+        if let Ok(ExtractQueriesResponse { queries }) = serde_json::from_str(&json_text) {
             if !queries.is_empty() {
                 log::debug!("Question [{}] need queries: {:?}", &question, &queries);
                 related_queries.extend(queries);
@@ -112,12 +141,14 @@ pub async fn stream_chat(
                 .chain(std::iter::once(question.to_string()))
                 .collect();
 
-        let prompt = make_simplify_queries_prompt(&not_simplify_queries);
+        let prompt = make_simplify_queries_prompt(&not_simplify_queries, &lang);
         let json_text = extract_code_block(
             &api::llm::chat_completion(
                 &prompt,
                 &[],
-                &ChatCompletionOptions::default().with_temperature(LLM_CHAT_TEMPERATURE_STABLE),
+                &ChatCompletionOptions::default()
+                    .with_temperature(LLM_CHAT_TEMPERATURE_STABLE)
+                    .with_usage_tag(UsageTag::new("simplify-queries").with_mem_path(mem_path)),
                 chat_options.llm_for_chat.as_deref(),
             )
             .await?
@@ -164,6 +195,23 @@ pub async fn stream_chat(
         candidates.extend(contents);
     }
 
+    // Retrieve contents by embedding match if an embedding LLM is active
+    let embedding_method = embed_questions(mem_path, question, &related_queries_vec).await;
+    if let Some(method) = &embedding_method {
+        match retrieve_contents(
+            method,
+            mem_path,
+            question,
+            &related_queries_vec,
+            chat_options.deep,
+        )
+        .await
+        {
+            Ok(contents) => candidates.extend(contents),
+            Err(err) => log::warn!("Retrieve contents by embedding error: {err}"),
+        }
+    }
+
     // Retrieve skills
     let mut skill_retrievers: Vec<JoinHandle<AiterResult<Vec<db::mem::skill::SkillEntity>>>> =
         vec![];
@@ -185,6 +233,22 @@ pub async fn stream_chat(
         }));
     }
 
+    if let Some(method) = embedding_method {
+        let mem_path = mem_path.to_path_buf();
+        let question = question.to_string();
+        let related_queries_vec = related_queries_vec.clone();
+        let deep = chat_options.deep;
+        skill_retrievers.push(tokio::spawn(async move {
+            match retrieve_skill(&method, &mem_path, &question, &related_queries_vec, deep).await {
+                Ok(skills) => Ok(skills),
+                Err(err) => {
+                    log::warn!("Retrieve skills by embedding error: {err}");
+                    Ok(vec![])
+                }
+            }
+        }));
+    }
+
     let mut skills_map: HashMap<String, db::mem::skill::SkillEntity> = HashMap::new();
     for handle in skill_retrievers {
         for skill in handle.await?? {
@@ -192,21 +256,26 @@ pub async fn stream_chat(
         }
     }
 
+    // GeoJSON layers can be looked up by the built-in spatial skill, only if they are relevant to the question
+    let geo_layers: Vec<GeoLayer> = match api::mem::doc::list_geo_layers_by_mem_path(mem_path).await
+    {
+        Ok(geo_layers) => geo_layers
+            .into_iter()
+            .filter(|layer| {
+                is_geo_layer_relevant(layer, question, &related_queries_vec, &candidates)
+            })
+            .collect(),
+        Err(err) => {
+            log::warn!("List geo layers error: {err}");
+            vec![]
+        }
+    };
+
     // Prepare result stream
     let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER_DEFAULT);
-    let stream = ChatCompletionStream::new(receiver);
-
-    let mut chat_completion_options = ChatCompletionOptions::default();
-    if chat_options.deep {
-        chat_completion_options = chat_completion_options.with_enable_think(true);
-    }
-
-    let llm_options = VecOptions(&chat_options.llm_options);
-    if let Some(temperature_str) = llm_options.get("temperature") {
-        if let Ok(temperature) = temperature_str.parse() {
-            chat_completion_options = chat_completion_options.with_temperature(temperature);
-        }
-    }
+    let stream =
+        ChatCompletionStream::new(receiver).with_cancel_token(chat_options.cancel_token.clone());
+    let cancel_token = stream.cancel_token();
 
     let llm_for_chat: Option<String> = if chat_options.deep {
         if chat_options.llm_for_reasoning.is_some() {
@@ -218,10 +287,15 @@ pub async fn stream_chat(
         chat_options.llm_for_chat.clone()
     };
 
+    let llm_for_rerank = chat_options.llm_for_chat.clone();
+    let mem_path = mem_path.to_path_buf();
     let question = question.to_string();
     let chat_history = chat_history.to_vec();
-    let history_questions = history_questions.clone();
     let strict = chat_options.strict;
+    let attachments = chat_options.attachments.clone();
+    let max_tool_steps = chat_options
+        .max_tool_steps
+        .unwrap_or(CHAT_TOOL_STEPS_DEFAULT);
 
     tokio::spawn(async move {
         let mut call_tool_end_tasks: Vec<(ChatCallToolTask, String, String)> = vec![];
@@ -229,15 +303,25 @@ pub async fn stream_chat(
 
         // Invoke skills
         let skills = skills_map.values().cloned().collect::<Vec<_>>();
-        if !skills.is_empty() {
-            log::debug!("Skills: {skills:?}");
+        if !skills.is_empty() || !geo_layers.is_empty() {
+            log::debug!("Skills: {skills:?}, geo layers: {geo_layers:?}");
 
-            if let Ok(mut call_tool_stream) =
-                stream_invoke_skills(&skills, &question, &chat_history, llm_for_chat.as_deref())
-                    .await
+            if let Ok(mut call_tool_stream) = stream_invoke_skills(
+                &mem_path,
+                &skills,
+                &geo_layers,
+                &question,
+                &chat_history,
+                llm_for_chat.as_deref(),
+                max_tool_steps,
+            )
+            .await
             {
                 let mut tasks_map: HashMap<String, ChatCallToolTask> = HashMap::new();
-                while let Some(event) = call_tool_stream.next().await {
+                while let Some(Some(event)) = cancel_token
+                    .run_until_cancelled(call_tool_stream.next())
+                    .await
+                {
                     match event {
                         ChatCompletionEvent::CallToolStart(ref task) => {
                             tasks_map.insert(task.id.clone(), task.clone());
@@ -283,17 +367,74 @@ pub async fn stream_chat(
 
         log::debug!("Candidates: {candidates:?}");
 
+        // Rerank candidates, keep the most relevant ones within tokens budget
+        let candidates: Vec<String> = candidates.into_iter().collect();
+        let candidates = match cancel_token
+            .run_until_cancelled(rerank_candidates(
+                &mem_path,
+                &question,
+                &candidates,
+                RERANK_TOP_K,
+                RERANK_TOKENS_BUDGET,
+                llm_for_rerank.as_deref(),
+                &lang,
+            ))
+            .await
+        {
+            Some(Ok(reranked)) => reranked,
+            Some(Err(err)) => {
+                log::warn!("Rerank candidates error: {err}");
+                candidates
+            }
+            None => candidates,
+        };
+
+        // Pack history and candidates into the context window of the LLM
+        let (chat_history, candidates) =
+            match api::llm::get_prompt_tokens_budget(llm_for_chat.as_deref()).await {
+                Ok(Some(budget)) => {
+                    let with_candidates = !candidates.is_empty();
+                    let fixed_prompt = if with_candidates {
+                        make_answer_by_candidates_prompt(&question, &[], &[], strict, &lang)
+                    } else {
+                        question.clone()
+                    };
+
+                    pack_by_context_budget(
+                        to_tokens(&fixed_prompt, &CURRENT_TOKENIZER).len(),
+                        chat_history,
+                        candidates,
+                        budget,
+                        with_candidates,
+                    )
+                }
+                Ok(None) => (chat_history, candidates),
+                Err(err) => {
+                    log::warn!("Get prompt tokens budget error: {err}");
+                    (chat_history, candidates)
+                }
+            };
+        let history_questions = chat_history
+            .iter()
+            .filter(|m| m.role == Role::User)
+            .map(|m| m.content.clone())
+            .collect::<Vec<_>>();
+
         // Generate answer by candidates
-        let chat_stream = if !candidates.is_empty() {
+        let chat_stream = if cancel_token.is_cancelled() {
+            Err(AiterError::Interrupted("Chat has been stopped".to_string()))
+        } else if !candidates.is_empty() {
             let prompt = make_answer_by_candidates_prompt(
                 &question,
                 &history_questions,
-                &candidates.into_iter().collect::<Vec<_>>(),
+                &candidates,
                 strict,
+                &lang,
             );
 
-            api::llm::stream_chat_completion(
+            api::llm::stream_chat_completion_with_parts(
                 &prompt,
+                &attachments,
                 &chat_history,
                 &chat_completion_options,
                 llm_for_chat.as_deref(),
@@ -301,18 +442,20 @@ pub async fn stream_chat(
             .await
         } else {
             if strict {
-                let prompt = make_no_answer_prompt(&question);
+                let prompt = make_no_answer_prompt(&question, &lang);
 
-                api::llm::stream_chat_completion(
+                api::llm::stream_chat_completion_with_parts(
                     &prompt,
+                    &attachments,
                     &[],
                     &chat_completion_options,
                     llm_for_chat.as_deref(),
                 )
                 .await
             } else {
-                api::llm::stream_chat_completion(
+                api::llm::stream_chat_completion_with_parts(
                     &question,
+                    &attachments,
                     &chat_history,
                     &chat_completion_options,
                     llm_for_chat.as_deref(),
@@ -323,9 +466,13 @@ pub async fn stream_chat(
 
         let mut content = String::new();
         let mut reasoning_content = String::new();
+        let mut served_llm_name: Option<String> = None;
 
         if let Ok(mut chat_stream) = chat_stream {
-            while let Some(event) = chat_stream.next().await {
+            served_llm_name = chat_stream.llm_name().map(|s| s.to_string());
+
+            while let Some(Some(event)) = cancel_token.run_until_cancelled(chat_stream.next()).await
+            {
                 match event {
                     ChatCompletionEvent::Content(ref delta) => {
                         content.push_str(delta);
@@ -346,6 +493,9 @@ pub async fn stream_chat(
             }
         }
 
+        // Stopped by the user or the receiver has been dropped, the partial answer is kept
+        let interrupted = cancel_token.is_cancelled();
+
         // Save to mem history
         {
             let call_tools_end = call_tool_end_tasks
@@ -373,6 +523,8 @@ pub async fn stream_chat(
                 "content": content,
                 "reasoning": reasoning_content,
                 "call_tools": call_tools,
+                "llm": served_llm_name,
+                "interrupted": interrupted,
             })
             .to_string();
             {
@@ -392,6 +544,110 @@ pub async fn stream_chat(
     Ok(stream)
 }
 
+/// The layer is relevant if contents are retrieved from it, or it is mentioned by the question or queries
+fn is_geo_layer_relevant(
+    layer: &GeoLayer,
+    question: &str,
+    related_queries: &[String],
+    candidates: &HashSet<String>,
+) -> bool {
+    // Retrieved contents are prefixed by the context of their docs
+    let prefix = format!("**{}** ", layer.context);
+    if candidates.iter().any(|c| c.starts_with(&prefix)) {
+        return true;
+    }
+
+    let context = layer.context.to_lowercase();
+    !context.is_empty()
+        && std::iter::once(question)
+            .chain(related_queries.iter().map(|q| q.as_str()))
+            .any(|q| q.to_lowercase().contains(&context))
+}
+
+/// Pack history and candidates into the tokens budget, older history is truncated first, then the lowest ranked
+/// candidates are dropped. Questions of history are counted twice if they are repeated in the prompt.
+fn pack_by_context_budget(
+    fixed_tokens: usize,
+    mut chat_history: Vec<ChatMessage>,
+    mut candidates: Vec<String>,
+    budget: usize,
+    history_questions_in_prompt: bool,
+) -> (Vec<ChatMessage>, Vec<String>) {
+    let message_tokens = |message: &ChatMessage| {
+        let tokens = to_tokens(&message.content, &CURRENT_TOKENIZER).len();
+        if history_questions_in_prompt && message.role == Role::User {
+            tokens * 2
+        } else {
+            tokens
+        }
+    };
+    let candidate_tokens = |candidate: &String| to_tokens(candidate, &CURRENT_TOKENIZER).len();
+
+    let mut tokens = fixed_tokens
+        + chat_history.iter().map(message_tokens).sum::<usize>()
+        + candidates.iter().map(candidate_tokens).sum::<usize>();
+
+    let mut dropped_history = 0;
+    while tokens > budget && !chat_history.is_empty() {
+        tokens -= message_tokens(&chat_history.remove(0));
+        dropped_history += 1;
+
+        // History should start with a question of the user
+        while chat_history.first().is_some_and(|m| m.role != Role::User) {
+            tokens -= message_tokens(&chat_history.remove(0));
+            dropped_history += 1;
+        }
+    }
+
+    let mut dropped_candidates: Vec<String> = vec![];
+    while tokens > budget {
+        if let Some(candidate) = candidates.pop() {
+            tokens -= candidate_tokens(&candidate);
+            dropped_candidates.push(truncate_format(&candidate, TRUNCATE_LOG_MESSAGE, false));
+        } else {
+            break;
+        }
+    }
+
+    if dropped_history > 0 || !dropped_candidates.is_empty() {
+        log::debug!(
+            "Packed into {} of {} budget tokens, dropped {} history messages and {} candidates: {:?}",
+            tokens,
+            budget,
+            dropped_history,
+            dropped_candidates.len(),
+            dropped_candidates
+        );
+    }
+
+    (chat_history, candidates)
+}
+
+async fn embed_questions(
+    mem_path: &Path,
+    question: &str,
+    related_queries: &[String],
+) -> Option<RetrieveMethod> {
+    db::mem::get_mem_embedding_dims(mem_path)?;
+    api::llm::get_actived_name("embedding").await.ok()??;
+
+    let all_questions: Vec<String> = std::iter::once(question.to_string())
+        .chain(related_queries.iter().cloned())
+        .collect::<HashSet<String>>()
+        .into_iter()
+        .collect();
+
+    match api::llm::embeddings(&all_questions, None).await {
+        Ok(embeddings) => Some(RetrieveMethod::Embedding(Arc::new(
+            all_questions.into_iter().zip(embeddings).collect(),
+        ))),
+        Err(err) => {
+            log::warn!("Embed questions error: {err}");
+            None
+        }
+    }
+}
+
 async fn retrieve_contents(
     method: &RetrieveMethod,
     mem_path: &Path,
@@ -440,93 +696,186 @@ async fn retrieve_contents(
 }
 
 async fn stream_invoke_skills(
+    mem_path: &Path,
     skills: &[db::mem::skill::SkillEntity],
+    geo_layers: &[GeoLayer],
     question: &str,
     chat_history: &[ChatMessage],
     chat_llm_name: Option<&str>,
+    max_steps: usize,
 ) -> AiterResult<ChatCompletionStream> {
     let mut functions: Vec<ChatFunction> = vec![];
     for skill in skills {
         if let Some(tool) = api::tool::get(&skill.tool_id).await? {
-            let chat_function = match ToolType::from_str(&tool.r#type)? {
-                ToolType::Ahp => chat_function_from_ahp(&tool),
-                ToolType::Mcp => chat_function_from_mcp(&tool),
-            };
-
-            if let Ok(chat_function) = chat_function {
+            if let Ok(chat_function) = chat_function_from_tool(&tool) {
                 functions.push(chat_function);
             }
         }
     }
+    if !geo_layers.is_empty() {
+        functions.push(chat_function_for_spatial(geo_layers));
+    }
     log::debug!("Functions: {functions:?}");
 
-    let function_calls =
-        api::llm::chat_function_calls(&functions, question, chat_history, chat_llm_name).await?;
-    log::debug!("Function calls: {function_calls:?}");
-
     let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER_DEFAULT);
     let stream = ChatCompletionStream::new(receiver);
+    let cancel_token = stream.cancel_token();
 
-    for function_call in function_calls {
-        let tool_id = function_call.name;
-        let options = function_call.arguments;
+    let question = question.to_string();
+    let chat_history = chat_history.to_vec();
+    let chat_llm_name = chat_llm_name.map(|s| s.to_string());
+    let geo_layers = geo_layers.to_vec();
+    let usage_tag = UsageTag::new("function-calls").with_mem_path(mem_path);
 
-        if let Some(tool) = api::tool::get(&tool_id).await? {
-            let description = tool.description;
+    // Feed results back to LLM until it stops calling, so that calls can be chained
+    tokio::spawn(async move {
+        let mut tool_turns: Vec<ChatMessage> = vec![];
 
-            let tool_parameters: serde_json::Value = serde_json::from_str(&tool.parameters)?;
-            let mut parameters: HashMap<String, String> = HashMap::new();
-            for (k, v) in &options {
-                if let Some(param_description) =
-                    tool_parameters["params"][k]["description"].as_str()
-                {
-                    parameters.insert(param_description.to_string(), v.to_string());
+        for step in 1..=max_steps {
+            let function_calls = match cancel_token
+                .run_until_cancelled(api::llm::chat_function_calls(
+                    &functions,
+                    &question,
+                    &chat_history,
+                    &tool_turns,
+                    &usage_tag,
+                    chat_llm_name.as_deref(),
+                ))
+                .await
+            {
+                Some(Ok(function_calls)) => function_calls,
+                Some(Err(err)) => {
+                    log::warn!("Function calls error: {err}");
+                    break;
                 }
+                None => break,
+            };
+            log::debug!("Function calls of step {step}: {function_calls:?}");
+
+            if function_calls.is_empty() {
+                break;
             }
 
-            let sender = sender.clone();
-            let task = ChatCallToolTask {
-                id: Ulid::new().to_string(),
-                tool_id: tool_id.clone(),
-                description,
-                parameters,
-            };
+            let mut call_results: Vec<(String, JoinHandle<String>)> = vec![];
+            for function_call in &function_calls {
+                let tool_id = function_call.name.clone();
+                let arguments = function_call
+                    .arguments
+                    .as_object()
+                    .cloned()
+                    .unwrap_or_default();
 
-            tokio::spawn(async move {
-                let _ = sender
-                    .send(ChatCompletionEvent::CallToolStart(task.clone()))
-                    .await;
+                // Every call must be answered, including those of unknown tools
+                let is_spatial = tool_id == SPATIAL_FUNCTION_NAME && !geo_layers.is_empty();
+                let description = if is_spatial {
+                    chat_function_for_spatial(&geo_layers).description
+                } else if let Ok(Some(tool)) = api::tool::get(&tool_id).await {
+                    tool.description
+                } else {
+                    call_results.push((
+                        function_call.id.clone(),
+                        tokio::spawn(async move { format!("Tool '{tool_id}' not exists") }),
+                    ));
+                    continue;
+                };
 
-                match api::tool::run(&tool_id, &options).await {
-                    Ok(result) => {
-                        let _ = sender
-                            .send(ChatCompletionEvent::CallToolEnd(
-                                task.id.to_string(),
-                                result,
-                                now_iso_datetime_string(),
-                            ))
-                            .await;
-                    }
-                    Err(err) => {
-                        let _ = sender
-                            .send(ChatCompletionEvent::CallToolFail(
-                                task.id.to_string(),
-                                err.to_string(),
-                                now_iso_datetime_string(),
-                            ))
-                            .await;
-
-                        log::error!("Call function failed: {err:?}");
-                    }
+                // Describe arguments for display, fall back to argument name if no description declared
+                let properties = functions
+                    .iter()
+                    .find(|f| f.name == tool_id)
+                    .map(|f| f.parameters["properties"].clone())
+                    .unwrap_or_default();
+                let mut parameters: HashMap<String, String> = HashMap::new();
+                for (k, v) in &arguments {
+                    let param_description = properties[k]["description"].as_str().unwrap_or(k);
+                    parameters.insert(param_description.to_string(), json_value_to_string(v));
+                }
+
+                let sender = sender.clone();
+                let task = ChatCallToolTask {
+                    id: Ulid::new().to_string(),
+                    tool_id: tool_id.clone(),
+                    description,
+                    parameters,
                 };
+
+                let geo_layers = geo_layers.clone();
+                let handle = tokio::spawn(async move {
+                    let _ = sender
+                        .send(ChatCompletionEvent::CallToolStart(task.clone()))
+                        .await;
+
+                    let call_result = if is_spatial {
+                        tokio::task::spawn_blocking(move || run_spatial(&geo_layers, &arguments))
+                            .await
+                            .unwrap_or_else(|err| Err(err.into()))
+                    } else {
+                        api::tool::call(&tool_id, &arguments).await
+                    };
+
+                    match call_result {
+                        Ok(result) => {
+                            let _ = sender
+                                .send(ChatCompletionEvent::CallToolEnd(
+                                    task.id.to_string(),
+                                    result.clone(),
+                                    now_iso_datetime_string(),
+                                ))
+                                .await;
+
+                            result
+                        }
+                        Err(err) => {
+                            let _ = sender
+                                .send(ChatCompletionEvent::CallToolFail(
+                                    task.id.to_string(),
+                                    err.to_string(),
+                                    now_iso_datetime_string(),
+                                ))
+                                .await;
+
+                            log::error!("Call function failed: {err:?}");
+                            err.to_string()
+                        }
+                    }
+                });
+
+                call_results.push((function_call.id.clone(), handle));
+            }
+
+            tool_turns.push(ChatMessage {
+                tool_calls: function_calls,
+                ..ChatMessage::new(Role::Bot, "")
             });
+
+            for (call_id, handle) in call_results {
+                let result = handle.await.unwrap_or_else(|err| err.to_string());
+                tool_turns.push(ChatMessage {
+                    tool_call_id: Some(call_id),
+                    ..ChatMessage::new(Role::Tool, &result)
+                });
+            }
+
+            if step == max_steps {
+                log::debug!("Function calls stopped at max steps {max_steps}");
+            }
         }
-    }
+    });
 
     Ok(stream)
 }
 
 impl ChatOptions {
+    pub fn with_attachments(mut self, attachments: Vec<ChatContentPart>) -> Self {
+        self.attachments = attachments;
+        self
+    }
+
+    pub fn with_cancel_token(mut self, cancel_token: CancellationToken) -> Self {
+        self.cancel_token = cancel_token;
+        self
+    }
+
     pub fn with_deep(mut self, deep: bool) -> Self {
         self.deep = deep;
         self
@@ -552,6 +901,11 @@ impl ChatOptions {
         self
     }
 
+    pub fn with_max_tool_steps(mut self, max_tool_steps: Option<usize>) -> Self {
+        self.max_tool_steps = max_tool_steps;
+        self
+    }
+
     pub fn with_retrace(mut self, retrace: u64) -> Self {
         self.retrace = retrace;
         self
@@ -567,3 +921,263 @@ impl ChatOptions {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::llm::provider::mock::{mock_response, mock_server_with_recorder};
+
+    #[test]
+    fn test_pack_by_context_budget() {
+        let tokens = |s: &str| to_tokens(s, &CURRENT_TOKENIZER).len();
+
+        let chat_history = vec![
+            ChatMessage::new(Role::User, "What is the capital of France?"),
+            ChatMessage::new(Role::Bot, "The capital of France is Paris."),
+            ChatMessage::new(Role::User, "And what about Germany?"),
+            ChatMessage::new(Role::Bot, "The capital of Germany is Berlin."),
+        ];
+        let candidates = vec![
+            "Berlin is the capital and largest city of Germany.".to_string(),
+            "Germany is a country in Central Europe.".to_string(),
+        ];
+
+        // Everything fits
+        let (history, packed) =
+            pack_by_context_budget(10, chat_history.clone(), candidates.clone(), 10000, true);
+        assert_eq!(history.len(), 4);
+        assert_eq!(packed.len(), 2);
+
+        // Older round of history is dropped first
+        let budget = 10
+            + tokens(&chat_history[2].content) * 2
+            + tokens(&chat_history[3].content)
+            + tokens(&candidates[0])
+            + tokens(&candidates[1]);
+        let (history, packed) =
+            pack_by_context_budget(10, chat_history.clone(), candidates.clone(), budget, true);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].content, chat_history[2].content);
+        assert_eq!(packed.len(), 2);
+
+        // Then the lowest ranked candidates
+        let budget = 10 + tokens(&candidates[0]);
+        let (history, packed) =
+            pack_by_context_budget(10, chat_history.clone(), candidates.clone(), budget, true);
+        assert!(history.is_empty());
+        assert_eq!(packed, vec![candidates[0].clone()]);
+    }
+
+    #[test]
+    fn test_is_geo_layer_relevant() {
+        let layer = GeoLayer {
+            source: "https://example.com/stations.geojson".to_string(),
+            context: "Stations".to_string(),
+            path: "stations".into(),
+        };
+
+        let candidates =
+            HashSet::from(["**Stations** name , line\nPeople's Square , 1".to_string()]);
+        assert!(is_geo_layer_relevant(
+            &layer,
+            "Where to transfer?",
+            &[],
+            &candidates
+        ));
+
+        let candidates = HashSet::from(["**Menu** Noodles , 12".to_string()]);
+        assert!(!is_geo_layer_relevant(
+            &layer,
+            "What to eat?",
+            &[],
+            &candidates
+        ));
+        assert!(is_geo_layer_relevant(
+            &layer,
+            "What to eat?",
+            &["restaurants near stations".to_string()],
+            &candidates
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stream_chat() {
+        crate::db::ensure_test_tables().await;
+
+        // Responses are recorded by the record protocol, so chatting is replayed without network
+        let cassette = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/cassettes/chat.jsonl");
+        let llm_name = Ulid::new().to_string();
+        db::core::llm::upsert(
+            &llm_name,
+            "chat",
+            "replay",
+            &HashMap::from([(
+                "cassette".to_string(),
+                cassette.to_string_lossy().to_string(),
+            )]),
+        )
+        .await
+        .unwrap();
+
+        let mem_path = crate::DATA_DIR.join(format!("{}.db", Ulid::new()));
+        db::ensure_mem_tables(&mem_path).await.unwrap();
+
+        // Nothing is learned, so the question is answered by the LLM directly after queries are extracted
+        let mut stream = stream_chat(
+            &mem_path,
+            0,
+            "What is Aiter?",
+            &ChatOptions::default().with_llm_for_chat(Some(llm_name)),
+            &[],
+            db::mem::spawn_mem_write(&mem_path),
+        )
+        .await
+        .unwrap();
+
+        let mut content = String::new();
+        while let Some(event) = stream.next().await {
+            if let ChatCompletionEvent::Content(delta) = event {
+                content.push_str(&delta);
+            }
+        }
+        assert_eq!(
+            content,
+            "Aiter is an AI assistant which learns from your documents."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_invoke_skills() {
+        crate::db::ensure_test_tables().await;
+
+        let path = crate::DATA_DIR.join(format!("{}.geojson", Ulid::new()));
+        std::fs::write(
+            &path,
+            r#"{"type": "FeatureCollection", "features": [{"type": "Feature", "geometry": {"type": "Point", "coordinates": [121.5, 31.2]}, "properties": {"name": "A"}}]}"#,
+        )
+        .unwrap();
+        let geo_layers = vec![GeoLayer {
+            source: "stations.geojson".to_string(),
+            context: "Stations".to_string(),
+            path,
+        }];
+
+        let calls_response = |calls: Value| {
+            let body = format!(
+                "data: {}\n\ndata: [DONE]\n\n",
+                json!({"choices": [{"delta": {"tool_calls": calls}}]})
+            );
+            mock_response("200 OK", &[], "text/event-stream", &body)
+        };
+        let spatial_call = |index: u64, id: &str| {
+            json!({
+                "index": index,
+                "id": id,
+                "type": "function",
+                "function": {
+                    "name": SPATIAL_FUNCTION_NAME,
+                    "arguments": r#"{"mode": "nearest", "lat": 31.2, "lon": 121.5}"#,
+                },
+            })
+        };
+        let add_llm = |base_url: String| async move {
+            let llm_name = Ulid::new().to_string();
+            db::core::llm::upsert(
+                &llm_name,
+                "chat",
+                "openai",
+                &HashMap::from([
+                    ("base_url".to_string(), base_url),
+                    ("model".to_string(), "gpt".to_string()),
+                    ("max_retry".to_string(), "0".to_string()),
+                ]),
+            )
+            .await
+            .unwrap();
+            llm_name
+        };
+
+        // Two rounds of calls are chained, the loop stops once the model makes no call
+        let (base_url, records) = mock_server_with_recorder(vec![
+            calls_response(json!([spatial_call(0, "call_1")])),
+            calls_response(json!([
+                spatial_call(0, "call_2"),
+                {
+                    "index": 1,
+                    "id": "call_3",
+                    "type": "function",
+                    "function": {"name": "unknown_tool", "arguments": "{}"},
+                },
+            ])),
+            calls_response(json!([])),
+            calls_response(json!([spatial_call(0, "call_4")])),
+        ]);
+        let llm_name = add_llm(base_url).await;
+
+        let mut stream = stream_invoke_skills(
+            Path::new("test"),
+            &[],
+            &geo_layers,
+            "Nearest station?",
+            &[],
+            Some(&llm_name),
+            5,
+        )
+        .await
+        .unwrap();
+
+        let mut ended_results: Vec<String> = vec![];
+        while let Some(event) = stream.next().await {
+            if let ChatCompletionEvent::CallToolEnd(_, result, _) = event {
+                ended_results.push(result);
+            }
+        }
+        assert_eq!(ended_results.len(), 2);
+        assert!(ended_results.iter().all(|result| result.contains("\"A\"")));
+
+        let requests: Vec<Value> = records
+            .try_iter()
+            .map(|body| serde_json::from_str(&body).unwrap())
+            .collect();
+        assert_eq!(requests.len(), 3);
+
+        let messages = requests[1]["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1]["tool_calls"][0]["id"], "call_1");
+        assert_eq!(messages[2]["role"], "tool");
+        assert_eq!(messages[2]["tool_call_id"], "call_1");
+
+        // Calls of unknown tools are answered too, otherwise the model would reject the turns
+        let messages = requests[2]["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 6);
+        let unknown_result = messages
+            .iter()
+            .find(|m| m["tool_call_id"] == "call_3")
+            .unwrap();
+        assert_eq!(unknown_result["role"], "tool");
+        assert_eq!(unknown_result["content"], "Tool 'unknown_tool' not exists");
+
+        // Calls stop at the max steps even if the model keeps calling
+        let (base_url, records) = mock_server_with_recorder(vec![
+            calls_response(json!([spatial_call(0, "call_1")])),
+            calls_response(json!([spatial_call(0, "call_2")])),
+        ]);
+        let llm_name = add_llm(base_url).await;
+
+        let mut stream = stream_invoke_skills(
+            Path::new("test"),
+            &[],
+            &geo_layers,
+            "Nearest station?",
+            &[],
+            Some(&llm_name),
+            1,
+        )
+        .await
+        .unwrap();
+        while stream.next().await.is_some() {}
+        assert_eq!(records.try_iter().count(), 1);
+    }
+}
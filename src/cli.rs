@@ -12,6 +12,7 @@ mod info;
 mod learn;
 mod llm;
 mod mem;
+mod prompt;
 mod read;
 mod serve;
 mod tool;
@@ -46,6 +47,10 @@ pub enum Commands {
     #[clap(subcommand)]
     Mem(Box<mem::MemCommand>),
 
+    #[command(about = "Manage prompt templates and their language")]
+    #[clap(subcommand)]
+    Prompt(Box<prompt::PromptCommand>),
+
     #[command(about = "Read documents")]
     Read(Box<read::ReadCommand>),
 
@@ -1,7 +1,13 @@
-use std::io::{Write, stdout};
+use std::{
+    io::{Write, stdout},
+    path::PathBuf,
+};
 
 use aiter::{
-    api::{chat::ChatOptions, llm::ChatCompletionEvent},
+    api::{
+        chat::ChatOptions,
+        llm::{ChatCompletionEvent, ChatContentPart},
+    },
     *,
 };
 use colored::Colorize;
@@ -19,6 +25,13 @@ pub struct ChatCommand {
     )]
     ai: Option<String>,
 
+    #[arg(
+        short = 'a',
+        long = "attach",
+        help = "Attach image to the message, can be specified multiple times, e.g. -a screenshot.png"
+    )]
+    attachments: Vec<PathBuf>,
+
     #[arg(
         short = 'd',
         long = "deep",
@@ -43,10 +56,16 @@ pub struct ChatCommand {
     #[arg(
         short = 'O',
         long = "llm-option",
-        help = "Additional option passed to LLM, e.g. -O temperature:0.6"
+        help = "Additional option passed to LLM, e.g. -O temperature:0.6, supported options: temperature/max_tokens/top_p/stop/seed/presence_penalty/frequency_penalty/extra_body"
     )]
     llm_options: Vec<String>,
 
+    #[arg(
+        long = "max-tool-steps",
+        help = "Max rounds of calling tools, results of each round are fed back to LLM, default value is 5"
+    )]
+    max_tool_steps: Option<usize>,
+
     #[arg(
         short = 'r',
         long = "retrace",
@@ -78,14 +97,27 @@ impl ChatCommand {
             return;
         }
 
+        let mut attachments: Vec<ChatContentPart> = vec![];
+        for path in &self.attachments {
+            match ChatContentPart::from_image_path(path) {
+                Ok(attachment) => attachments.push(attachment),
+                Err(err) => {
+                    println!("{}", err.to_string().red());
+                    return;
+                }
+            }
+        }
+
         let ai = self.ai.clone();
         let message = self.message.clone();
         let chat_options = ChatOptions::default()
+            .with_attachments(attachments)
             .with_deep(self.deep)
             .with_exchange(None)
             .with_llm_for_chat(self.llm_for_chat.clone())
             .with_llm_for_reasoning(self.llm_for_reasoning.clone())
             .with_llm_options(self.llm_options.clone())
+            .with_max_tool_steps(self.max_tool_steps)
             .with_retrace(self.retrace)
             .with_session(self.session.clone())
             .with_strict(self.strict);
@@ -118,8 +150,32 @@ impl ChatCommand {
 
                 let mut has_content = false;
                 let mut has_reasoning_content = false;
+                let mut interrupted = false;
+
+                let ctrl_c = tokio::signal::ctrl_c();
+                tokio::pin!(ctrl_c);
+
+                loop {
+                    let event = tokio::select! {
+                        event = stream.next() => event,
+                        _ = &mut ctrl_c => {
+                            // Exit at once on the second press, in case the stream does not end in time
+                            if interrupted {
+                                println!("\n{}", "[Interrupted]".yellow());
+                                std::process::exit(130);
+                            }
+
+                            // Keep receiving until the stream ends, so the partial answer is saved before exit
+                            interrupted = true;
+                            stream.cancel();
+                            ctrl_c.set(tokio::signal::ctrl_c());
+                            continue;
+                        }
+                    };
+                    let Some(event) = event else {
+                        break;
+                    };
 
-                while let Some(event) = stream.next().await {
                     match event {
                         ChatCompletionEvent::CallToolStart(task) => {
                             let task_name = format!("[{}]", task.description);
@@ -140,6 +196,7 @@ impl ChatCommand {
                         }
                         ChatCompletionEvent::CallToolEnd(_task_id, _result, _time) => {}
                         ChatCompletionEvent::CallToolFail(_task_id, _error, _time) => {}
+                        ChatCompletionEvent::Usage(_usage) => {}
                         ChatCompletionEvent::Content(delta) => {
                             if !has_content && has_reasoning_content {
                                 print!("\n\n");
@@ -163,6 +220,9 @@ impl ChatCommand {
                 }
 
                 println!();
+                if interrupted {
+                    println!("{}", "[Interrupted]".yellow());
+                }
             }
             Err(err) => {
                 spinner.finish_with_message(format!("[{}] {}", bot_name, err.to_string().red()));
@@ -38,6 +38,18 @@ pub struct DigestCommand {
     )]
     deep: bool,
 
+    #[arg(
+        long = "llm",
+        help = "LLM for digesting, the active digest LLM is used if not specified, and then the active chat LLM"
+    )]
+    llm: Option<String>,
+
+    #[arg(
+        long = "no-cache",
+        help = "Bypass cached LLM responses, always request LLM"
+    )]
+    no_cache: bool,
+
     #[arg(
         short = 'r',
         long = "retry",
@@ -52,6 +64,8 @@ impl DigestCommand {
             return;
         }
 
+        api::llm::set_cache_bypass(self.no_cache);
+
         let bot_name = self.ai.clone().unwrap_or("~".to_string()).cyan();
 
         let (event_sender, mut event_receiver) =
@@ -62,6 +76,7 @@ impl DigestCommand {
             .with_batch(self.batch)
             .with_concurrent(self.concurrent)
             .with_deep(self.deep)
+            .with_llm(self.llm.clone())
             .with_retry(self.retry);
 
         let mem_write_event_sender = api::mem::spawn_mem_write(ai.as_deref())
@@ -19,6 +19,13 @@ impl InfoCommand {
             .unwrap_or("".to_string());
         println!("Default Chat LLM: {}", llm_for_chat.cyan().bold());
 
+        let llm_for_digest = api::llm::get_actived_name("digest")
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or("".to_string());
+        println!("Default Digest LLM: {}", llm_for_digest.cyan().bold());
+
         let llm_for_reasoning = api::llm::get_actived_name("reasoning")
             .await
             .ok()
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::Path;
 
 use aiter::{api::learn::*, *};
 use bytesize::ByteSize;
@@ -30,10 +30,16 @@ pub struct LearnCommand {
     )]
     digest_deep: bool,
 
+    #[arg(
+        long = "llm",
+        help = "LLM for digesting, the active digest LLM is used if not specified, and then the active chat LLM"
+    )]
+    llm: Option<String>,
+
     #[arg(
         short = 'f',
         long = "format",
-        help = "Specify the data source format rather than judging by suffix, currently supported formats: csv/docx/epub/md/pdf/txt/xlsx"
+        help = "Specify the data source format rather than judging by suffix, currently supported formats: csv/docx/epub/geojson/html/md/odp/pdf/pptx/txt/xlsx and source code such as c/go/java/js/py/rs/ts"
     )]
     format: Option<String>,
 
@@ -44,8 +50,14 @@ pub struct LearnCommand {
     )]
     keep: bool,
 
-    #[clap(required = true, help = "Source file")]
-    source: PathBuf,
+    #[arg(
+        long = "no-cache",
+        help = "Bypass cached LLM responses, always request LLM"
+    )]
+    no_cache: bool,
+
+    #[clap(required = true, help = "Source file or HTTP URL")]
+    source: String,
 }
 
 impl LearnCommand {
@@ -54,6 +66,8 @@ impl LearnCommand {
             return;
         }
 
+        api::llm::set_cache_bypass(self.no_cache);
+
         if let Some((doc_id, need_digest)) = self.exec_read().await {
             if need_digest {
                 self.exec_digest(&doc_id).await;
@@ -62,13 +76,13 @@ impl LearnCommand {
     }
 
     async fn exec_read(&self) -> Option<(String, bool)> {
-        let filename = utils::fs::extract_filename_from_path(&self.source);
+        let filename = self.source_name();
         let bot_name = self.ai.clone().unwrap_or("~".to_string()).cyan();
 
         let (event_sender, mut event_receiver) = mpsc::channel::<ReadEvent>(CHANNEL_BUFFER_DEFAULT);
 
         let ai = self.ai.clone();
-        let path_buf = self.source.to_path_buf();
+        let source = self.source.clone();
         let format = self.format.clone();
         let keep = self.keep;
 
@@ -77,16 +91,28 @@ impl LearnCommand {
             .expect("Spawn mem write error");
 
         let handle = tokio::spawn(async move {
-            read_doc(
-                ai.as_deref(),
-                &path_buf,
-                None,
-                format.as_deref(),
-                keep,
-                mem_write_event_sender,
-                Some(event_sender),
-            )
-            .await
+            if is_url_source(&source) {
+                read_url(
+                    ai.as_deref(),
+                    &source,
+                    format.as_deref(),
+                    keep,
+                    mem_write_event_sender,
+                    Some(event_sender),
+                )
+                .await
+            } else {
+                read_doc(
+                    ai.as_deref(),
+                    Path::new(&source),
+                    None,
+                    format.as_deref(),
+                    keep,
+                    mem_write_event_sender,
+                    Some(event_sender),
+                )
+                .await
+            }
         });
 
         let spinner = ProgressBar::new_spinner();
@@ -150,7 +176,7 @@ impl LearnCommand {
     }
 
     async fn exec_digest(&self, doc_id: &str) {
-        let filename = utils::fs::extract_filename_from_path(&self.source);
+        let filename = self.source_name();
         let bot_name = self.ai.clone().unwrap_or("~".to_string()).cyan();
 
         let (event_sender, mut event_receiver) =
@@ -160,7 +186,8 @@ impl LearnCommand {
         let doc_id = doc_id.to_string();
         let options = DigestOptions::default()
             .with_concurrent(self.digest_concurrent)
-            .with_deep(self.digest_deep);
+            .with_deep(self.digest_deep)
+            .with_llm(self.llm.clone());
 
         let mem_write_event_sender = api::mem::spawn_mem_write(ai.as_deref())
             .await
@@ -232,4 +259,12 @@ impl LearnCommand {
             }
         }
     }
+
+    fn source_name(&self) -> String {
+        if is_url_source(&self.source) {
+            self.source.clone()
+        } else {
+            utils::fs::extract_filename_from_path(Path::new(&self.source))
+        }
+    }
 }
@@ -2,17 +2,30 @@ use aiter::*;
 use clap::Subcommand;
 
 mod active;
+mod cache;
+mod check;
 mod config;
 mod delete;
 mod list;
+mod models;
 mod rename;
 mod test;
+mod usage;
 
 #[derive(Subcommand)]
 pub enum LlmCommand {
     #[command(about = "Active LLM as the default provider")]
     Active(Box<active::LlmActiveCommand>),
 
+    #[command(about = "Commands for cached LLM responses")]
+    #[clap(subcommand)]
+    Cache(Box<cache::LlmCacheCommand>),
+
+    #[command(
+        about = "Check connectivity and capabilities of LLM provider, detected capabilities are saved to its options"
+    )]
+    Check(Box<check::LlmCheckCommand>),
+
     #[command(about = "Configure LLM provider")]
     Config(Box<config::LlmConfigCommand>),
 
@@ -20,15 +33,23 @@ pub enum LlmCommand {
     #[clap(visible_aliases = &["del", "remove", "rm"])]
     Delete(Box<delete::LlmDeleteCommand>),
 
-    #[command(about = "List LLM providers")]
+    #[command(
+        about = "List LLM providers, utilization of limits is per process and reported by /api/llm/list-utilizations of the server"
+    )]
     #[clap(visible_aliases = &["ls"])]
     List(Box<list::LlmListCommand>),
 
+    #[command(about = "List models provided by LLM provider")]
+    Models(Box<models::LlmModelsCommand>),
+
     #[command(about = "Rename LLM provider")]
     Rename(Box<rename::LlmRenameCommand>),
 
     #[command(about = "Test the default LLM provider")]
     Test(Box<test::LlmTestCommand>),
+
+    #[command(about = "Report token usage and cost of LLMs")]
+    Usage(Box<usage::LlmUsageCommand>),
 }
 
 impl LlmCommand {
@@ -37,6 +58,12 @@ impl LlmCommand {
             LlmCommand::Active(cmd) => {
                 cmd.exec().await;
             }
+            LlmCommand::Cache(cmd) => {
+                cmd.exec().await;
+            }
+            LlmCommand::Check(cmd) => {
+                cmd.exec().await;
+            }
             LlmCommand::Config(cmd) => {
                 cmd.exec().await;
             }
@@ -46,12 +73,18 @@ impl LlmCommand {
             LlmCommand::List(cmd) => {
                 cmd.exec().await;
             }
+            LlmCommand::Models(cmd) => {
+                cmd.exec().await;
+            }
             LlmCommand::Rename(cmd) => {
                 cmd.exec().await;
             }
             LlmCommand::Test(cmd) => {
                 cmd.exec().await;
             }
+            LlmCommand::Usage(cmd) => {
+                cmd.exec().await;
+            }
         }
     }
 }
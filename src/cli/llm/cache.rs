@@ -0,0 +1,27 @@
+use clap::Subcommand;
+
+mod clear;
+mod stats;
+
+#[derive(Subcommand)]
+pub enum LlmCacheCommand {
+    #[command(about = "Clear cached LLM responses")]
+    #[clap(visible_aliases = &["erase"])]
+    Clear(Box<clear::LlmCacheClearCommand>),
+
+    #[command(about = "Statistics of cached LLM responses")]
+    Stats(Box<stats::LlmCacheStatsCommand>),
+}
+
+impl LlmCacheCommand {
+    pub async fn exec(&self) {
+        match self {
+            LlmCacheCommand::Clear(cmd) => {
+                cmd.exec().await;
+            }
+            LlmCacheCommand::Stats(cmd) => {
+                cmd.exec().await;
+            }
+        }
+    }
+}
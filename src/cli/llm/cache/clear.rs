@@ -0,0 +1,32 @@
+use aiter::*;
+use colored::Colorize;
+
+use crate::cli;
+
+#[derive(clap::Args)]
+pub struct LlmCacheClearCommand {
+    #[arg(long = "llm", help = "Only clear cached responses of the LLM")]
+    llm: Option<String>,
+}
+
+impl LlmCacheClearCommand {
+    pub async fn exec(&self) {
+        let target = match &self.llm {
+            Some(llm) => format!("LLM '{llm}'"),
+            None => "all LLMs".to_string(),
+        };
+
+        if cli::confirm_action(&format!(
+            "Are you sure you want to clear cached responses of {target}?"
+        )) {
+            match api::llm::cache_clear(self.llm.as_deref()).await {
+                Ok(count) => {
+                    println!("{count} cached responses of {target} have been cleared");
+                }
+                Err(err) => {
+                    println!("{}", err.to_string().red());
+                }
+            }
+        }
+    }
+}
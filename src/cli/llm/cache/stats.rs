@@ -0,0 +1,19 @@
+use aiter::*;
+use colored::Colorize;
+use tabled::Table;
+
+#[derive(clap::Args)]
+pub struct LlmCacheStatsCommand;
+
+impl LlmCacheStatsCommand {
+    pub async fn exec(&self) {
+        match api::llm::cache_stats().await {
+            Ok(rows) => {
+                println!("{}", Table::new(rows));
+            }
+            Err(err) => {
+                println!("{}", err.to_string().red());
+            }
+        }
+    }
+}
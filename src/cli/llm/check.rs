@@ -0,0 +1,39 @@
+use aiter::*;
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use tabled::Table;
+use tokio::time::Duration;
+
+#[derive(clap::Args)]
+pub struct LlmCheckCommand {
+    name: String,
+}
+
+impl LlmCheckCommand {
+    pub async fn exec(&self) {
+        let spinner = ProgressBar::new_spinner();
+        spinner.set_style(ProgressStyle::with_template("{msg} {spinner:.cyan}").unwrap());
+        spinner.set_message(format!("Checking LLM '{}'", self.name));
+        spinner.enable_steady_tick(Duration::from_millis(100));
+
+        let result = api::llm::check(&self.name).await;
+        spinner.finish_and_clear();
+
+        match result {
+            Ok(items) => {
+                let has_capabilities = items.iter().any(|item| item.capability.is_some());
+
+                println!("{}", Table::new(items));
+                if has_capabilities {
+                    println!(
+                        "Detected capabilities have been saved to options of LLM '{}'",
+                        self.name
+                    );
+                }
+            }
+            Err(err) => {
+                println!("{}", err.to_string().red());
+            }
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use aiter::*;
+use colored::Colorize;
+
+#[derive(clap::Args)]
+pub struct LlmModelsCommand {
+    name: String,
+}
+
+impl LlmModelsCommand {
+    pub async fn exec(&self) {
+        match api::llm::models(&self.name).await {
+            Ok(models) => {
+                if models.is_empty() {
+                    println!("No models listed by LLM '{}'", self.name);
+                }
+
+                for model in models {
+                    println!("{model}");
+                }
+            }
+            Err(err) => {
+                println!("{}", err.to_string().red());
+            }
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use std::io::{Write, stdout};
 
 use aiter::{
-    api::llm::{ChatCompletionEvent, ChatCompletionOptions, ChatCompletionStream},
+    api::llm::{ChatCompletionEvent, ChatCompletionOptions, ChatCompletionStream, UsageTag},
     error::AiterResult,
     *,
 };
@@ -22,7 +22,7 @@ pub struct LlmTestCommand {
         short = 't',
         long = "type",
         default_value = "chat",
-        help = "LLM provider's type, the default value is chat, currently supported types: chat/reasoning"
+        help = "LLM provider's type, the default value is chat, currently supported types: chat/digest/embedding/reasoning/rerank"
     )]
     r#type: String,
 
@@ -35,21 +35,70 @@ impl LlmTestCommand {
             return;
         }
 
-        let mut chat_completion_options = ChatCompletionOptions::default();
-        let llm_options = VecOptions(&self.llm_options);
-        if let Some(temperature_str) = llm_options.get("temperature") {
-            if let Ok(temperature) = temperature_str.parse() {
-                chat_completion_options = chat_completion_options.with_temperature(temperature);
+        let chat_completion_options = match ChatCompletionOptions::default()
+            .with_llm_options(&VecOptions(&self.llm_options).into_map())
+        {
+            Ok(options) => options.with_usage_tag(UsageTag::new("test")),
+            Err(err) => {
+                println!("{}", err.to_string().red());
+                return;
             }
-        }
+        };
 
         let r#type = self.r#type.clone();
         let prompt = self.prompt.clone();
 
+        if r#type == "embedding" {
+            match api::llm::embeddings(&[prompt], None).await {
+                Ok(embeddings) => {
+                    if let Some(embedding) = embeddings.first() {
+                        println!(
+                            "{} {:?}",
+                            format!("[{} dims]", embedding.len()).bright_black(),
+                            &embedding[..embedding.len().min(8)]
+                        );
+                    }
+                }
+                Err(err) => {
+                    println!("{}", err.to_string().red());
+                }
+            }
+
+            return;
+        }
+
+        if r#type == "rerank" {
+            // Score the prompt against itself, a working reranker should give a high score
+            match api::llm::rerank(&prompt, std::slice::from_ref(&prompt), None).await {
+                Ok(scores) => {
+                    if let Some(score) = scores.first() {
+                        println!("{}", format!("[score] {score}").bright_black());
+                    }
+                }
+                Err(err) => {
+                    println!("{}", err.to_string().red());
+                }
+            }
+
+            return;
+        }
+
         let result: AiterResult<ChatCompletionStream> = match r#type.as_str() {
             "chat" => {
                 api::llm::stream_chat_completion(&prompt, &[], &chat_completion_options, None).await
             }
+            "digest" => match api::llm::get_actived_name("digest").await {
+                Ok(llm_name) => {
+                    api::llm::stream_chat_completion(
+                        &prompt,
+                        &[],
+                        &chat_completion_options,
+                        llm_name.as_deref(),
+                    )
+                    .await
+                }
+                Err(err) => Err(err),
+            },
             "reasoning" => match api::llm::get_actived_name("reasoning").await {
                 Ok(llm_name) => {
                     if llm_name.is_some() {
@@ -83,6 +132,7 @@ impl LlmTestCommand {
                         ChatCompletionEvent::CallToolStart(_task) => {}
                         ChatCompletionEvent::CallToolEnd(_task_id, _result, _time) => {}
                         ChatCompletionEvent::CallToolFail(_task_id, _error, _time) => {}
+                        ChatCompletionEvent::Usage(_usage) => {}
                         ChatCompletionEvent::Content(delta) => {
                             if !has_content && has_reasoning_content {
                                 print!("\n\n");
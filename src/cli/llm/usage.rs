@@ -0,0 +1,55 @@
+use aiter::{api::llm::LlmUsageGroup, *};
+use colored::Colorize;
+use tabled::Table;
+
+#[derive(clap::Args)]
+pub struct LlmUsageCommand {
+    #[arg(
+        short = 'g',
+        long = "group-by",
+        help = "Group usage by llm/ai/purpose/doc/date, can be repeated, e.g. -g llm -g purpose"
+    )]
+    group_by: Vec<String>,
+
+    #[arg(
+        long = "since",
+        help = "Only count usage since the date, e.g. 2025-01-01"
+    )]
+    since: Option<String>,
+
+    #[arg(
+        long = "until",
+        help = "Only count usage until the date, e.g. 2025-01-31"
+    )]
+    until: Option<String>,
+}
+
+impl LlmUsageCommand {
+    pub async fn exec(&self) {
+        let mut group_by: Vec<LlmUsageGroup> = vec![];
+        for group in &self.group_by {
+            match group.parse() {
+                Ok(group) => group_by.push(group),
+                Err(_) => {
+                    println!(
+                        "{}",
+                        format!(
+                            "Invalid group '{group}', available values: llm/ai/purpose/doc/date"
+                        )
+                        .red()
+                    );
+                    return;
+                }
+            }
+        }
+
+        match api::llm::usage(&group_by, self.since.as_deref(), self.until.as_deref()).await {
+            Ok(rows) => {
+                println!("{}", Table::new(rows));
+            }
+            Err(err) => {
+                println!("{}", err.to_string().red());
+            }
+        }
+    }
+}
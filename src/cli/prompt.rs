@@ -0,0 +1,60 @@
+use aiter::*;
+use clap::Subcommand;
+use colored::Colorize;
+
+mod edit;
+mod lang;
+mod list;
+mod show;
+
+#[derive(Subcommand)]
+pub enum PromptCommand {
+    #[command(
+        about = "Edit prompt template, the built-in template is copied to the data directory to override"
+    )]
+    Edit(Box<edit::PromptEditCommand>),
+
+    #[command(about = "Show or set language of prompts")]
+    Lang(Box<lang::PromptLangCommand>),
+
+    #[command(about = "List prompt templates")]
+    #[clap(visible_aliases = &["ls"])]
+    List(Box<list::PromptListCommand>),
+
+    #[command(about = "Show prompt template")]
+    Show(Box<show::PromptShowCommand>),
+}
+
+impl PromptCommand {
+    pub async fn exec(&self) {
+        match self {
+            PromptCommand::Edit(cmd) => {
+                cmd.exec().await;
+            }
+            PromptCommand::Lang(cmd) => {
+                cmd.exec().await;
+            }
+            PromptCommand::List(cmd) => {
+                cmd.exec().await;
+            }
+            PromptCommand::Show(cmd) => {
+                cmd.exec().await;
+            }
+        }
+    }
+}
+
+/// Language specified, or the language used by the AI
+async fn get_lang(lang: Option<&str>, ai: Option<&str>) -> Option<String> {
+    if let Some(lang) = lang {
+        return Some(lang.to_string());
+    }
+
+    match api::prompt::get_lang(ai).await {
+        Ok(lang) => Some(lang),
+        Err(err) => {
+            println!("{}", err.to_string().red());
+            None
+        }
+    }
+}
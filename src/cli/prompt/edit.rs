@@ -0,0 +1,81 @@
+use std::{env, process::Command};
+
+use aiter::*;
+use colored::Colorize;
+
+use crate::cli;
+
+#[derive(clap::Args)]
+pub struct PromptEditCommand {
+    #[arg(
+        long = "ai",
+        value_name = "AI",
+        help = "Edit the prompt in the language used by the AI"
+    )]
+    ai: Option<String>,
+
+    #[arg(
+        short = 'l',
+        long = "lang",
+        help = "Language of prompts, currently supported languages: en/zh"
+    )]
+    lang: Option<String>,
+
+    #[arg(
+        long = "reset",
+        help = "Discard the edited template, restore the built-in one"
+    )]
+    reset: bool,
+
+    name: String,
+}
+
+impl PromptEditCommand {
+    pub async fn exec(&self) {
+        if !cli::is_ai_valid(self.ai.as_deref()).await {
+            return;
+        }
+
+        let Some(lang) = cli::prompt::get_lang(self.lang.as_deref(), self.ai.as_deref()).await
+        else {
+            return;
+        };
+
+        if self.reset {
+            match api::prompt::reset(&self.name, &lang).await {
+                Ok(true) => println!("Prompt '{}' has been reset", self.name.green()),
+                Ok(false) => println!("Prompt '{}' has not been edited", self.name.yellow()),
+                Err(err) => println!("{}", err.to_string().red()),
+            }
+            return;
+        }
+
+        match api::prompt::edit(&self.name, &lang).await {
+            Ok(path) => {
+                // Editor command may have arguments, e.g. `code --wait`
+                let editor = env::var("VISUAL")
+                    .or(env::var("EDITOR"))
+                    .unwrap_or("vi".to_string());
+                let mut editor_args = editor.split_whitespace();
+
+                if let Some(program) = editor_args.next() {
+                    match Command::new(program).args(editor_args).arg(&path).status() {
+                        Ok(status) if status.success() => {
+                            println!("Prompt '{}' has been saved", self.name.green());
+                        }
+                        Ok(_) | Err(_) => {
+                            println!(
+                                "Unable to run editor '{}', edit the file directly: {}",
+                                editor.yellow(),
+                                path.display()
+                            );
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                println!("{}", err.to_string().red());
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use aiter::*;
+use colored::Colorize;
+
+use crate::cli;
+
+#[derive(clap::Args)]
+pub struct PromptLangCommand {
+    #[arg(
+        long = "ai",
+        value_name = "AI",
+        help = "The character performing the operation, it is the alias of `@<AI>`"
+    )]
+    ai: Option<String>,
+
+    #[arg(
+        short = 'g',
+        long = "global",
+        help = "Set language for all AIs which have not set their own"
+    )]
+    global: bool,
+
+    #[arg(help = "Language to set, currently supported languages: en/zh")]
+    lang: Option<String>,
+}
+
+impl PromptLangCommand {
+    pub async fn exec(&self) {
+        if !cli::is_ai_valid(self.ai.as_deref()).await {
+            return;
+        }
+
+        if let Some(lang) = &self.lang {
+            if let Err(err) = api::prompt::set_lang(self.ai.as_deref(), lang, self.global).await {
+                println!("{}", err.to_string().red());
+            } else if self.global {
+                println!("Language of prompts has been set to '{}'", lang.green());
+            } else {
+                println!(
+                    "Language of prompts for {} has been set to '{}'",
+                    cli::display_ai(self.ai.as_deref(), false),
+                    lang.green()
+                );
+            }
+        } else {
+            match api::prompt::get_lang(self.ai.as_deref()).await {
+                Ok(lang) => println!("{}", lang.cyan().bold()),
+                Err(err) => println!("{}", err.to_string().red()),
+            }
+        }
+    }
+}
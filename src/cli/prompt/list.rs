@@ -0,0 +1,45 @@
+use aiter::*;
+use colored::Colorize;
+use tabled::Table;
+
+use crate::cli;
+
+#[derive(clap::Args)]
+pub struct PromptListCommand {
+    #[arg(
+        long = "ai",
+        value_name = "AI",
+        help = "List prompts in the language used by the AI"
+    )]
+    ai: Option<String>,
+
+    #[arg(
+        short = 'l',
+        long = "lang",
+        help = "Language of prompts, currently supported languages: en/zh"
+    )]
+    lang: Option<String>,
+}
+
+impl PromptListCommand {
+    pub async fn exec(&self) {
+        if !cli::is_ai_valid(self.ai.as_deref()).await {
+            return;
+        }
+
+        let Some(lang) = cli::prompt::get_lang(self.lang.as_deref(), self.ai.as_deref()).await
+        else {
+            return;
+        };
+
+        match api::prompt::list(&lang).await {
+            Ok(rows) => {
+                println!("Language: {}", lang.cyan().bold());
+                println!("{}", Table::new(rows));
+            }
+            Err(err) => {
+                println!("{}", err.to_string().red());
+            }
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use aiter::*;
+use colored::Colorize;
+
+use crate::cli;
+
+#[derive(clap::Args)]
+pub struct PromptShowCommand {
+    #[arg(
+        long = "ai",
+        value_name = "AI",
+        help = "Show the prompt in the language used by the AI"
+    )]
+    ai: Option<String>,
+
+    #[arg(
+        short = 'l',
+        long = "lang",
+        help = "Language of prompts, currently supported languages: en/zh"
+    )]
+    lang: Option<String>,
+
+    name: String,
+}
+
+impl PromptShowCommand {
+    pub async fn exec(&self) {
+        if !cli::is_ai_valid(self.ai.as_deref()).await {
+            return;
+        }
+
+        let Some(lang) = cli::prompt::get_lang(self.lang.as_deref(), self.ai.as_deref()).await
+        else {
+            return;
+        };
+
+        match api::prompt::show(&self.name, &lang).await {
+            Ok(template) => {
+                println!("{template}");
+            }
+            Err(err) => {
+                println!("{}", err.to_string().red());
+            }
+        }
+    }
+}
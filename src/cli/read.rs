@@ -2,6 +2,7 @@ use std::{fs::read_dir, path::Path};
 
 use aiter::{api::learn::*, *};
 use colored::Colorize;
+use ignore::WalkBuilder;
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
 use tokio::{sync::mpsc, time::Duration};
@@ -20,7 +21,7 @@ pub struct ReadCommand {
     #[arg(
         short = 'f',
         long = "format",
-        help = "Specify the data source format rather than judging by suffix, currently supported formats: csv/docx/epub/md/pdf/txt/xlsx"
+        help = "Specify the data source format rather than judging by suffix, currently supported formats: csv/docx/epub/geojson/html/md/odp/pdf/pptx/txt/xlsx and source code such as c/go/java/js/py/rs/ts"
     )]
     format: Option<String>,
 
@@ -31,7 +32,14 @@ pub struct ReadCommand {
     )]
     keep: bool,
 
-    #[clap(required = true, help = "Source file or directory")]
+    #[arg(
+        short = 'r',
+        long = "recursive",
+        help = "Read directories recursively, files ignored by `.gitignore` are skipped"
+    )]
+    recursive: bool,
+
+    #[clap(required = true, help = "Source file, directory or HTTP URL")]
     sources: Vec<String>,
 }
 
@@ -43,7 +51,11 @@ impl ReadCommand {
 
         for source in &self.sources {
             let source_path = Path::new(source);
-            if source_path.is_dir() {
+            if is_url_source(source) {
+                self.exec_read_from_url(source).await;
+            } else if source_path.is_dir() && self.recursive {
+                self.exec_read_from_dir_recursively(source_path).await;
+            } else if source_path.is_dir() {
                 if let Ok(entries) = read_dir(source_path) {
                     let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
                     entries.par_sort_by(|a, b| {
@@ -64,18 +76,64 @@ impl ReadCommand {
         }
     }
 
+    /// Sources are paths relative to the directory, so files with the same name in different subdirectories are distinguished
+    async fn exec_read_from_dir_recursively(&self, dir_path: &Path) {
+        let walker = WalkBuilder::new(dir_path)
+            .require_git(false)
+            .sort_by_file_name(|a, b| {
+                utils::text::compare_phonetic(&a.to_string_lossy(), &b.to_string_lossy())
+            })
+            .build();
+
+        for entry in walker.filter_map(|entry| entry.ok()) {
+            if !entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+            {
+                continue;
+            }
+
+            // Repositories are full of files that can not be read, skip them quietly
+            let path = entry.path();
+            if self.format.is_none()
+                && !path
+                    .extension()
+                    .is_some_and(|ext| is_format_supported(&ext.to_string_lossy()))
+            {
+                continue;
+            }
+
+            let filename = path
+                .strip_prefix(dir_path)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string();
+            self.exec_read(&filename, &path.to_string_lossy()).await;
+        }
+    }
+
     async fn exec_read_from_file(&self, path: &Path) {
         let filename = utils::fs::extract_filename_from_path(path);
         if filename.starts_with('.') {
             return;
         }
 
+        self.exec_read(&filename, &path.to_string_lossy()).await;
+    }
+
+    async fn exec_read_from_url(&self, url: &str) {
+        self.exec_read(url, url).await;
+    }
+
+    /// The source is either a file path or an HTTP URL, the filename of a file is kept as the source of doc
+    async fn exec_read(&self, filename: &str, source: &str) {
         let bot_name = self.ai.clone().unwrap_or("~".to_string()).cyan();
 
         let (event_sender, mut event_receiver) = mpsc::channel::<ReadEvent>(CHANNEL_BUFFER_DEFAULT);
 
         let ai = self.ai.clone();
-        let path_buf = path.to_path_buf();
+        let source_name = filename.to_string();
+        let source = source.to_string();
         let format = self.format.clone();
         let keep = self.keep;
 
@@ -84,16 +142,28 @@ impl ReadCommand {
             .expect("Spawn mem write error");
 
         let handle = tokio::spawn(async move {
-            read_doc(
-                ai.as_deref(),
-                &path_buf,
-                None,
-                format.as_deref(),
-                keep,
-                mem_write_event_sender,
-                Some(event_sender),
-            )
-            .await
+            if is_url_source(&source) {
+                read_url(
+                    ai.as_deref(),
+                    &source,
+                    format.as_deref(),
+                    keep,
+                    mem_write_event_sender,
+                    Some(event_sender),
+                )
+                .await
+            } else {
+                read_doc(
+                    ai.as_deref(),
+                    Path::new(&source),
+                    Some(&source_name),
+                    format.as_deref(),
+                    keep,
+                    mem_write_event_sender,
+                    Some(event_sender),
+                )
+                .await
+            }
         });
 
         let spinner = ProgressBar::new_spinner();
@@ -80,6 +80,12 @@ pub struct ServeCommand {
     )]
     option_digest_deep: bool,
 
+    #[arg(
+        long = "digest-llm",
+        help = "LLM for digesting, the active digest LLM is used if not specified, and then the active chat LLM"
+    )]
+    option_digest_llm: Option<String>,
+
     #[arg(long = "skip-digest", help = "Skip digesting when learning")]
     option_skip_digest: bool,
 }
@@ -115,6 +121,7 @@ impl ServeCommand {
             digest_batch: self.option_digest_batch.max(1),
             digest_concurrent: self.option_digest_concurrent.max(1),
             digest_deep: self.option_digest_deep,
+            digest_llm: self.option_digest_llm.clone(),
             skip_digest: self.option_skip_digest,
         };
 
@@ -172,16 +179,19 @@ impl ServeCommand {
                         )
                         .service(
                             scope("/chat")
+                                .service(web::api::chat::index_multipart)
                                 .service(web::api::chat::index)
                                 .service(web::api::chat::clear)
                                 .service(web::api::chat::delete)
-                                .service(web::api::chat::history),
+                                .service(web::api::chat::history)
+                                .service(web::api::chat::stop),
                         )
                         .service(
                             scope("/doc")
                                 .service(web::api::doc::count_part)
                                 .service(web::api::doc::delete)
                                 .service(web::api::doc::learn)
+                                .service(web::api::doc::learn_url)
                                 .service(web::api::doc::list)
                                 .service(web::api::doc::list_by_ids)
                                 .service(web::api::doc::list_digesting_ids)
@@ -190,12 +200,16 @@ impl ServeCommand {
                         .service(
                             scope("/llm")
                                 .service(web::api::llm::active)
+                                .service(web::api::llm::check)
                                 .service(web::api::llm::config)
                                 .service(web::api::llm::delete)
                                 .service(web::api::llm::edit)
                                 .service(web::api::llm::list)
                                 .service(web::api::llm::list_actived_names)
-                                .service(web::api::llm::test_chat),
+                                .service(web::api::llm::list_utilizations)
+                                .service(web::api::llm::models)
+                                .service(web::api::llm::test_chat)
+                                .service(web::api::llm::usage),
                         )
                         .service(
                             scope("/mem")
@@ -285,7 +299,8 @@ fn spawn_digest_queue(
                 tokio::spawn(async move {
                     let options = DigestOptions::default()
                         .with_concurrent(app_config.digest_concurrent)
-                        .with_deep(app_config.digest_deep);
+                        .with_deep(app_config.digest_deep)
+                        .with_llm(app_config.digest_llm.clone());
 
                     let _ = api::learn::digest_doc(
                         event.ai.as_deref(),
@@ -3,12 +3,13 @@ use std::fmt::Debug;
 use crate::{
     Tokenizer,
     content::{
-        doc::{markdown::MarkdownDoc, sheet::SheetDoc, text::TextDoc},
+        doc::{code::CodeDoc, markdown::MarkdownDoc, sheet::SheetDoc, text::TextDoc},
         seg::SegContent,
     },
     error::AiterResult,
 };
 
+pub mod code;
 pub mod markdown;
 pub mod sheet;
 pub mod text;
@@ -28,6 +29,7 @@ pub trait DocContent: Debug + Send + Sync {
 #[derive(strum::Display, strum::EnumString, Debug)]
 #[strum(ascii_case_insensitive)]
 pub enum DocContentType {
+    Code,
     Markdown,
     Sheet,
     Text,
@@ -35,6 +37,7 @@ pub enum DocContentType {
 
 pub fn decode_content(content: &[u8], content_type: &str) -> AiterResult<Box<dyn DocContent>> {
     match content_type.parse::<DocContentType>()? {
+        DocContentType::Code => Ok(Box::new(CodeDoc::try_from_bytes(content)?)),
         DocContentType::Markdown => Ok(Box::new(MarkdownDoc::try_from_bytes(content)?)),
         DocContentType::Sheet => Ok(Box::new(SheetDoc::try_from_bytes(content)?)),
         DocContentType::Text => Ok(Box::new(TextDoc::try_from_bytes(content)?)),
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    SPLIT_TOKENS_OF_SEG, TRUNCATE_PREVIEW, Tokenizer,
+    content::{
+        doc::{DocContent, DocContentType},
+        seg::{SegContent, code::CodeSegContent},
+    },
+    error::AiterResult,
+    utils::{code::split_code_by_max_tokens, compress, text::truncate_format},
+};
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct CodeDoc {
+    pub path: String,
+    pub language: String,
+    pub code: String,
+}
+
+impl DocContent for CodeDoc {
+    fn get_title(&self) -> Option<String> {
+        Some(self.path.clone())
+    }
+
+    fn get_preview(&self) -> String {
+        truncate_format(self.code.trim(), TRUNCATE_PREVIEW, false)
+    }
+
+    fn get_type(&self) -> DocContentType {
+        DocContentType::Code
+    }
+
+    fn split(&self, tokenizer: &Tokenizer) -> Vec<Vec<Box<dyn SegContent>>> {
+        let segs =
+            split_code_by_max_tokens(&self.code, &self.language, SPLIT_TOKENS_OF_SEG, tokenizer)
+                .into_iter()
+                .map(|chunk| {
+                    Box::new(CodeSegContent {
+                        path: self.path.clone(),
+                        language: self.language.clone(),
+                        symbols: chunk.symbols,
+                        code: chunk.code,
+                    }) as Box<dyn SegContent>
+                })
+                .collect();
+
+        vec![segs]
+    }
+
+    fn to_string(&self) -> String {
+        format!("# {}\n\n{}", self.path, self.code)
+    }
+
+    fn try_from_bytes(bytes: &[u8]) -> AiterResult<Self> {
+        let json = compress::decode(bytes)?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    fn try_into_bytes(&self) -> AiterResult<Vec<u8>> {
+        let json = serde_json::to_vec(self)?;
+        Ok(compress::encode(&json)?)
+    }
+}
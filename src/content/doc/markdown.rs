@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     SPLIT_TOKENS_OF_SEG, TRUNCATE_PREVIEW, Tokenizer,
     content::{
-        doc::{DocContent, DocContentType},
+        doc::{DocContent, DocContentType, sheet::DocSheet},
         seg::{SegContent, text::TextSegContent},
     },
     error::AiterResult,
@@ -18,6 +18,10 @@ pub struct MarkdownDoc {
     pub title: Option<String>,
     pub pages: Vec<String>,
     pub outlines: Vec<MarkdownDocOutline>,
+
+    /// Tables kept apart from the text of pages, split as sheet segments
+    #[serde(default)]
+    pub sheets: Vec<DocSheet>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -45,16 +49,23 @@ impl DocContent for MarkdownDoc {
     fn split(&self, tokenizer: &Tokenizer) -> Vec<Vec<Box<dyn SegContent>>> {
         self.pages
             .iter()
-            .map(|page| {
+            .enumerate()
+            .map(|(index, page)| {
                 let text = page.to_string();
-                split_markdown_by_max_tokens(&text, SPLIT_TOKENS_OF_SEG, tokenizer)
+                let mut segs = split_markdown_by_max_tokens(&text, SPLIT_TOKENS_OF_SEG, tokenizer)
                     .iter()
                     .map(|s| {
                         Box::new(TextSegContent {
                             text: s.to_string(),
                         }) as Box<dyn SegContent>
                     })
-                    .collect::<Vec<_>>()
+                    .collect::<Vec<_>>();
+
+                for sheet in self.sheets.iter().filter(|sheet| sheet.page == index) {
+                    segs.extend(sheet.data.split(tokenizer));
+                }
+
+                segs
             })
             .collect()
     }
@@ -67,9 +78,14 @@ impl DocContent for MarkdownDoc {
             s.push_str("\n\n");
         }
 
-        for page in &self.pages {
+        for (index, page) in self.pages.iter().enumerate() {
             s.push_str(page);
             s.push_str("\n\n");
+
+            for sheet in self.sheets.iter().filter(|sheet| sheet.page == index) {
+                s.push_str(&sheet.data.to_string());
+                s.push_str("\n\n");
+            }
         }
 
         s
@@ -27,6 +27,13 @@ pub struct SheetData {
     pub rows: Vec<Vec<String>>,
 }
 
+/// Table kept apart from the text of a page in other docs, split as sheet segments
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct DocSheet {
+    pub page: usize,
+    pub data: SheetData,
+}
+
 impl DocContent for SheetDoc {
     fn get_title(&self) -> Option<String> {
         None
@@ -62,51 +69,7 @@ impl DocContent for SheetDoc {
     fn split(&self, tokenizer: &Tokenizer) -> Vec<Vec<Box<dyn SegContent>>> {
         self.pages
             .iter()
-            .map(|(_, data)| {
-                let mut segs = vec![];
-
-                // Split columns, make each column as a segment, split the column again if it is bigger than the token window
-                {
-                    let col_num = if let Some(headers) = &data.headers {
-                        headers.len()
-                    } else {
-                        data.rows.first().map(|row| row.len()).unwrap_or(0)
-                    };
-
-                    if col_num > 1 {
-                        for i in 0..col_num {
-                            let header_vec: Option<Vec<String>> =
-                                if let Some(headers) = &data.headers {
-                                    headers.get(i).map(|header| vec![header.to_string()])
-                                } else {
-                                    None
-                                };
-
-                            let rows: Vec<Vec<String>> = data
-                                .rows
-                                .iter()
-                                .map(|row| {
-                                    row.get(i)
-                                        .map_or(vec!["".to_string()], |v| vec![v.to_string()])
-                                })
-                                .collect();
-
-                            segs.extend(split_to_segs_by_max_tokens(&header_vec, &rows, tokenizer));
-                        }
-                    }
-                }
-
-                // Split rows, make several rows as a segment
-                if data.rows.len() > 1 {
-                    segs.extend(split_to_segs_by_max_tokens(
-                        &data.headers,
-                        &data.rows,
-                        tokenizer,
-                    ));
-                }
-
-                segs
-            })
+            .map(|(_, data)| data.split(tokenizer))
             .filter(|segs| !segs.is_empty())
             .collect()
     }
@@ -136,6 +99,54 @@ impl DocContent for SheetDoc {
     }
 }
 
+impl SheetData {
+    /// Split into segments by columns and by rows
+    pub fn split(&self, tokenizer: &Tokenizer) -> Vec<Box<dyn SegContent>> {
+        let mut segs = vec![];
+
+        // Split columns, make each column as a segment, split the column again if it is bigger than the token window
+        {
+            let col_num = if let Some(headers) = &self.headers {
+                headers.len()
+            } else {
+                self.rows.first().map(|row| row.len()).unwrap_or(0)
+            };
+
+            if col_num > 1 {
+                for i in 0..col_num {
+                    let header_vec: Option<Vec<String>> = if let Some(headers) = &self.headers {
+                        headers.get(i).map(|header| vec![header.to_string()])
+                    } else {
+                        None
+                    };
+
+                    let rows: Vec<Vec<String>> = self
+                        .rows
+                        .iter()
+                        .map(|row| {
+                            row.get(i)
+                                .map_or(vec!["".to_string()], |v| vec![v.to_string()])
+                        })
+                        .collect();
+
+                    segs.extend(split_to_segs_by_max_tokens(&header_vec, &rows, tokenizer));
+                }
+            }
+        }
+
+        // Split rows, make several rows as a segment
+        if self.rows.len() > 1 {
+            segs.extend(split_to_segs_by_max_tokens(
+                &self.headers,
+                &self.rows,
+                tokenizer,
+            ));
+        }
+
+        segs
+    }
+}
+
 fn split_to_segs_by_max_tokens(
     headers: &Option<Vec<String>>,
     rows: &[Vec<String>],
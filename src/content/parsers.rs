@@ -1,7 +1,12 @@
+pub mod code;
 pub mod csv;
 pub mod docx;
 pub mod epub;
+pub mod geojson;
+pub mod html;
 pub mod md;
+pub mod odp;
 pub mod pdf;
+pub mod pptx;
 pub mod txt;
 pub mod xlsx;
@@ -0,0 +1,30 @@
+use std::{fs::File, io::Read, path::Path};
+
+use chardetng::EncodingDetector;
+
+use crate::{
+    content::doc::code::CodeDoc,
+    error::{AiterError, AiterResult},
+};
+
+pub fn to_code_doc(path: &Path, source: &str, language: &str) -> AiterResult<CodeDoc> {
+    let mut file = File::open(path)?;
+
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(&buffer, false);
+    let encoding = detector.guess(None, true);
+
+    let (code, _, _) = encoding.decode(&buffer);
+    if code.trim().is_empty() {
+        return Err(AiterError::Invalid(format!("{source} is empty")));
+    }
+
+    Ok(CodeDoc {
+        path: source.to_string(),
+        language: language.to_string(),
+        code: code.to_string(),
+    })
+}
@@ -1,4 +1,7 @@
-use std::path::{Path, PathBuf};
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
 
 use csv::Reader;
 
@@ -9,8 +12,17 @@ use crate::{
 };
 
 pub fn to_sheet_doc(path: &Path, source: &str) -> AiterResult<SheetDoc> {
-    let mut rdr = Reader::from_path(path)?;
+    let rdr = Reader::from_path(path)?;
+
+    Ok(SheetDoc {
+        pages: vec![(
+            extract_filestem_from_path(&PathBuf::from(source)),
+            read_sheet_data(rdr),
+        )],
+    })
+}
 
+pub(super) fn read_sheet_data<R: Read>(mut rdr: Reader<R>) -> SheetData {
     let headers = rdr
         .headers()
         .ok()
@@ -25,12 +37,5 @@ pub fn to_sheet_doc(path: &Path, source: &str) -> AiterResult<SheetDoc> {
         })
         .collect();
 
-    let sheet_data = SheetData { headers, rows };
-
-    Ok(SheetDoc {
-        pages: vec![(
-            extract_filestem_from_path(&PathBuf::from(source)),
-            sheet_data,
-        )],
-    })
+    SheetData { headers, rows }
 }
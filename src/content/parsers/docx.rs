@@ -40,6 +40,7 @@ pub fn to_text_doc(path: &Path, source: &str) -> AiterResult<TextDoc> {
         title: None,
         pages: vec![page],
         outlines: vec![],
+        sheets: vec![],
     })
 }
 
@@ -47,6 +47,7 @@ pub fn to_text_doc(path: &Path, source: &str) -> AiterResult<TextDoc> {
         title,
         pages,
         outlines,
+        sheets: vec![],
     })
 }
 
@@ -0,0 +1,29 @@
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+use csv::Reader;
+
+use crate::{
+    content::{doc::sheet::SheetDoc, parsers::csv::read_sheet_data},
+    error::{AiterError, AiterResult},
+    utils::{fs::extract_filestem_from_path, geo::geojson_to_csv},
+};
+
+/// Properties of features are read as rows, geometries are not included in the sheet
+pub fn to_sheet_doc(path: &Path, source: &str) -> AiterResult<SheetDoc> {
+    let csv_str = geojson_to_csv(&read_to_string(path)?)?;
+
+    let sheet_data = read_sheet_data(Reader::from_reader(csv_str.as_bytes()));
+    if sheet_data.rows.is_empty() {
+        return Err(AiterError::Invalid(format!("{source} has no features")));
+    }
+
+    Ok(SheetDoc {
+        pages: vec![(
+            extract_filestem_from_path(&PathBuf::from(source)),
+            sheet_data,
+        )],
+    })
+}
@@ -0,0 +1,581 @@
+use std::{fs::File, io::Read, path::Path, sync::LazyLock};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chardetng::EncodingDetector;
+use scraper::{ElementRef, Html, Node, Selector};
+
+use crate::{
+    content::doc::{
+        markdown::{MarkdownDoc, MarkdownDocOutline},
+        sheet::{DocSheet, SheetData},
+    },
+    error::{AiterError, AiterResult},
+};
+
+/// Parse HTML, XHTML or a saved MHTML web page
+pub fn to_markdown_doc(path: &Path, source: &str) -> AiterResult<MarkdownDoc> {
+    let mut file = File::open(path)?;
+
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+
+    let buffer = extract_html_from_mhtml(&buffer).unwrap_or(buffer);
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(&buffer, true);
+    let encoding = detector.guess(None, true);
+    let (html, _, _) = encoding.decode(&buffer);
+
+    parse_html(&html, source)
+}
+
+enum HtmlBlock {
+    Heading(usize, String),
+    Text(String),
+    Sheet(SheetData),
+}
+
+static BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "ol",
+    "p",
+    "section",
+    "summary",
+    "td",
+    "th",
+    "tr",
+    "ul",
+];
+
+// Controls are dropped but not forms, which may wrap the content of the page
+static IGNORE_TAGS: &[&str] = &[
+    "button", "canvas", "head", "iframe", "img", "input", "nav", "noscript", "script", "select",
+    "style", "svg", "template", "textarea",
+];
+
+static SELECTOR_OG_TITLE: LazyLock<Selector> = LazyLock::new(|| {
+    Selector::parse(r#"meta[property="og:title"]"#).expect("OG_TITLE selector is invalid")
+});
+static SELECTOR_TITLE: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("title").expect("TITLE selector is invalid"));
+static SELECTOR_TR: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("tr").expect("TR selector is invalid"));
+
+#[derive(Default)]
+struct HtmlWalker {
+    blocks: Vec<HtmlBlock>,
+    inline: String,
+}
+
+impl HtmlWalker {
+    fn flush(&mut self) {
+        let text = self
+            .inline
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !text.is_empty() {
+            self.blocks.push(HtmlBlock::Text(text));
+        }
+
+        self.inline.clear();
+    }
+
+    fn walk(&mut self, element: &ElementRef) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => self.inline.push_str(&text.replace('\n', " ")),
+                Node::Element(_) => {
+                    if let Some(child_element) = ElementRef::wrap(child) {
+                        self.walk_element(&child_element);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn walk_element(&mut self, element: &ElementRef) {
+        let name = element.value().name();
+        if IGNORE_TAGS.contains(&name) {
+            return;
+        }
+
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.flush();
+
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                let text = collapse_text(element);
+                if !text.is_empty() {
+                    self.blocks.push(HtmlBlock::Heading(level, text));
+                }
+            }
+            "table" => {
+                self.flush();
+
+                // Tables only used for layout are read as text
+                if let Some(data) = to_sheet_data(element) {
+                    self.blocks.push(HtmlBlock::Sheet(data));
+                } else {
+                    self.walk(element);
+                    self.flush();
+                }
+            }
+            "pre" => {
+                self.flush();
+
+                let text = element.text().collect::<String>();
+                if !text.trim().is_empty() {
+                    self.blocks.push(HtmlBlock::Text(format!(
+                        "```\n{}\n```",
+                        text.trim_matches('\n')
+                    )));
+                }
+            }
+            "br" => self.inline.push('\n'),
+            "code" => {
+                let text = collapse_text(element);
+                if !text.is_empty() {
+                    self.inline.push_str(&format!("`{text}`"));
+                }
+            }
+            "li" => {
+                self.flush();
+                self.inline.push_str("- ");
+                self.walk(element);
+                self.flush();
+            }
+            _ if BLOCK_TAGS.contains(&name) => {
+                self.flush();
+                self.walk(element);
+                self.flush();
+            }
+            _ => self.walk(element),
+        }
+    }
+}
+
+fn parse_html(html: &str, source: &str) -> AiterResult<MarkdownDoc> {
+    let document = Html::parse_document(html);
+
+    let mut walker = HtmlWalker::default();
+    walker.walk(&document.root_element());
+    walker.flush();
+
+    let blocks = walker.blocks;
+    if blocks.is_empty() {
+        return Err(AiterError::Invalid(format!("{source} is empty")));
+    }
+
+    let headings: Vec<usize> = blocks
+        .iter()
+        .filter_map(|block| match block {
+            HtmlBlock::Heading(level, _) => Some(*level),
+            _ => None,
+        })
+        .collect();
+
+    // A single top heading is the title of the whole page rather than a section
+    let top_level = headings.iter().min().copied();
+    let heading_title = top_level.and_then(|top_level| {
+        if headings.iter().filter(|level| **level == top_level).count() == 1 {
+            blocks.iter().find_map(|block| match block {
+                HtmlBlock::Heading(level, text) if *level == top_level => Some(text.clone()),
+                _ => None,
+            })
+        } else {
+            None
+        }
+    });
+    let section_level = if heading_title.is_some() {
+        headings
+            .iter()
+            .filter(|level| Some(**level) != top_level)
+            .min()
+    } else {
+        top_level.as_ref()
+    }
+    .copied();
+
+    let title = document
+        .select(&SELECTOR_TITLE)
+        .next()
+        .map(|element| collapse_text(&element))
+        .filter(|title| !title.is_empty())
+        .or_else(|| {
+            document
+                .select(&SELECTOR_OG_TITLE)
+                .next()
+                .and_then(|element| element.attr("content"))
+                .map(|title| title.trim().to_string())
+                .filter(|title| !title.is_empty())
+        })
+        .or(heading_title);
+
+    let mut pages: Vec<String> = vec![];
+    let mut sheets: Vec<DocSheet> = vec![];
+    let mut outline_headings: Vec<(usize, String, usize)> = vec![];
+
+    for block in blocks {
+        let page_has_content = pages.last().is_some_and(|page| !page.trim().is_empty())
+            || sheets
+                .last()
+                .is_some_and(|sheet| sheet.page + 1 == pages.len());
+
+        match block {
+            HtmlBlock::Heading(level, text) => {
+                if section_level.is_some_and(|section_level| level <= section_level) {
+                    if page_has_content || pages.is_empty() {
+                        pages.push(String::new());
+                    }
+                } else if pages.is_empty() {
+                    pages.push(String::new());
+                }
+
+                if section_level.is_some_and(|section_level| level >= section_level) {
+                    outline_headings.push((level, text.clone(), pages.len() - 1));
+                }
+
+                if let Some(page) = pages.last_mut() {
+                    page.push_str(&format!("{} {}\n\n", "#".repeat(level), text));
+                }
+            }
+            HtmlBlock::Text(text) => {
+                if pages.is_empty() {
+                    pages.push(String::new());
+                }
+
+                if let Some(page) = pages.last_mut() {
+                    page.push_str(&text);
+                    page.push_str("\n\n");
+                }
+            }
+            HtmlBlock::Sheet(data) => {
+                if pages.is_empty() {
+                    pages.push(String::new());
+                }
+
+                sheets.push(DocSheet {
+                    page: pages.len() - 1,
+                    data,
+                });
+            }
+        }
+    }
+
+    Ok(MarkdownDoc {
+        title,
+        pages: pages.iter().map(|page| page.trim().to_string()).collect(),
+        outlines: to_outlines(&outline_headings),
+        sheets,
+    })
+}
+
+fn collapse_text(element: &ElementRef) -> String {
+    element
+        .text()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Take the first text/html part of a MIME multipart archive, returns None if it is not MHTML.
+/// The archive is split on bytes, so that parts in encodings other than UTF-8 are kept as is for detection.
+fn extract_html_from_mhtml(bytes: &[u8]) -> Option<Vec<u8>> {
+    let content = replace_bytes(bytes, b"\r\n", b"\n");
+
+    let (headers, body) = split_once_bytes(&content, b"\n\n")?;
+    let headers = String::from_utf8_lossy(headers);
+    if !headers.to_lowercase().contains("multipart/related") {
+        return None;
+    }
+
+    let boundary = headers
+        .split(';')
+        .map(|s| s.trim())
+        .find_map(|s| s.strip_prefix("boundary="))?
+        .lines()
+        .next()?
+        .trim_matches('"');
+
+    let delimiter = format!("--{boundary}");
+    let mut rest = body;
+    while !rest.is_empty() {
+        let (part, next) =
+            split_once_bytes(rest, delimiter.as_bytes()).unwrap_or((rest, &[] as &[u8]));
+        rest = next;
+
+        let part = &part[part.iter().position(|b| *b != b'\n').unwrap_or(part.len())..];
+        if let Some((part_headers, part_body)) = split_once_bytes(part, b"\n\n") {
+            let part_headers = String::from_utf8_lossy(part_headers).to_lowercase();
+            if !part_headers.contains("content-type: text/html") {
+                continue;
+            }
+
+            return if part_headers.contains("content-transfer-encoding: quoted-printable") {
+                Some(decode_quoted_printable(part_body))
+            } else if part_headers.contains("content-transfer-encoding: base64") {
+                STANDARD
+                    .decode(
+                        part_body
+                            .iter()
+                            .filter(|b| !b.is_ascii_whitespace())
+                            .copied()
+                            .collect::<Vec<_>>(),
+                    )
+                    .ok()
+            } else {
+                Some(part_body.to_vec())
+            };
+        }
+    }
+
+    None
+}
+
+fn decode_quoted_printable(bytes: &[u8]) -> Vec<u8> {
+    let bytes = replace_bytes(bytes, b"=\n", b"");
+
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'=' && i + 3 <= bytes.len() {
+            if let Ok(byte) = u8::from_str_radix(&String::from_utf8_lossy(&bytes[i + 1..i + 3]), 16)
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    decoded
+}
+
+fn replace_bytes(bytes: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    let mut replaced = Vec::with_capacity(bytes.len());
+    let mut rest = bytes;
+    while let Some((before, after)) = split_once_bytes(rest, from) {
+        replaced.extend_from_slice(before);
+        replaced.extend_from_slice(to);
+        rest = after;
+    }
+    replaced.extend_from_slice(rest);
+
+    replaced
+}
+
+fn split_once_bytes<'a>(bytes: &'a [u8], delimiter: &[u8]) -> Option<(&'a [u8], &'a [u8])> {
+    let index = bytes
+        .windows(delimiter.len())
+        .position(|window| window == delimiter)?;
+
+    Some((&bytes[..index], &bytes[index + delimiter.len()..]))
+}
+
+fn to_outlines(headings: &[(usize, String, usize)]) -> Vec<MarkdownDocOutline> {
+    let mut outlines: Vec<MarkdownDocOutline> = vec![];
+
+    let mut i = 0;
+    while i < headings.len() {
+        let (level, title, page) = &headings[i];
+        let end = headings[i + 1..]
+            .iter()
+            .position(|(sub_level, _, _)| sub_level <= level)
+            .map_or(headings.len(), |n| i + 1 + n);
+
+        outlines.push(MarkdownDocOutline {
+            title: title.clone(),
+            page: *page,
+            children: to_outlines(&headings[i + 1..end]),
+        });
+
+        i = end;
+    }
+
+    outlines
+}
+
+/// Returns None if the table has less than 2 columns or no data rows
+fn to_sheet_data(table: &ElementRef) -> Option<SheetData> {
+    let mut headers: Option<Vec<String>> = None;
+    let mut rows: Vec<Vec<String>> = vec![];
+
+    for tr in table.select(&SELECTOR_TR) {
+        // Rows of nested tables belong to those tables
+        let owner = tr
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .find(|element| element.value().name() == "table");
+        if owner.map(|element| element.id()) != Some(table.id()) {
+            continue;
+        }
+
+        let cells: Vec<ElementRef> = tr
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|element| ["td", "th"].contains(&element.value().name()))
+            .collect();
+        if cells.is_empty() {
+            continue;
+        }
+
+        let texts: Vec<String> = cells.iter().map(collapse_text).collect();
+        let in_thead = tr
+            .parent()
+            .and_then(ElementRef::wrap)
+            .is_some_and(|element| element.value().name() == "thead");
+        let all_th = cells.iter().all(|cell| cell.value().name() == "th");
+
+        if headers.is_none() && rows.is_empty() && (in_thead || all_th) {
+            headers = Some(texts);
+        } else {
+            rows.push(texts);
+        }
+    }
+
+    let col_num = headers
+        .iter()
+        .chain(rows.iter())
+        .map(|row| row.len())
+        .max()
+        .unwrap_or(0);
+    if col_num < 2 || rows.is_empty() {
+        return None;
+    }
+
+    Some(SheetData { headers, rows })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_html() {
+        let doc = parse_html(
+            r##"
+            <!DOCTYPE html>
+            <html>
+                <head>
+                    <title>Fruits</title>
+                    <style>body { color: red; }</style>
+                </head>
+                <body>
+                    <nav><a href="/">Home</a></nav>
+                    <h1>All about fruits</h1>
+                    <form><p>Fruits are <b>sweet</b>.</p><input value="Search"><button>Go</button></form>
+                    <h2>Apple</h2>
+                    <p>Apples are red.</p>
+                    <h3>Varieties</h3>
+                    <table>
+                        <thead><tr><th>Name</th><th>Color</th></tr></thead>
+                        <tbody>
+                            <tr><td>Fuji</td><td>Red</td></tr>
+                            <tr><td>Granny Smith</td><td>Green</td></tr>
+                        </tbody>
+                    </table>
+                    <h2>Banana</h2>
+                    <ul><li>Yellow</li><li>Long</li></ul>
+                    <script>alert("Hi");</script>
+                </body>
+            </html>
+            "##,
+            "fruits.html",
+        )
+        .unwrap();
+
+        assert_eq!(doc.title, Some("Fruits".to_string()));
+        assert_eq!(doc.pages.len(), 3);
+        assert_eq!(doc.pages[0], "# All about fruits\n\nFruits are sweet.");
+        assert_eq!(doc.pages[2], "## Banana\n\n- Yellow\n\n- Long");
+
+        assert_eq!(doc.outlines.len(), 2);
+        assert_eq!(doc.outlines[0].title, "Apple");
+        assert_eq!(doc.outlines[0].page, 1);
+        assert_eq!(doc.outlines[0].children[0].title, "Varieties");
+
+        assert_eq!(doc.sheets.len(), 1);
+        assert_eq!(doc.sheets[0].page, 1);
+        assert_eq!(
+            doc.sheets[0].data.headers,
+            Some(vec!["Name".to_string(), "Color".to_string()])
+        );
+        assert_eq!(doc.sheets[0].data.rows.len(), 2);
+    }
+
+    #[test]
+    fn test_extract_html_from_mhtml() {
+        let mhtml = [
+            "From: <Saved by Blink>",
+            "MIME-Version: 1.0",
+            "Content-Type: multipart/related;",
+            "\ttype=\"text/html\";",
+            "\tboundary=\"----MultipartBoundary--abc----\"",
+            "",
+            "------MultipartBoundary--abc----",
+            "Content-Type: text/html",
+            "Content-Transfer-Encoding: quoted-printable",
+            "",
+            "<html><body><p class=3D\"a\">Caf=C3=A9 au =",
+            "lait</p></body></html>",
+            "------MultipartBoundary--abc----",
+            "Content-Type: image/png",
+            "Content-Transfer-Encoding: base64",
+            "",
+            "iVBORw0KGgo=",
+            "------MultipartBoundary--abc------",
+        ]
+        .join("\r\n");
+
+        assert_eq!(
+            String::from_utf8(extract_html_from_mhtml(mhtml.as_bytes()).unwrap()).unwrap(),
+            "<html><body><p class=\"a\">Café au lait</p></body></html>\n"
+        );
+        assert!(extract_html_from_mhtml(b"<html></html>").is_none());
+
+        // 8bit parts are not decoded as UTF-8, so that GBK text is detected later
+        let gbk_body = b"<html><body><p>\xc4\xe3\xba\xc3</p></body></html>";
+        let mhtml = [
+            b"Content-Type: multipart/related; boundary=\"b\"\r\n\r\n--b\r\n".as_slice(),
+            b"Content-Type: text/html; charset=gbk\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+            gbk_body,
+            b"\r\n--b--\r\n",
+        ]
+        .concat();
+        assert_eq!(
+            extract_html_from_mhtml(&mhtml).unwrap(),
+            [gbk_body.as_slice(), b"\n"].concat()
+        );
+    }
+
+    #[test]
+    fn test_decode_quoted_printable() {
+        assert_eq!(decode_quoted_printable(b"Caf=C3=A9"), "Café".as_bytes());
+        assert_eq!(decode_quoted_printable(b"a=3D=\nb=3"), b"a=b=3");
+        assert_eq!(decode_quoted_printable(b"="), b"=");
+    }
+}
@@ -169,6 +169,7 @@ pub fn to_markdown_doc(path: &Path, _source: &str) -> AiterResult<MarkdownDoc> {
         title,
         pages,
         outlines,
+        sheets: vec![],
     })
 }
 
@@ -0,0 +1,176 @@
+use std::{fs::File, path::Path};
+
+use quick_xml::{Reader, events::Event};
+use zip::ZipArchive;
+
+use crate::{
+    content::{
+        doc::text::TextDoc,
+        parsers::pptx::{Slide, get_attribute, read_xml_text, read_zip_entry, slides_to_text_doc},
+    },
+    error::{AiterError, AiterResult},
+};
+
+pub fn to_text_doc(path: &Path, source: &str) -> AiterResult<TextDoc> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+
+    let Some(content_xml) = read_zip_entry(&mut archive, "content.xml")? else {
+        return Err(AiterError::Invalid(format!("{source} has no content")));
+    };
+    let slides = parse_content_xml(&content_xml)?;
+
+    let title = match read_zip_entry(&mut archive, "meta.xml")? {
+        Some(meta_xml) => read_xml_text(&meta_xml, "title")?,
+        None => None,
+    };
+
+    slides_to_text_doc(title, slides, source)
+}
+
+fn parse_content_xml(xml: &[u8]) -> AiterResult<Vec<Slide>> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = Vec::new();
+
+    let mut slides: Vec<Slide> = vec![];
+    let mut slide: Option<Slide> = None;
+    let mut in_notes = false;
+
+    let mut shape_class: Option<String> = None;
+    let mut shape_paragraphs: Vec<String> = vec![];
+    let mut paragraph = String::new();
+    let mut paragraph_depth = 0;
+
+    let mut table: Option<Vec<Vec<String>>> = None;
+    let mut row: Vec<String> = vec![];
+    let mut cell: Vec<String> = vec![];
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"page" => slide = Some(Slide::default()),
+                b"notes" => in_notes = true,
+                b"frame" | b"custom-shape" => {
+                    shape_class = get_attribute(&e, "presentation:class");
+                    shape_paragraphs.clear();
+                }
+                b"p" | b"h" => paragraph_depth += 1,
+                b"table" => table = Some(vec![]),
+                b"table-row" => row.clear(),
+                b"table-cell" | b"covered-table-cell" => cell.clear(),
+                _ => {}
+            },
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"s" if paragraph_depth > 0 => {
+                    let count = get_attribute(&e, "text:c")
+                        .and_then(|c| c.parse::<usize>().ok())
+                        .unwrap_or(1);
+                    paragraph.push_str(&" ".repeat(count));
+                }
+                b"tab" if paragraph_depth > 0 => paragraph.push('\t'),
+                b"line-break" if paragraph_depth > 0 => paragraph.push('\n'),
+                b"table-cell" | b"covered-table-cell" => row.push(String::new()),
+                _ => {}
+            },
+            Event::Text(e) if paragraph_depth > 0 => paragraph.push_str(&e.unescape()?),
+            Event::End(e) => match e.local_name().as_ref() {
+                b"page" => {
+                    if let Some(slide) = slide.take() {
+                        slides.push(slide);
+                    }
+                }
+                b"notes" => in_notes = false,
+                b"frame" | b"custom-shape" => {
+                    let text = shape_paragraphs.join("\n");
+                    if let (Some(slide), false) = (&mut slide, text.is_empty()) {
+                        match shape_class.as_deref() {
+                            // Boilerplate repeated on every slide
+                            Some("date-time" | "footer" | "header" | "page-number") => {}
+                            _ if in_notes => slide.notes.push(text),
+                            Some("title") if slide.title.is_none() => {
+                                slide.title = Some(text.replace('\n', " "));
+                            }
+                            _ => slide.texts.push(text),
+                        }
+                    }
+                    shape_class = None;
+                    shape_paragraphs.clear();
+                }
+                b"p" | b"h" => {
+                    paragraph_depth -= 1;
+                    if paragraph_depth == 0 {
+                        let text = paragraph.trim().to_string();
+                        if !text.is_empty() {
+                            if table.is_some() {
+                                cell.push(text);
+                            } else {
+                                shape_paragraphs.push(text);
+                            }
+                        }
+                        paragraph.clear();
+                    }
+                }
+                b"table" => {
+                    if let (Some(slide), Some(table)) = (&mut slide, table.take()) {
+                        slide.tables.push(table);
+                    }
+                }
+                b"table-row" => {
+                    if let Some(table) = &mut table {
+                        table.push(row.clone());
+                    }
+                }
+                b"table-cell" | b"covered-table-cell" => row.push(cell.join(" ")),
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(slides)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_content_xml() {
+        let slides = parse_content_xml(
+            br#"<?xml version="1.0" encoding="UTF-8"?>
+            <office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:draw="urn:oasis:names:tc:opendocument:xmlns:drawing:1.0" xmlns:presentation="urn:oasis:names:tc:opendocument:xmlns:presentation:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0">
+                <office:body><office:presentation>
+                    <draw:page draw:name="page1">
+                        <draw:frame presentation:class="title"><draw:text-box><text:p>Road<text:s/>map</text:p></draw:text-box></draw:frame>
+                        <draw:frame presentation:class="outline"><draw:text-box>
+                            <text:list><text:list-item><text:p>Ship <text:span>v1</text:span></text:p></text:list-item></text:list>
+                        </draw:text-box></draw:frame>
+                        <draw:frame><table:table>
+                            <table:table-row><table:table-cell><text:p>Phase</text:p></table:table-cell><table:table-cell><text:p>Date</text:p></table:table-cell></table:table-row>
+                            <table:table-row><table:table-cell><text:p>Beta</text:p></table:table-cell><table:table-cell/></table:table-row>
+                        </table:table></draw:frame>
+                        <presentation:notes>
+                            <draw:page-thumbnail/>
+                            <draw:frame presentation:class="notes"><draw:text-box><text:p>Keep it short</text:p></draw:text-box></draw:frame>
+                        </presentation:notes>
+                    </draw:page>
+                    <draw:page draw:name="page2"/>
+                </office:presentation></office:body>
+            </office:document-content>"#,
+        )
+        .unwrap();
+
+        assert_eq!(slides.len(), 1);
+        assert_eq!(slides[0].title, Some("Road map".to_string()));
+        assert_eq!(slides[0].texts, vec!["Ship v1".to_string()]);
+        assert_eq!(
+            slides[0].tables,
+            vec![vec![
+                vec!["Phase".to_string(), "Date".to_string()],
+                vec!["Beta".to_string(), "".to_string()],
+            ]]
+        );
+        assert_eq!(slides[0].notes, vec!["Keep it short".to_string()]);
+    }
+}
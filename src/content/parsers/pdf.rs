@@ -47,5 +47,6 @@ pub fn to_text_doc(path: &Path, _source: &str) -> AiterResult<TextDoc> {
         title: None,
         pages,
         outlines,
+        sheets: vec![],
     })
 }
@@ -0,0 +1,461 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek},
+    path::Path,
+};
+
+use natord::compare;
+use quick_xml::{
+    Reader,
+    escape::unescape,
+    events::{BytesStart, Event},
+};
+use zip::ZipArchive;
+
+use crate::{
+    content::doc::{
+        sheet::{DocSheet, SheetData},
+        text::{TextDoc, TextDocOutline},
+    },
+    error::{AiterError, AiterResult},
+};
+
+/// Content of a slide, shared by presentation parsers
+#[derive(Default)]
+pub(super) struct Slide {
+    pub title: Option<String>,
+    pub texts: Vec<String>,
+    pub tables: Vec<Vec<Vec<String>>>,
+    pub notes: Vec<String>,
+}
+
+pub fn to_text_doc(path: &Path, source: &str) -> AiterResult<TextDoc> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+
+    let mut slides: Vec<Slide> = vec![];
+    for slide_path in list_slide_paths(&mut archive)? {
+        let Some(slide_xml) = read_zip_entry(&mut archive, &slide_path)? else {
+            continue;
+        };
+        let mut slide = parse_slide_xml(&slide_xml)?;
+
+        if let Some(notes_path) = find_notes_path(&mut archive, &slide_path)? {
+            if let Some(notes_xml) = read_zip_entry(&mut archive, &notes_path)? {
+                slide.notes = parse_slide_xml(&notes_xml)?.texts;
+            }
+        }
+
+        slides.push(slide);
+    }
+
+    let title = match read_zip_entry(&mut archive, "docProps/core.xml")? {
+        Some(core_xml) => read_xml_text(&core_xml, "title")?,
+        None => None,
+    };
+
+    slides_to_text_doc(title, slides, source)
+}
+
+/// Each slide becomes a page, slide titles become outlines, and qualified tables become sheets
+pub(super) fn slides_to_text_doc(
+    title: Option<String>,
+    slides: Vec<Slide>,
+    source: &str,
+) -> AiterResult<TextDoc> {
+    let mut pages: Vec<String> = vec![];
+    let mut outlines: Vec<TextDocOutline> = vec![];
+    let mut sheets: Vec<DocSheet> = vec![];
+
+    for slide in slides {
+        let mut page = String::new();
+        let mut page_sheets: Vec<SheetData> = vec![];
+
+        if let Some(title) = &slide.title {
+            page.push_str(&format!("{title}\n\n"));
+        }
+
+        for text in &slide.texts {
+            page.push_str(&format!("{text}\n\n"));
+        }
+
+        for table in slide.tables {
+            match to_sheet_data(&table) {
+                Some(data) => page_sheets.push(data),
+                None => {
+                    for row in &table {
+                        page.push_str(&format!("{}\n", row.join(" | ")));
+                    }
+                    page.push('\n');
+                }
+            }
+        }
+
+        if !slide.notes.is_empty() {
+            page.push_str(&format!("Notes:\n{}\n\n", slide.notes.join("\n")));
+        }
+
+        if page.trim().is_empty() && page_sheets.is_empty() {
+            continue;
+        }
+
+        if let Some(title) = slide.title {
+            outlines.push(TextDocOutline {
+                title,
+                page: pages.len(),
+                children: vec![],
+            });
+        }
+
+        sheets.extend(page_sheets.into_iter().map(|data| DocSheet {
+            page: pages.len(),
+            data,
+        }));
+        pages.push(page.trim().to_string());
+    }
+
+    if pages.is_empty() {
+        return Err(AiterError::Invalid(format!("{source} is empty")));
+    }
+
+    let title = title.or_else(|| outlines.first().map(|outline| outline.title.clone()));
+
+    Ok(TextDoc {
+        title,
+        pages,
+        outlines,
+        sheets,
+    })
+}
+
+pub(super) fn get_attribute(element: &BytesStart, name: &str) -> Option<String> {
+    element
+        .try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|attr| {
+            let value = String::from_utf8_lossy(&attr.value).to_string();
+            unescape(&value).ok().map(|value| value.to_string())
+        })
+}
+
+pub(super) fn read_zip_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> AiterResult<Option<Vec<u8>>> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut buffer = Vec::new();
+    entry.read_to_end(&mut buffer)?;
+
+    Ok(Some(buffer))
+}
+
+/// Text of the first element with the local name, e.g. `dc:title` of document properties
+pub(super) fn read_xml_text(xml: &[u8], local_name: &str) -> AiterResult<Option<String>> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = Vec::new();
+
+    let mut text: Option<String> = None;
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) if e.local_name().as_ref() == local_name.as_bytes() => {
+                text = Some(String::new());
+            }
+            Event::Text(e) => {
+                if let Some(text) = &mut text {
+                    text.push_str(&e.unescape()?);
+                }
+            }
+            Event::End(e) if e.local_name().as_ref() == local_name.as_bytes() => break,
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(text
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty()))
+}
+
+/// The first row is taken as headers, returns None if there are less than 2 columns or no data rows
+pub(super) fn to_sheet_data(table: &[Vec<String>]) -> Option<SheetData> {
+    let col_num = table.iter().map(|row| row.len()).max().unwrap_or(0);
+    if col_num < 2 || table.len() < 2 {
+        return None;
+    }
+
+    Some(SheetData {
+        headers: table.first().cloned(),
+        rows: table[1..].to_vec(),
+    })
+}
+
+fn find_notes_path<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    slide_path: &str,
+) -> AiterResult<Option<String>> {
+    let (dir, filename) = slide_path.rsplit_once('/').unwrap_or(("", slide_path));
+    let rels_path = format!("{dir}/_rels/{filename}.rels");
+
+    Ok(read_relationships(archive, &rels_path)?
+        .into_iter()
+        .find(|(_, (r#type, _))| r#type.ends_with("/notesSlide"))
+        .map(|(_, (_, target))| resolve_target(dir, &target)))
+}
+
+/// Slides in the order of presentation, or in the natural order of filenames if the order is missing
+fn list_slide_paths<R: Read + Seek>(archive: &mut ZipArchive<R>) -> AiterResult<Vec<String>> {
+    let mut slide_ids: Vec<String> = vec![];
+    if let Some(presentation_xml) = read_zip_entry(archive, "ppt/presentation.xml")? {
+        let mut reader = Reader::from_reader(presentation_xml.as_slice());
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"sldId" => {
+                    if let Some(id) = get_attribute(&e, "r:id") {
+                        slide_ids.push(id);
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+    }
+
+    let relationships = read_relationships(archive, "ppt/_rels/presentation.xml.rels")?;
+    let slide_paths: Vec<String> = slide_ids
+        .iter()
+        .filter_map(|id| relationships.get(id))
+        .map(|(_, target)| resolve_target("ppt", target))
+        .collect();
+    if !slide_paths.is_empty() {
+        return Ok(slide_paths);
+    }
+
+    let mut slide_paths: Vec<String> = archive
+        .file_names()
+        .filter(|name| name.starts_with("ppt/slides/slide") && name.ends_with(".xml"))
+        .map(|name| name.to_string())
+        .collect();
+    slide_paths.sort_by(|a, b| compare(a, b));
+
+    Ok(slide_paths)
+}
+
+fn parse_slide_xml(xml: &[u8]) -> AiterResult<Slide> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = Vec::new();
+
+    let mut slide = Slide::default();
+
+    let mut shape_placeholder: Option<String> = None;
+    let mut shape_paragraphs: Vec<String> = vec![];
+    let mut paragraph = String::new();
+    let mut in_text = false;
+
+    let mut table: Option<Vec<Vec<String>>> = None;
+    let mut row: Vec<String> = vec![];
+    let mut cell: Vec<String> = vec![];
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"sp" => {
+                    shape_placeholder = None;
+                    shape_paragraphs.clear();
+                }
+                b"ph" => {
+                    shape_placeholder = Some(get_attribute(&e, "type").unwrap_or_default());
+                }
+                b"t" => in_text = true,
+                b"tbl" => table = Some(vec![]),
+                b"tc" => cell.clear(),
+                b"tr" => row.clear(),
+                _ => {}
+            },
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"br" => paragraph.push('\n'),
+                b"ph" => {
+                    shape_placeholder = Some(get_attribute(&e, "type").unwrap_or_default());
+                }
+                _ => {}
+            },
+            Event::Text(e) if in_text => paragraph.push_str(&e.unescape()?),
+            Event::End(e) => match e.local_name().as_ref() {
+                b"p" => {
+                    let text = paragraph.trim().to_string();
+                    if !text.is_empty() {
+                        if table.is_some() {
+                            cell.push(text);
+                        } else {
+                            shape_paragraphs.push(text);
+                        }
+                    }
+                    paragraph.clear();
+                }
+                b"sp" => {
+                    let text = shape_paragraphs.join("\n");
+                    if !text.is_empty() {
+                        match shape_placeholder.as_deref() {
+                            Some("title" | "ctrTitle") if slide.title.is_none() => {
+                                slide.title = Some(text.replace('\n', " "));
+                            }
+                            // Boilerplate repeated on every slide
+                            Some("dt" | "ftr" | "sldNum") => {}
+                            _ => slide.texts.push(text),
+                        }
+                    }
+                    shape_placeholder = None;
+                    shape_paragraphs.clear();
+                }
+                b"t" => in_text = false,
+                b"tbl" => {
+                    if let Some(table) = table.take() {
+                        slide.tables.push(table);
+                    }
+                }
+                b"tc" => row.push(cell.join(" ")),
+                b"tr" => {
+                    if let Some(table) = &mut table {
+                        table.push(row.clone());
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(slide)
+}
+
+/// Map of relationship ID to its type and target
+fn read_relationships<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    rels_path: &str,
+) -> AiterResult<HashMap<String, (String, String)>> {
+    let mut relationships: HashMap<String, (String, String)> = HashMap::new();
+
+    if let Some(rels_xml) = read_zip_entry(archive, rels_path)? {
+        let mut reader = Reader::from_reader(rels_xml.as_slice());
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                    if let (Some(id), Some(target)) =
+                        (get_attribute(&e, "Id"), get_attribute(&e, "Target"))
+                    {
+                        let r#type = get_attribute(&e, "Type").unwrap_or_default();
+                        relationships.insert(id, (r#type, target));
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+    }
+
+    Ok(relationships)
+}
+
+/// Resolve the target of relationship relative to the directory of its source part
+fn resolve_target(dir: &str, target: &str) -> String {
+    if let Some(absolute) = target.strip_prefix('/') {
+        return absolute.to_string();
+    }
+
+    let mut segments: Vec<&str> = dir.split('/').filter(|s| !s.is_empty()).collect();
+    for segment in target.split('/') {
+        match segment {
+            ".." => {
+                segments.pop();
+            }
+            "." | "" => {}
+            _ => segments.push(segment),
+        }
+    }
+
+    segments.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_slide_xml() {
+        let slide = parse_slide_xml(
+            br#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+            <p:sld xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main">
+                <p:cSld><p:spTree>
+                    <p:sp>
+                        <p:nvSpPr><p:nvPr><p:ph type="title"/></p:nvPr></p:nvSpPr>
+                        <p:txBody><a:p><a:r><a:t>Quarterly </a:t></a:r><a:r><a:t>Sales</a:t></a:r></a:p></p:txBody>
+                    </p:sp>
+                    <p:sp>
+                        <p:nvSpPr><p:nvPr><p:ph idx="1"/></p:nvPr></p:nvSpPr>
+                        <p:txBody><a:p><a:r><a:t>Up 5% &amp; rising</a:t></a:r></a:p><a:p><a:r><a:t>Best in Q3</a:t></a:r></a:p></p:txBody>
+                    </p:sp>
+                    <p:graphicFrame><a:graphic><a:graphicData><a:tbl>
+                        <a:tr><a:tc><a:txBody><a:p><a:r><a:t>Quarter</a:t></a:r></a:p></a:txBody></a:tc><a:tc><a:txBody><a:p><a:r><a:t>Sales</a:t></a:r></a:p></a:txBody></a:tc></a:tr>
+                        <a:tr><a:tc><a:txBody><a:p><a:r><a:t>Q3</a:t></a:r></a:p></a:txBody></a:tc><a:tc><a:txBody><a:p><a:r><a:t>105</a:t></a:r></a:p></a:txBody></a:tc></a:tr>
+                    </a:tbl></a:graphicData></a:graphic></p:graphicFrame>
+                    <p:sp>
+                        <p:nvSpPr><p:nvPr><p:ph type="sldNum"/></p:nvPr></p:nvSpPr>
+                        <p:txBody><a:p><a:r><a:t>3</a:t></a:r></a:p></p:txBody>
+                    </p:sp>
+                </p:spTree></p:cSld>
+            </p:sld>"#,
+        )
+        .unwrap();
+
+        assert_eq!(slide.title, Some("Quarterly Sales".to_string()));
+        assert_eq!(slide.texts, vec!["Up 5% & rising\nBest in Q3".to_string()]);
+        assert_eq!(
+            slide.tables,
+            vec![vec![
+                vec!["Quarter".to_string(), "Sales".to_string()],
+                vec!["Q3".to_string(), "105".to_string()],
+            ]]
+        );
+
+        let notes = Slide {
+            notes: vec!["Mention the new market".to_string()],
+            ..Default::default()
+        };
+        let doc =
+            slides_to_text_doc(None, vec![slide, Slide::default(), notes], "sales.pptx").unwrap();
+        assert_eq!(doc.title, Some("Quarterly Sales".to_string()));
+        assert_eq!(doc.pages.len(), 2);
+        assert_eq!(doc.pages[1], "Notes:\nMention the new market");
+        assert_eq!(doc.outlines.len(), 1);
+        assert_eq!(doc.sheets.len(), 1);
+        assert_eq!(doc.sheets[0].page, 0);
+    }
+
+    #[test]
+    fn test_resolve_target() {
+        assert_eq!(
+            resolve_target("ppt/slides", "../notesSlides/notesSlide1.xml"),
+            "ppt/notesSlides/notesSlide1.xml"
+        );
+        assert_eq!(
+            resolve_target("ppt", "slides/slide2.xml"),
+            "ppt/slides/slide2.xml"
+        );
+        assert_eq!(
+            resolve_target("ppt", "/ppt/slides/slide3.xml"),
+            "ppt/slides/slide3.xml"
+        );
+    }
+}
@@ -33,5 +33,6 @@ pub fn to_text_doc(path: &Path, source: &str) -> AiterResult<TextDoc> {
         title: Some(extract_filestem_from_path(&PathBuf::from(source))),
         pages: vec![trimmed_text.to_string()],
         outlines: vec![],
+        sheets: vec![],
     })
 }
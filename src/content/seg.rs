@@ -4,11 +4,12 @@ use crate::{
     Tokenizer,
     content::{
         frag::FragContent,
-        seg::{sheet::SheetSegContent, text::TextSegContent},
+        seg::{code::CodeSegContent, sheet::SheetSegContent, text::TextSegContent},
     },
     error::AiterResult,
 };
 
+pub mod code;
 pub mod sheet;
 pub mod text;
 
@@ -25,12 +26,14 @@ pub trait SegContent: Debug + Send + Sync {
 #[derive(strum::Display, strum::EnumString, Debug)]
 #[strum(ascii_case_insensitive)]
 pub enum SegContentType {
+    Code,
     Sheet,
     Text,
 }
 
 pub fn decode_content(content: &[u8], content_type: &str) -> AiterResult<Box<dyn SegContent>> {
     match content_type.parse::<SegContentType>()? {
+        SegContentType::Code => Ok(Box::new(CodeSegContent::try_from_bytes(content)?)),
         SegContentType::Sheet => Ok(Box::new(SheetSegContent::try_from_bytes(content)?)),
         SegContentType::Text => Ok(Box::new(TextSegContent::try_from_bytes(content)?)),
     }
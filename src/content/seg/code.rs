@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    SPLIT_TOKENS_OF_FRAG, Tokenizer,
+    content::{
+        frag::{FragContent, text::TextFragContent},
+        seg::{SegContent, SegContentType},
+    },
+    error::AiterResult,
+    utils::{compress, text::split_by_max_tokens},
+};
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct CodeSegContent {
+    pub path: String,
+    pub language: String,
+    pub symbols: Vec<String>, // Names of functions, classes, impls, etc. defined in the segment
+    pub code: String,
+}
+
+impl SegContent for CodeSegContent {
+    fn get_type(&self) -> SegContentType {
+        SegContentType::Code
+    }
+
+    fn split(&self, tokenizer: &Tokenizer) -> Vec<Box<dyn FragContent>> {
+        split_by_max_tokens(&self.code, SPLIT_TOKENS_OF_FRAG, tokenizer)
+            .into_iter()
+            .map(|text| Box::new(TextFragContent { text }) as Box<dyn FragContent>)
+            .collect()
+    }
+
+    fn to_string(&self) -> String {
+        self.code.clone()
+    }
+
+    fn try_from_bytes(bytes: &[u8]) -> AiterResult<Self> {
+        let json = compress::decode(bytes)?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    fn try_into_bytes(&self) -> AiterResult<Vec<u8>> {
+        let json = serde_json::to_vec(self)?;
+        Ok(compress::encode(&json)?)
+    }
+}
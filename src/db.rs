@@ -5,7 +5,7 @@ use libsql::{Builder, Connection};
 
 use crate::{
     CURRENT_DB_VERSION, CURRENT_SIGNATURE_DIMS, CURRENT_TOKENIZER, DB_BUSY_SECS, DB_CORE_PATH,
-    Tokenizer, error::AiterResult,
+    DB_VECTOR_NEIGHBORS, Tokenizer, error::AiterResult,
 };
 
 pub mod core;
@@ -26,7 +26,7 @@ pub async fn ensure_core_tables() -> AiterResult<()> {
                             tx.execute(sql, ()).await?;
                         }
 
-                        tx.execute(updates::SQL_UPDATE_DB_VERSION, [i]).await?;
+                        tx.execute(updates::SQL_UPDATE_DB_VERSION, [i + 1]).await?;
                         tx.commit().await?;
                     }
                 }
@@ -36,6 +36,8 @@ pub async fn ensure_core_tables() -> AiterResult<()> {
         core::ai::ensure_tables().await?;
         core::config::ensure_tables().await?;
         core::llm::ensure_tables().await?;
+        core::llm_cache::ensure_tables().await?;
+        core::llm_usage::ensure_tables().await?;
         core::meta::ensure_tables().await?;
         core::tool::ensure_tables().await?;
     }
@@ -43,6 +45,22 @@ pub async fn ensure_core_tables() -> AiterResult<()> {
     Ok(())
 }
 
+/// Create tables of the test data directory once per process
+#[cfg(test)]
+pub async fn ensure_test_tables() {
+    static ENSURED: tokio::sync::OnceCell<()> = tokio::sync::OnceCell::const_new();
+
+    ENSURED
+        .get_or_init(|| async {
+            std::fs::create_dir_all(&*crate::DATA_DIR).unwrap();
+            ensure_core_tables().await.unwrap();
+            ensure_mem_tables(&crate::DB_DEFAULT_MEM_PATH)
+                .await
+                .unwrap();
+        })
+        .await;
+}
+
 pub async fn ensure_mem_tables(db_path: &Path) -> AiterResult<()> {
     if db_path.exists() {
         if let Some(db_version_str) = mem::meta::get_db_version(db_path).await? {
@@ -57,7 +75,7 @@ pub async fn ensure_mem_tables(db_path: &Path) -> AiterResult<()> {
                             tx.execute(sql, ()).await?;
                         }
 
-                        tx.execute(updates::SQL_UPDATE_DB_VERSION, [i]).await?;
+                        tx.execute(updates::SQL_UPDATE_DB_VERSION, [i + 1]).await?;
                         tx.commit().await?;
                     }
                 }
@@ -77,6 +95,12 @@ pub async fn ensure_mem_tables(db_path: &Path) -> AiterResult<()> {
                 .unwrap_or(CURRENT_TOKENIZER);
             mem::MEM_TOKENIZER_MAP.insert(db_path.to_path_buf(), tokenizer);
         }
+
+        if let Some(embedding_dims_str) = mem::meta::get_embedding_dims(db_path).await? {
+            if let Ok(embedding_dims) = embedding_dims_str.parse::<usize>() {
+                mem::MEM_EMBEDDING_DIMS_MAP.insert(db_path.to_path_buf(), embedding_dims);
+            }
+        }
     } else {
         mem::doc::ensure_tables(db_path).await?;
         mem::doc_frag::ensure_tables(db_path).await?;
@@ -92,6 +116,75 @@ pub async fn ensure_mem_tables(db_path: &Path) -> AiterResult<()> {
     Ok(())
 }
 
+/// Add a vector column with its index to an existing table, the column is recreated if dims changed
+pub async fn ensure_vector_column(
+    db_path: &Path,
+    table: &str,
+    column: &str,
+    dims: usize,
+    clear: bool,
+) -> AiterResult<()> {
+    let column_type = format!("F16_BLOB({dims})");
+    let index_name = format!("idx_{table}_{column}");
+
+    let conn = open(db_path).await?;
+    let tx = conn.transaction().await?;
+
+    let exists_type: Option<String> = tx
+        .query(
+            r#"
+SELECT "type"
+FROM pragma_table_info(?)
+WHERE "name" = ?
+LIMIT 1
+;"#,
+            (table, column),
+        )
+        .await?
+        .next()
+        .await?
+        .map(|row| row.get::<String>(0))
+        .transpose()?;
+
+    if let Some(exists_type) = &exists_type {
+        if exists_type.eq_ignore_ascii_case(&column_type) {
+            if clear {
+                tx.execute(&format!(r#"UPDATE "{table}" SET "{column}" = NULL;"#), ())
+                    .await?;
+            }
+        } else {
+            tx.execute(&format!(r#"DROP INDEX IF EXISTS "{index_name}";"#), ())
+                .await?;
+            tx.execute(
+                &format!(r#"ALTER TABLE "{table}" DROP COLUMN "{column}";"#),
+                (),
+            )
+            .await?;
+        }
+    }
+
+    if !exists_type.is_some_and(|t| t.eq_ignore_ascii_case(&column_type)) {
+        tx.execute(
+            &format!(r#"ALTER TABLE "{table}" ADD COLUMN "{column}" {column_type};"#),
+            (),
+        )
+        .await?;
+    }
+
+    tx.execute(
+        &format!(
+        r#"
+CREATE INDEX IF NOT EXISTS "{index_name}" ON "{table}" (libsql_vector_idx({column}, 'compress_neighbors=float8', 'max_neighbors={}'))
+;"#, *DB_VECTOR_NEIGHBORS),
+        (),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
 pub async fn open(db_path: &Path) -> AiterResult<Connection> {
     let db = Builder::new_local(db_path).build().await?;
     let conn = db.connect()?;
@@ -122,7 +215,6 @@ pub fn vec_f32_to_f16_str(vec: &[f32]) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::DB_VECTOR_NEIGHBORS;
 
     #[tokio::test]
     async fn test_fts5() {
@@ -274,4 +366,70 @@ WHERE rowid IN vector_top_k('idx_test_vec_embedding', vector16(?1), 10) AND vect
             assert_eq!(id, Some("4".to_string()));
         }
     }
+
+    #[tokio::test]
+    async fn test_ensure_vector_column() {
+        let db_path = std::env::temp_dir().join(format!("aiter-test-{}.db", ulid::Ulid::new()));
+
+        {
+            let conn = open(&db_path).await.unwrap();
+            conn.execute(r#"CREATE TABLE "t" ("id" TEXT PRIMARY KEY);"#, ())
+                .await
+                .unwrap();
+            conn.execute(r#"INSERT INTO "t"("id") VALUES ('a');"#, ())
+                .await
+                .unwrap();
+        }
+
+        ensure_vector_column(&db_path, "t", "emb", 3, false)
+            .await
+            .unwrap();
+
+        {
+            let conn = open(&db_path).await.unwrap();
+            conn.execute(
+                r#"UPDATE "t" SET "emb" = vector16(?);"#,
+                [vec_f32_to_f16_str(&[1.0, 0.0, 0.0])],
+            )
+            .await
+            .unwrap();
+
+            let id: String = conn
+                .query(
+                    r#"SELECT "id" FROM "t" WHERE rowid IN vector_top_k('idx_t_emb', vector16(?), 1);"#,
+                    [vec_f32_to_f16_str(&[0.9, 0.1, 0.0])],
+                )
+                .await
+                .unwrap()
+                .next()
+                .await
+                .unwrap()
+                .unwrap()
+                .get(0)
+                .unwrap();
+            assert_eq!(id, "a");
+        }
+
+        // Changing dims recreates the column, so previous embeddings are gone
+        ensure_vector_column(&db_path, "t", "emb", 4, false)
+            .await
+            .unwrap();
+
+        {
+            let conn = open(&db_path).await.unwrap();
+            let count: u64 = conn
+                .query(r#"SELECT COUNT(*) FROM "t" WHERE "emb" IS NULL;"#, ())
+                .await
+                .unwrap()
+                .next()
+                .await
+                .unwrap()
+                .unwrap()
+                .get(0)
+                .unwrap();
+            assert_eq!(count, 1);
+        }
+
+        let _ = std::fs::remove_file(&db_path);
+    }
 }
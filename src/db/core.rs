@@ -1,5 +1,7 @@
 pub mod ai;
 pub mod config;
 pub mod llm;
+pub mod llm_cache;
+pub mod llm_usage;
 pub mod meta;
 pub mod tool;
@@ -3,13 +3,17 @@ use crate::{DB_CORE_PATH, db::open, error::*};
 #[derive(strum::Display, strum::EnumString)]
 pub enum ConfigKey {
     ActiveChatLlm,
+    ActiveDigestLlm,
+    ActiveEmbeddingLlm,
     ActiveReasoningLlm,
+    ActiveRerankLlm,
     AppDigestBatch,
     AppDigestConcurrent,
     AppDigestDeep,
     AppRemoteUrl,
     AppRemoteToken,
     AppSkipDigest,
+    PromptLang,
 }
 
 pub async fn ensure_tables() -> AiterResult<()> {
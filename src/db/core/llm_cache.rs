@@ -0,0 +1,286 @@
+use libsql::Rows;
+use serde::Serialize;
+use tabled::Tabled;
+
+use crate::{DB_CORE_PATH, db::open, error::*};
+
+#[derive(Clone, Serialize)]
+pub struct LlmCacheEntity {
+    pub content: String,
+    pub reasoning: Option<String>,
+}
+
+#[derive(Clone, Serialize, Tabled)]
+pub struct LlmCacheStatsEntity {
+    #[tabled(rename = "LLM")]
+    pub llm_name: String,
+
+    #[tabled(rename = "Model")]
+    pub model: String,
+
+    #[tabled(rename = "Entries")]
+    pub count: u64,
+
+    #[tabled(rename = "Hits")]
+    pub hits: u64,
+
+    #[tabled(rename = "Size")]
+    #[tabled(format("{}", bytesize::ByteSize(self.size)))]
+    pub size: u64,
+}
+
+pub async fn ensure_tables() -> AiterResult<()> {
+    let conn = open(&DB_CORE_PATH).await?;
+    conn.execute(
+        r#"
+CREATE TABLE IF NOT EXISTS "llm_cache" (
+    "llm_name"     TEXT NOT NULL,
+    "model"        TEXT NOT NULL,
+    "temperature"  REAL NOT NULL,
+    "prompt_hash"  TEXT NOT NULL,
+    "content"      TEXT NOT NULL,
+    "reasoning"    TEXT,
+    "hits"         INTEGER NOT NULL DEFAULT 0,
+    "created_at"   TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY ("llm_name", "model", "temperature", "prompt_hash"))
+;"#,
+        (),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Delete cached responses, of all LLMs if `llm_name` is None, return the count of deleted
+pub async fn clear(llm_name: Option<&str>) -> AiterResult<u64> {
+    let conn = open(&DB_CORE_PATH).await?;
+    let count = if let Some(llm_name) = llm_name {
+        conn.execute(
+            r#"
+DELETE FROM "llm_cache"
+WHERE "llm_name" = ?
+;"#,
+            [llm_name],
+        )
+        .await?
+    } else {
+        conn.execute(
+            r#"
+DELETE FROM "llm_cache"
+;"#,
+            (),
+        )
+        .await?
+    };
+
+    Ok(count)
+}
+
+pub async fn get(
+    llm_name: &str,
+    model: &str,
+    temperature: f64,
+    prompt_hash: &str,
+) -> AiterResult<Option<LlmCacheEntity>> {
+    let conn = open(&DB_CORE_PATH).await?;
+    let mut rows = conn
+        .query(
+            r#"
+UPDATE "llm_cache"
+SET "hits" = "hits" + 1
+WHERE "llm_name" = ?1 AND "model" = ?2 AND "temperature" = ?3 AND "prompt_hash" = ?4
+RETURNING "content", "reasoning"
+;"#,
+            (llm_name, model, temperature, prompt_hash),
+        )
+        .await?;
+
+    Ok(LlmCacheEntity::collect_rows(&mut rows).await?.pop())
+}
+
+pub async fn stats() -> AiterResult<Vec<LlmCacheStatsEntity>> {
+    let conn = open(&DB_CORE_PATH).await?;
+    let mut rows = conn
+        .query(
+            r#"
+SELECT "llm_name", "model", COUNT(*), SUM("hits"), SUM(LENGTH("content") + IFNULL(LENGTH("reasoning"), 0))
+FROM "llm_cache"
+GROUP BY "llm_name", "model"
+ORDER BY "llm_name", "model"
+;"#,
+            (),
+        )
+        .await?;
+
+    let mut vec = vec![];
+    while let Some(row) = rows.next().await? {
+        vec.push(LlmCacheStatsEntity {
+            llm_name: row.get(0)?,
+            model: row.get(1)?,
+            count: row.get(2)?,
+            hits: row.get(3)?,
+            size: row.get(4)?,
+        });
+    }
+
+    Ok(vec)
+}
+
+pub async fn upsert(
+    llm_name: &str,
+    model: &str,
+    temperature: f64,
+    prompt_hash: &str,
+    content: &str,
+    reasoning: Option<&str>,
+) -> AiterResult<()> {
+    let conn = open(&DB_CORE_PATH).await?;
+    conn.execute(
+        r#"
+INSERT INTO "llm_cache" ("llm_name", "model", "temperature", "prompt_hash", "content", "reasoning")
+VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+ON CONFLICT ("llm_name", "model", "temperature", "prompt_hash") DO UPDATE SET
+    "content" = ?5,
+    "reasoning" = ?6
+;"#,
+        (
+            llm_name,
+            model,
+            temperature,
+            prompt_hash,
+            content,
+            reasoning,
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+impl LlmCacheEntity {
+    async fn collect_rows(rows: &mut Rows) -> AiterResult<Vec<Self>> {
+        let mut vec = vec![];
+
+        while let Some(row) = rows.next().await? {
+            vec.push(Self {
+                content: row.get(0)?,
+                reasoning: row.get(1)?,
+            });
+        }
+
+        Ok(vec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ulid::Ulid;
+
+    use super::*;
+    use crate::{
+        api::llm::{ChatCompletionOptions, chat_completion},
+        db::{core::llm, ensure_test_tables},
+        llm::provider::mock::{mock_response, mock_server_with_responses},
+    };
+
+    fn completion_response(content: &str) -> String {
+        let body = format!(
+            "data: {}\n\ndata: [DONE]\n\n",
+            serde_json::json!({"choices": [{"delta": {"content": content}}]})
+        );
+        mock_response("200 OK", &[], "text/event-stream", &body)
+    }
+
+    async fn add_llm(base_url: &str, fallback: Option<&str>) -> String {
+        let name = Ulid::new().to_string();
+        let mut options = HashMap::from([
+            ("base_url".to_string(), base_url.to_string()),
+            ("model".to_string(), format!("model-{name}")),
+            ("max_retry".to_string(), "0".to_string()),
+        ]);
+        if let Some(fallback) = fallback {
+            options.insert("fallback".to_string(), fallback.to_string());
+        }
+        llm::upsert(&name, "chat", "openai", &options)
+            .await
+            .unwrap();
+
+        name
+    }
+
+    #[tokio::test]
+    async fn test_get_upsert() {
+        ensure_test_tables().await;
+
+        let llm_name = Ulid::new().to_string();
+        assert!(get(&llm_name, "gpt", 0.0, "hash").await.unwrap().is_none());
+
+        upsert(&llm_name, "gpt", 0.0, "hash", "Hello", Some("Think"))
+            .await
+            .unwrap();
+        let cached = get(&llm_name, "gpt", 0.0, "hash").await.unwrap().unwrap();
+        assert_eq!(cached.content, "Hello");
+        assert_eq!(cached.reasoning.as_deref(), Some("Think"));
+
+        assert!(get(&llm_name, "gpt", 0.6, "hash").await.unwrap().is_none());
+        assert!(get(&llm_name, "gpt", 0.0, "other").await.unwrap().is_none());
+        assert!(
+            get(&llm_name, "gpt-mini", 0.0, "hash")
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        assert_eq!(clear(Some(&llm_name)).await.unwrap(), 1);
+        assert!(get(&llm_name, "gpt", 0.0, "hash").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_chat_completion_cache() {
+        ensure_test_tables().await;
+
+        // Each response can be served only once, so a response served again must be from cache
+        let base_url = mock_server_with_responses(vec![
+            completion_response("A"),
+            completion_response("B"),
+            completion_response("C"),
+        ]);
+        let llm_name = add_llm(&base_url, None).await;
+
+        let stable = ChatCompletionOptions::default().with_temperature(0.0);
+        let unstable = ChatCompletionOptions::default().with_temperature(0.6);
+
+        let message = chat_completion("Hi", &[], &stable, Some(&llm_name))
+            .await
+            .unwrap();
+        assert_eq!(message.content, "A");
+        let message = chat_completion("Hi", &[], &stable, Some(&llm_name))
+            .await
+            .unwrap();
+        assert_eq!(message.content, "A");
+
+        let message = chat_completion("Hi", &[], &unstable, Some(&llm_name))
+            .await
+            .unwrap();
+        assert_eq!(message.content, "B");
+
+        // Responses of fallbacks are not cached
+        let fallback_url =
+            mock_server_with_responses(vec![completion_response("C"), completion_response("D")]);
+        let fallback_name = add_llm(&fallback_url, None).await;
+        let unavailable = || mock_response("503 Service Unavailable", &[], "text/plain", "");
+        let primary_url = mock_server_with_responses(vec![unavailable(), unavailable()]);
+        let primary_name = add_llm(&primary_url, Some(&fallback_name)).await;
+
+        let message = chat_completion("Hi", &[], &stable, Some(&primary_name))
+            .await
+            .unwrap();
+        assert_eq!(message.content, "C");
+        let message = chat_completion("Hi", &[], &stable, Some(&primary_name))
+            .await
+            .unwrap();
+        assert_eq!(message.content, "D");
+    }
+}
@@ -0,0 +1,290 @@
+use serde::Serialize;
+use tabled::Tabled;
+
+use crate::{DB_CORE_PATH, db::open, error::*};
+
+#[derive(strum::Display, strum::EnumString, Copy, Clone, Debug)]
+#[strum(ascii_case_insensitive, serialize_all = "lowercase")]
+pub enum LlmUsageGroup {
+    Llm,
+    Ai,
+    Purpose,
+    Doc,
+    Date,
+}
+
+#[derive(Clone, Serialize, Tabled)]
+pub struct LlmUsageStatsEntity {
+    #[tabled(rename = "Group")]
+    pub group: String,
+
+    #[tabled(rename = "Requests")]
+    pub requests: u64,
+
+    #[tabled(rename = "Estimated")]
+    pub estimated_requests: u64,
+
+    #[tabled(rename = "Prompt Tokens")]
+    pub prompt_tokens: u64,
+
+    #[tabled(rename = "Completion Tokens")]
+    pub completion_tokens: u64,
+
+    #[tabled(rename = "Cost")]
+    #[tabled(format("{}", self.cost.map_or("-".to_string(), |cost| format!("{cost:.4}"))))]
+    pub cost: Option<f64>,
+}
+
+pub async fn ensure_tables() -> AiterResult<()> {
+    let conn = open(&DB_CORE_PATH).await?;
+    let tx = conn.transaction().await?;
+
+    tx.execute(
+        r#"
+CREATE TABLE IF NOT EXISTS "llm_usage" (
+    "id"                 INTEGER PRIMARY KEY AUTOINCREMENT,
+    "llm_name"           TEXT NOT NULL,
+    "model"              TEXT NOT NULL,
+    "ai"                 TEXT,
+    "purpose"            TEXT NOT NULL,
+    "doc_id"             TEXT,
+    "prompt_tokens"      INTEGER NOT NULL,
+    "completion_tokens"  INTEGER NOT NULL,
+    "estimated"          INTEGER NOT NULL DEFAULT 0,
+    "cost"               REAL,
+    "created_at"         TIMESTAMP DEFAULT CURRENT_TIMESTAMP)
+;"#,
+        (),
+    )
+    .await?;
+
+    tx.execute(
+        r#"
+CREATE INDEX IF NOT EXISTS "idx_llm_usage_created_at" ON "llm_usage" ("created_at")
+;"#,
+        (),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn insert(
+    llm_name: &str,
+    model: &str,
+    ai: Option<&str>,
+    purpose: &str,
+    doc_id: Option<&str>,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    estimated: bool,
+    cost: Option<f64>,
+) -> AiterResult<()> {
+    let conn = open(&DB_CORE_PATH).await?;
+    conn.execute(
+        r#"
+INSERT INTO "llm_usage" ("llm_name", "model", "ai", "purpose", "doc_id", "prompt_tokens", "completion_tokens", "estimated", "cost")
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+;"#,
+        (
+            llm_name,
+            model,
+            ai,
+            purpose,
+            doc_id,
+            prompt_tokens,
+            completion_tokens,
+            estimated,
+            cost,
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Sum usage by groups, `since` and `until` are local dates in YYYY-MM-DD format, both are inclusive
+pub async fn stats(
+    group_by: &[LlmUsageGroup],
+    since: Option<&str>,
+    until: Option<&str>,
+) -> AiterResult<Vec<LlmUsageStatsEntity>> {
+    let group_exprs = group_by
+        .iter()
+        .map(|group| match group {
+            LlmUsageGroup::Llm => r#""llm_usage"."llm_name""#,
+            LlmUsageGroup::Ai => r#"COALESCE("ai"."name", "llm_usage"."ai", '-')"#,
+            LlmUsageGroup::Purpose => r#""llm_usage"."purpose""#,
+            LlmUsageGroup::Doc => r#"IFNULL("llm_usage"."doc_id", '-')"#,
+            LlmUsageGroup::Date => r#"DATE("llm_usage"."created_at", 'localtime')"#,
+        })
+        .collect::<Vec<_>>();
+
+    let (group_select, group_clause) = if group_exprs.is_empty() {
+        ("'Total'".to_string(), "".to_string())
+    } else {
+        (
+            group_exprs.join(" || ' / ' || "),
+            format!("GROUP BY {}", group_exprs.join(", ")),
+        )
+    };
+
+    let conn = open(&DB_CORE_PATH).await?;
+    let mut rows = conn
+        .query(
+            &format!(
+                r#"
+SELECT {group_select}, COUNT(*), IFNULL(SUM("llm_usage"."estimated"), 0), IFNULL(SUM("llm_usage"."prompt_tokens"), 0), IFNULL(SUM("llm_usage"."completion_tokens"), 0), SUM("llm_usage"."cost")
+FROM "llm_usage"
+LEFT JOIN "ai" ON "ai"."id" = "llm_usage"."ai"
+WHERE (?1 IS NULL OR "llm_usage"."created_at" >= DATETIME(?1, 'utc'))
+    AND (?2 IS NULL OR "llm_usage"."created_at" < DATETIME(?2, '+1 day', 'utc'))
+{group_clause}
+ORDER BY 1
+;"#
+            ),
+            (since, until),
+        )
+        .await?;
+
+    let mut vec = vec![];
+    while let Some(row) = rows.next().await? {
+        let requests: u64 = row.get(1)?;
+        if requests == 0 {
+            continue;
+        }
+
+        vec.push(LlmUsageStatsEntity {
+            group: row.get(0)?,
+            requests,
+            estimated_requests: row.get(2)?,
+            prompt_tokens: row.get(3)?,
+            completion_tokens: row.get(4)?,
+            cost: row.get(5)?,
+        });
+    }
+
+    Ok(vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{Date, macros::format_description};
+    use ulid::Ulid;
+
+    use super::*;
+    use crate::db::ensure_test_tables;
+
+    #[tokio::test]
+    async fn test_insert_stats() {
+        ensure_test_tables().await;
+
+        let llm_name = Ulid::new().to_string();
+        insert(
+            &llm_name,
+            "gpt",
+            None,
+            "answer",
+            None,
+            10,
+            5,
+            false,
+            Some(0.5),
+        )
+        .await
+        .unwrap();
+        insert(
+            &llm_name,
+            "gpt",
+            None,
+            "answer",
+            None,
+            20,
+            10,
+            true,
+            Some(1.0),
+        )
+        .await
+        .unwrap();
+        insert(
+            &llm_name,
+            "gpt",
+            None,
+            "digest-seg",
+            Some("doc"),
+            30,
+            15,
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Rows of other tests are filtered out by the LLM name
+        let stats_of = |stats: Vec<LlmUsageStatsEntity>| {
+            stats
+                .into_iter()
+                .filter(|s| s.group.starts_with(&llm_name))
+                .collect::<Vec<_>>()
+        };
+
+        let by_purpose = stats_of(
+            stats(&[LlmUsageGroup::Llm, LlmUsageGroup::Purpose], None, None)
+                .await
+                .unwrap(),
+        );
+        assert_eq!(by_purpose.len(), 2);
+        assert_eq!(by_purpose[0].group, format!("{llm_name} / answer"));
+        assert_eq!(by_purpose[0].requests, 2);
+        assert_eq!(by_purpose[0].estimated_requests, 1);
+        assert_eq!(by_purpose[0].prompt_tokens, 30);
+        assert_eq!(by_purpose[0].completion_tokens, 15);
+        assert_eq!(by_purpose[0].cost, Some(1.5));
+        assert_eq!(by_purpose[1].group, format!("{llm_name} / digest-seg"));
+        assert_eq!(by_purpose[1].cost, None);
+
+        // Dates are grouped in local time, the range is inclusive at both ends
+        let by_date = stats_of(
+            stats(&[LlmUsageGroup::Llm, LlmUsageGroup::Date], None, None)
+                .await
+                .unwrap(),
+        );
+        assert_eq!(by_date.len(), 1);
+        let date_str = by_date[0].group.rsplit(" / ").next().unwrap().to_string();
+        let date = Date::parse(&date_str, format_description!("[year]-[month]-[day]")).unwrap();
+        let format = |date: Date| {
+            date.format(format_description!("[year]-[month]-[day]"))
+                .unwrap()
+        };
+
+        let in_range = stats_of(
+            stats(&[LlmUsageGroup::Llm], Some(&date_str), Some(&date_str))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(in_range.len(), 1);
+        assert_eq!(in_range[0].requests, 3);
+
+        let next_day = format(date.next_day().unwrap());
+        let previous_day = format(date.previous_day().unwrap());
+        assert!(
+            stats_of(
+                stats(&[LlmUsageGroup::Llm], Some(&next_day), None)
+                    .await
+                    .unwrap()
+            )
+            .is_empty()
+        );
+        assert!(
+            stats_of(
+                stats(&[LlmUsageGroup::Llm], None, Some(&previous_day))
+                    .await
+                    .unwrap()
+            )
+            .is_empty()
+        );
+    }
+}
@@ -20,6 +20,7 @@ pub mod history_chat;
 pub mod meta;
 pub mod skill;
 
+pub static MEM_EMBEDDING_DIMS_MAP: LazyLock<DashMap<PathBuf, usize>> = LazyLock::new(DashMap::new);
 pub static MEM_SIGNATURE_DIMS_MAP: LazyLock<DashMap<PathBuf, usize>> = LazyLock::new(DashMap::new);
 pub static MEM_TOKENIZER_MAP: LazyLock<DashMap<PathBuf, Tokenizer>> = LazyLock::new(DashMap::new);
 
@@ -50,6 +51,12 @@ pub enum MemWriteEvent {
         resp_sender: oneshot::Sender<AiterResult<()>>,
     },
 
+    EnsureEmbedding {
+        dims: usize,
+        model: String,
+        resp_sender: oneshot::Sender<AiterResult<()>>,
+    },
+
     DeleteSkill {
         skill_id: String,
         resp_sender: oneshot::Sender<AiterResult<()>>,
@@ -91,6 +98,11 @@ pub enum MemWriteEvent {
         resp_sender: oneshot::Sender<AiterResult<()>>,
     },
 
+    SetDocFragEmbeddings {
+        embeddings: Vec<(String, Vec<f32>)>,
+        resp_sender: oneshot::Sender<AiterResult<()>>,
+    },
+
     SetDocFragDigestEnd {
         frag_id: String,
         success: bool,
@@ -108,6 +120,16 @@ pub enum MemWriteEvent {
         resp_sender: oneshot::Sender<AiterResult<()>>,
     },
 
+    SetDocImplicitEmbeddings {
+        embeddings: Vec<(String, Vec<f32>)>,
+        resp_sender: oneshot::Sender<AiterResult<()>>,
+    },
+
+    SetDocKnlEmbeddings {
+        embeddings: Vec<(String, Vec<f32>)>,
+        resp_sender: oneshot::Sender<AiterResult<()>>,
+    },
+
     SetDocPartDigestEnd {
         part_id: String,
         success: bool,
@@ -160,6 +182,11 @@ pub enum MemWriteEvent {
         resp_sender: oneshot::Sender<AiterResult<()>>,
     },
 
+    SetSkillEmbeddings {
+        embeddings: Vec<(String, Vec<f32>)>,
+        resp_sender: oneshot::Sender<AiterResult<()>>,
+    },
+
     UpsertDocFrag {
         doc_frag: doc_frag::DocFrag,
         context: String,
@@ -197,6 +224,27 @@ pub enum MemWriteEvent {
     },
 }
 
+/// Embedding columns are added to mem on demand, since the dims are decided by the embedding LLM
+pub async fn ensure_embedding(path: &Path, dims: usize, model: &str) -> AiterResult<()> {
+    let clear = meta::get_embedding_model(path)
+        .await?
+        .is_some_and(|m| m != model);
+
+    doc_frag::ensure_embedding(path, dims, clear).await?;
+    doc_implicit::ensure_embedding(path, dims, clear).await?;
+    doc_knl::ensure_embedding(path, dims, clear).await?;
+    skill::ensure_embedding(path, dims, clear).await?;
+
+    meta::set_embedding(path, dims, model).await?;
+    MEM_EMBEDDING_DIMS_MAP.insert(path.to_path_buf(), dims);
+
+    Ok(())
+}
+
+pub fn get_mem_embedding_dims(path: &Path) -> Option<usize> {
+    MEM_EMBEDDING_DIMS_MAP.get(path).map(|v| *v.value())
+}
+
 pub fn get_mem_signature_dims(path: &Path) -> usize {
     MEM_SIGNATURE_DIMS_MAP
         .get(path)
@@ -256,6 +304,14 @@ pub fn spawn_mem_write(mem_path: &Path) -> Sender<MemWriteEvent> {
                     );
                 }
 
+                MemWriteEvent::EnsureEmbedding {
+                    dims,
+                    model,
+                    resp_sender,
+                } => {
+                    let _ = resp_sender.send(ensure_embedding(&db_path, dims, &model).await);
+                }
+
                 MemWriteEvent::DeleteSkill {
                     skill_id,
                     resp_sender,
@@ -318,6 +374,13 @@ pub fn spawn_mem_write(mem_path: &Path) -> Sender<MemWriteEvent> {
                     let _ = resp_sender.send(doc::set_summary(&db_path, &doc_id, &summary).await);
                 }
 
+                MemWriteEvent::SetDocFragEmbeddings {
+                    embeddings,
+                    resp_sender,
+                } => {
+                    let _ = resp_sender.send(doc_frag::set_embeddings(&db_path, &embeddings).await);
+                }
+
                 MemWriteEvent::SetDocFragDigestEnd {
                     frag_id,
                     success,
@@ -343,6 +406,21 @@ pub fn spawn_mem_write(mem_path: &Path) -> Sender<MemWriteEvent> {
                     let _ = resp_sender.send(doc_frag::set_digest_start(&db_path, &frag_id).await);
                 }
 
+                MemWriteEvent::SetDocImplicitEmbeddings {
+                    embeddings,
+                    resp_sender,
+                } => {
+                    let _ =
+                        resp_sender.send(doc_implicit::set_embeddings(&db_path, &embeddings).await);
+                }
+
+                MemWriteEvent::SetDocKnlEmbeddings {
+                    embeddings,
+                    resp_sender,
+                } => {
+                    let _ = resp_sender.send(doc_knl::set_embeddings(&db_path, &embeddings).await);
+                }
+
                 MemWriteEvent::SetDocPartDigestEnd {
                     part_id,
                     success,
@@ -420,6 +498,13 @@ pub fn spawn_mem_write(mem_path: &Path) -> Sender<MemWriteEvent> {
                         .send(history_chat::set_content(&db_path, rowid, &content).await);
                 }
 
+                MemWriteEvent::SetSkillEmbeddings {
+                    embeddings,
+                    resp_sender,
+                } => {
+                    let _ = resp_sender.send(skill::set_embeddings(&db_path, &embeddings).await);
+                }
+
                 MemWriteEvent::UpsertDocFrag {
                     doc_frag,
                     context,
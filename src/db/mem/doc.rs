@@ -446,6 +446,43 @@ ORDER BY "updated_at" DESC
     DocEntity::collect_rows(&mut rows).await
 }
 
+pub async fn list_by_content_type(
+    db_path: &Path,
+    content_type: &str,
+) -> AiterResult<Vec<DocEntity>> {
+    let conn = open(db_path).await?;
+    let mut rows = conn
+        .query(
+            r#"
+SELECT "id", "source", "content_type", "title", "preview", "digest_start", "digest_end", "digest_retry", "digest_error", "created_at", "updated_at"
+FROM "doc"
+WHERE "content_type" = ?
+ORDER BY "updated_at" DESC
+;"#,
+            [content_type],
+        )
+    .await?;
+
+    DocEntity::collect_rows(&mut rows).await
+}
+
+pub async fn list_by_source(db_path: &Path, source: &str) -> AiterResult<Vec<DocEntity>> {
+    let conn = open(db_path).await?;
+    let mut rows = conn
+        .query(
+            r#"
+SELECT "id", "source", "content_type", "title", "preview", "digest_start", "digest_end", "digest_retry", "digest_error", "created_at", "updated_at"
+FROM "doc"
+WHERE "source" = ?
+ORDER BY "updated_at" DESC
+;"#,
+            [source],
+        )
+    .await?;
+
+    DocEntity::collect_rows(&mut rows).await
+}
+
 pub async fn list_digesting(db_path: &Path, limit: u64) -> AiterResult<Vec<DocEntity>> {
     let conn = open(db_path).await?;
     let mut rows = conn.query(r#"
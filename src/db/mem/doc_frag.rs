@@ -7,7 +7,7 @@ use crate::{
     DB_VECTOR_NEIGHBORS, DIGEST_RETRY, content,
     content::frag::FragContent,
     db::{
-        CURRENT_SIGNATURE_DIMS,
+        CURRENT_SIGNATURE_DIMS, ensure_vector_column,
         mem::{get_mem_signature_dims, get_mem_tokenizer},
         open, vec_f32_to_f16_str,
     },
@@ -107,6 +107,10 @@ CREATE VIRTUAL TABLE IF NOT EXISTS "doc_frag_fts" USING fts5(
     Ok(())
 }
 
+pub async fn ensure_embedding(db_path: &Path, dims: usize, clear: bool) -> AiterResult<()> {
+    ensure_vector_column(db_path, "doc_frag", "content_emb", dims, clear).await
+}
+
 pub async fn get(db_path: &Path, id: &str) -> AiterResult<Option<DocFragEntity>> {
     let conn = open(db_path).await?;
     let mut rows = conn
@@ -164,6 +168,23 @@ LIMIT 1
     Ok(DocFragEntity::collect_rows(&mut rows).await?.pop())
 }
 
+pub async fn list_not_embedded(db_path: &Path, limit: u64) -> AiterResult<Vec<DocFragEntity>> {
+    let conn = open(db_path).await?;
+    let mut rows = conn
+        .query(
+            r#"
+SELECT "id", "doc_id", "part_id", "seg_id", "index", "content", "content_type", "created_at", "updated_at"
+FROM "doc_frag"
+WHERE "content_emb" IS NULL
+LIMIT ?
+;"#,
+            [limit.max(1)],
+        )
+        .await?;
+
+    DocFragEntity::collect_rows(&mut rows).await
+}
+
 pub async fn list_not_digested_doc_ids(db_path: &Path) -> AiterResult<Vec<String>> {
     let conn = open(db_path).await?;
     let mut rows = conn
@@ -185,6 +206,34 @@ WHERE "digest_end" IS NULL
     Ok(vec)
 }
 
+/// Return ids with cosine similarity of the nearest embeddings
+pub async fn query_by_embedding(
+    db_path: &Path,
+    embedding: &[f32],
+    limit: u64,
+) -> AiterResult<Vec<(String, f64)>> {
+    let emb = vec_f32_to_f16_str(embedding);
+
+    let conn = open(db_path).await?;
+    let mut rows = conn
+        .query(
+            r#"
+SELECT "id", vector_distance_cos("content_emb", vector16(?1))
+FROM "doc_frag"
+WHERE rowid IN vector_top_k('idx_doc_frag_content_emb', vector16(?1), ?2)
+;"#,
+            (emb, limit.max(1)),
+        )
+        .await?;
+
+    let mut vec = vec![];
+    while let Some(row) = rows.next().await? {
+        vec.push((row.get::<String>(0)?, 1.0 - row.get::<f64>(1)?));
+    }
+
+    Ok(vec)
+}
+
 pub async fn query_by_search(
     db_path: &Path,
     search: &str,
@@ -324,6 +373,27 @@ WHERE
     Ok(())
 }
 
+pub async fn set_embeddings(db_path: &Path, embeddings: &[(String, Vec<f32>)]) -> AiterResult<()> {
+    let conn = open(db_path).await?;
+    let tx = conn.transaction().await?;
+
+    for (id, embedding) in embeddings {
+        tx.execute(
+            r#"
+UPDATE "doc_frag"
+SET "content_emb" = vector16(?)
+WHERE "id" = ?
+;"#,
+            (vec_f32_to_f16_str(embedding), id.as_str()),
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
 pub async fn upsert(db_path: &Path, doc_frag: &DocFrag, context: &str) -> AiterResult<()> {
     let content_str =
         content::frag::decode_content(&doc_frag.content, &doc_frag.content_type)?.to_string();
@@ -6,7 +6,7 @@ use ulid::Ulid;
 use crate::{
     DB_VECTOR_NEIGHBORS,
     db::{
-        CURRENT_SIGNATURE_DIMS,
+        CURRENT_SIGNATURE_DIMS, ensure_vector_column,
         mem::{doc, get_mem_signature_dims, get_mem_tokenizer},
         open, vec_f32_to_f16_str,
     },
@@ -99,6 +99,72 @@ CREATE VIRTUAL TABLE IF NOT EXISTS "doc_knl_fts" USING fts5(
     Ok(())
 }
 
+pub async fn ensure_embedding(db_path: &Path, dims: usize, clear: bool) -> AiterResult<()> {
+    ensure_vector_column(db_path, "doc_knl", "trigger_emb", dims, clear).await
+}
+
+pub async fn get(db_path: &Path, id: &str) -> AiterResult<Option<DocKnlEntity>> {
+    let conn = open(db_path).await?;
+    let mut rows = conn
+        .query(
+            r#"
+SELECT "id", "doc_id", "doc_ref", "trigger", "created_at", "updated_at"
+FROM "doc_knl"
+WHERE "id" = ?
+LIMIT 1
+;"#,
+            [id],
+        )
+        .await?;
+
+    Ok(DocKnlEntity::collect_rows(&mut rows).await?.pop())
+}
+
+pub async fn list_not_embedded(db_path: &Path, limit: u64) -> AiterResult<Vec<DocKnlEntity>> {
+    let conn = open(db_path).await?;
+    let mut rows = conn
+        .query(
+            r#"
+SELECT "id", "doc_id", "doc_ref", "trigger", "created_at", "updated_at"
+FROM "doc_knl"
+WHERE "trigger_emb" IS NULL
+LIMIT ?
+;"#,
+            [limit.max(1)],
+        )
+        .await?;
+
+    DocKnlEntity::collect_rows(&mut rows).await
+}
+
+/// Return ids with cosine similarity of the nearest embeddings
+pub async fn query_by_embedding(
+    db_path: &Path,
+    embedding: &[f32],
+    limit: u64,
+) -> AiterResult<Vec<(String, f64)>> {
+    let emb = vec_f32_to_f16_str(embedding);
+
+    let conn = open(db_path).await?;
+    let mut rows = conn
+        .query(
+            r#"
+SELECT "id", vector_distance_cos("trigger_emb", vector16(?1))
+FROM "doc_knl"
+WHERE rowid IN vector_top_k('idx_doc_knl_trigger_emb', vector16(?1), ?2)
+;"#,
+            (emb, limit.max(1)),
+        )
+        .await?;
+
+    let mut vec = vec![];
+    while let Some(row) = rows.next().await? {
+        vec.push((row.get::<String>(0)?, 1.0 - row.get::<f64>(1)?));
+    }
+
+    Ok(vec)
+}
+
 pub async fn query_by_search(
     db_path: &Path,
     search: &str,
@@ -149,6 +215,27 @@ WHERE rowid IN vector_top_k('idx_doc_knl_trigger_sig', vector16(?), ?)
     DocKnlEntity::collect_rows(&mut rows).await
 }
 
+pub async fn set_embeddings(db_path: &Path, embeddings: &[(String, Vec<f32>)]) -> AiterResult<()> {
+    let conn = open(db_path).await?;
+    let tx = conn.transaction().await?;
+
+    for (id, embedding) in embeddings {
+        tx.execute(
+            r#"
+UPDATE "doc_knl"
+SET "trigger_emb" = vector16(?)
+WHERE "id" = ?
+;"#,
+            (vec_f32_to_f16_str(embedding), id.as_str()),
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
 pub async fn upsert_batch(db_path: &Path, doc_knls: &[DocKnl], context: &str) -> AiterResult<()> {
     let signature_dims = get_mem_signature_dims(db_path);
     let tokenizer = get_mem_tokenizer(db_path);
@@ -66,6 +66,60 @@ LIMIT 1
     .transpose()
 }
 
+pub async fn get_embedding_dims(db_path: &Path) -> AiterResult<Option<String>> {
+    let conn = open(db_path).await?;
+    conn.query(
+        r#"
+SELECT "value"
+FROM "meta" 
+WHERE "key" = 'embedding_dims' 
+LIMIT 1
+;"#,
+        (),
+    )
+    .await?
+    .next()
+    .await?
+    .map(|row| Ok(row.get::<String>(0)?))
+    .transpose()
+}
+
+pub async fn get_embedding_model(db_path: &Path) -> AiterResult<Option<String>> {
+    let conn = open(db_path).await?;
+    conn.query(
+        r#"
+SELECT "value"
+FROM "meta" 
+WHERE "key" = 'embedding_model' 
+LIMIT 1
+;"#,
+        (),
+    )
+    .await?
+    .next()
+    .await?
+    .map(|row| Ok(row.get::<String>(0)?))
+    .transpose()
+}
+
+pub async fn get_prompt_lang(db_path: &Path) -> AiterResult<Option<String>> {
+    let conn = open(db_path).await?;
+    conn.query(
+        r#"
+SELECT "value"
+FROM "meta" 
+WHERE "key" = 'prompt_lang' 
+LIMIT 1
+;"#,
+        (),
+    )
+    .await?
+    .next()
+    .await?
+    .map(|row| Ok(row.get::<String>(0)?))
+    .transpose()
+}
+
 pub async fn get_signature_dims(db_path: &Path) -> AiterResult<Option<String>> {
     let conn = open(db_path).await?;
     conn.query(
@@ -101,3 +155,50 @@ LIMIT 1
     .map(|row| Ok(row.get::<String>(0)?))
     .transpose()
 }
+
+pub async fn set_embedding(db_path: &Path, dims: usize, model: &str) -> AiterResult<()> {
+    let conn = open(db_path).await?;
+    let tx = conn.transaction().await?;
+
+    tx.execute(
+        r#"
+INSERT INTO "meta" ("key", "value")
+VALUES ('embedding_dims', ?1)
+ON CONFLICT ("key") DO UPDATE SET
+    "value" = ?1
+;"#,
+        [dims.to_string()],
+    )
+    .await?;
+
+    tx.execute(
+        r#"
+INSERT INTO "meta" ("key", "value")
+VALUES ('embedding_model', ?1)
+ON CONFLICT ("key") DO UPDATE SET
+    "value" = ?1
+;"#,
+        [model],
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn set_prompt_lang(db_path: &Path, lang: &str) -> AiterResult<()> {
+    let conn = open(db_path).await?;
+    conn.execute(
+        r#"
+INSERT INTO "meta" ("key", "value")
+VALUES ('prompt_lang', ?1)
+ON CONFLICT ("key") DO UPDATE SET
+    "value" = ?1
+;"#,
+        [lang],
+    )
+    .await?;
+
+    Ok(())
+}
@@ -8,8 +8,8 @@ use ulid::Ulid;
 use crate::{
     DB_VECTOR_NEIGHBORS,
     db::{
-        CURRENT_SIGNATURE_DIMS,
-        mem::{get_mem_signature_dims, get_mem_tokenizer},
+        CURRENT_SIGNATURE_DIMS, ensure_vector_column,
+        mem::{get_mem_embedding_dims, get_mem_signature_dims, get_mem_tokenizer},
         open, vec_f32_to_f16_str,
     },
     error::AiterResult,
@@ -106,6 +106,10 @@ CREATE VIRTUAL TABLE IF NOT EXISTS "skill_fts" USING fts5(
     Ok(())
 }
 
+pub async fn ensure_embedding(db_path: &Path, dims: usize, clear: bool) -> AiterResult<()> {
+    ensure_vector_column(db_path, "skill", "trigger_emb", dims, clear).await
+}
+
 pub async fn delete(db_path: &Path, skill_id: &str) -> AiterResult<()> {
     let conn = open(db_path).await?;
     let tx = conn.transaction().await?;
@@ -216,6 +220,51 @@ OFFSET ?
     SkillEntity::collect_rows(&mut rows).await
 }
 
+pub async fn list_not_embedded(db_path: &Path, limit: u64) -> AiterResult<Vec<SkillEntity>> {
+    let conn = open(db_path).await?;
+    let mut rows = conn
+        .query(
+            r#"
+SELECT "id", "tool_id", "trigger", "created_at", "updated_at"
+FROM "skill"
+WHERE "trigger_emb" IS NULL
+LIMIT ?
+;"#,
+            [limit.max(1)],
+        )
+        .await?;
+
+    SkillEntity::collect_rows(&mut rows).await
+}
+
+/// Return ids with cosine similarity of the nearest embeddings
+pub async fn query_by_embedding(
+    db_path: &Path,
+    embedding: &[f32],
+    limit: u64,
+) -> AiterResult<Vec<(String, f64)>> {
+    let emb = vec_f32_to_f16_str(embedding);
+
+    let conn = open(db_path).await?;
+    let mut rows = conn
+        .query(
+            r#"
+SELECT "id", vector_distance_cos("trigger_emb", vector16(?1))
+FROM "skill"
+WHERE rowid IN vector_top_k('idx_skill_trigger_emb', vector16(?1), ?2)
+;"#,
+            (emb, limit.max(1)),
+        )
+        .await?;
+
+    let mut vec = vec![];
+    while let Some(row) = rows.next().await? {
+        vec.push((row.get::<String>(0)?, 1.0 - row.get::<f64>(1)?));
+    }
+
+    Ok(vec)
+}
+
 pub async fn query_by_search(
     db_path: &Path,
     search: &str,
@@ -266,6 +315,27 @@ WHERE rowid IN vector_top_k('idx_skill_trigger_sig', vector16(?), ?)
     SkillEntity::collect_rows(&mut rows).await
 }
 
+pub async fn set_embeddings(db_path: &Path, embeddings: &[(String, Vec<f32>)]) -> AiterResult<()> {
+    let conn = open(db_path).await?;
+    let tx = conn.transaction().await?;
+
+    for (id, embedding) in embeddings {
+        tx.execute(
+            r#"
+UPDATE "skill"
+SET "trigger_emb" = vector16(?)
+WHERE "id" = ?
+;"#,
+            (vec_f32_to_f16_str(embedding), id.as_str()),
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
 pub async fn upsert(db_path: &Path, skill: &Skill, context: &str) -> AiterResult<Option<String>> {
     let signature_dims = get_mem_signature_dims(db_path);
     let tokenizer = get_mem_tokenizer(db_path);
@@ -307,6 +377,19 @@ WHERE "id" = ?
             )
             .await?;
 
+            // The trigger may have been changed, so the embedding should be regenerated
+            if get_mem_embedding_dims(db_path).is_some() {
+                tx.execute(
+                    r#"
+UPDATE "skill"
+SET "trigger_emb" = NULL
+WHERE "id" = ?
+;"#,
+                    [id.as_str()],
+                )
+                .await?;
+            }
+
             tx.execute(
                 r#"
 UPDATE "skill_fts"
@@ -10,9 +10,46 @@ ON CONFLICT ("key") DO UPDATE SET
 pub static SQLS_UPDATE_CORE: [&[&str]; CURRENT_DB_VERSION as usize] = [
     // 0 -> 1
     &[],
+    // 1 -> 2
+    &[r#"
+CREATE TABLE IF NOT EXISTS "llm_cache" (
+    "llm_name"     TEXT NOT NULL,
+    "model"        TEXT NOT NULL,
+    "temperature"  REAL NOT NULL,
+    "prompt_hash"  TEXT NOT NULL,
+    "content"      TEXT NOT NULL,
+    "reasoning"    TEXT,
+    "hits"         INTEGER NOT NULL DEFAULT 0,
+    "created_at"   TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY ("llm_name", "model", "temperature", "prompt_hash"))
+;"#],
+    // 2 -> 3
+    &[
+        r#"
+CREATE TABLE IF NOT EXISTS "llm_usage" (
+    "id"                 INTEGER PRIMARY KEY AUTOINCREMENT,
+    "llm_name"           TEXT NOT NULL,
+    "model"              TEXT NOT NULL,
+    "ai"                 TEXT,
+    "purpose"            TEXT NOT NULL,
+    "doc_id"             TEXT,
+    "prompt_tokens"      INTEGER NOT NULL,
+    "completion_tokens"  INTEGER NOT NULL,
+    "estimated"          INTEGER NOT NULL DEFAULT 0,
+    "cost"               REAL,
+    "created_at"         TIMESTAMP DEFAULT CURRENT_TIMESTAMP)
+;"#,
+        r#"
+CREATE INDEX IF NOT EXISTS "idx_llm_usage_created_at" ON "llm_usage" ("created_at")
+;"#,
+    ],
 ];
 
 pub static SQLS_UPDATE_MEM: [&[&str]; CURRENT_DB_VERSION as usize] = [
     // 0 -> 1
     &[],
+    // 1 -> 2
+    &[],
+    // 2 -> 3
+    &[],
 ];
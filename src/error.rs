@@ -25,6 +25,9 @@ pub enum AiterError {
     #[error("[Event Error] {0}")]
     EventError(String),
 
+    #[error("[GeoJSON Error] {0}")]
+    GeoJsonError(String),
+
     #[error("[Hash Error] {0}")]
     HashError(String),
 
@@ -72,6 +75,19 @@ pub enum AiterError {
 
     #[error("[Xlsx Error] {0}")]
     XlsxError(#[from] calamine::Error),
+
+    #[error("[XML Error] {0}")]
+    XmlError(#[from] quick_xml::Error),
+
+    #[error("[Zip Error] {0}")]
+    ZipError(#[from] zip::result::ZipError),
+}
+
+// The error of GeoJSON is too large to be held in the variant directly
+impl From<geojson::Error> for AiterError {
+    fn from(err: geojson::Error) -> Self {
+        AiterError::GeoJsonError(err.to_string())
+    }
 }
 
 impl<T> From<tokio::sync::mpsc::error::SendError<T>> for AiterError {
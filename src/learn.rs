@@ -15,7 +15,7 @@ use tokio::{
 };
 
 use crate::{
-    AiterError, CHANNEL_BUFFER_DEFAULT, TRUNCATE_PROGRESS_MESSAGE,
+    AiterError, CHANNEL_BUFFER_DEFAULT, TRUNCATE_PROGRESS_MESSAGE, api,
     api::learn::*,
     content::doc::DocContent,
     db,
@@ -26,6 +26,7 @@ use crate::{
 };
 
 pub mod digest;
+pub mod embed;
 pub mod utils;
 
 pub async fn digest(
@@ -103,6 +104,9 @@ pub async fn digest(
     let total_frag_todo = Arc::new(AtomicUsize::new(0));
     let total_frag_done = Arc::new(AtomicUsize::new(0));
 
+    let llm = get_digest_llm_name(options).await?;
+    let lang = api::prompt::get_lang_by_mem_path(mem_path).await?;
+
     let mut handles: Vec<JoinHandle<AiterResult<()>>> = vec![];
     for i in 0..options.batch.max(1) {
         let mem_path = mem_path.to_path_buf();
@@ -111,6 +115,8 @@ pub async fn digest(
 
         let concurrent = options.concurrent;
         let deep = options.deep;
+        let llm = llm.clone();
+        let lang = lang.clone();
 
         let total_doc_done = Arc::clone(&total_doc_done);
         let total_part_todo = Arc::clone(&total_part_todo);
@@ -156,6 +162,8 @@ pub async fn digest(
                         &doc_id,
                         mem_write_event_sender.clone(),
                         progress_sender.clone(),
+                        llm.clone(),
+                        &lang,
                     ));
 
                     dgst.load_meta().await?;
@@ -245,6 +253,8 @@ pub async fn digest(
         }
     }
 
+    embed::embed_mem_silently(mem_path, mem_write_event_sender).await;
+
     Ok(DigestResult {
         doc_count: (total_doc_todo, total_doc_done.load(SeqCst)),
         part_count: (total_part_todo.load(SeqCst), total_part_done.load(SeqCst)),
@@ -290,6 +300,8 @@ pub async fn digest_doc(
 
         let concurrent = options.concurrent;
         let deep = options.deep;
+        let llm = get_digest_llm_name(options).await?;
+        let lang = api::prompt::get_lang_by_mem_path(&mem_path).await?;
 
         let doc_part_todo = AtomicUsize::new(0);
         let doc_part_done = AtomicUsize::new(0);
@@ -321,6 +333,8 @@ pub async fn digest_doc(
                 doc_id,
                 mem_write_event_sender.clone(),
                 progress_sender.clone(),
+                llm.clone(),
+                &lang,
             ));
 
             dgst.load_meta().await?;
@@ -374,12 +388,16 @@ pub async fn digest_doc(
                 seg_done,
                 frag_todo,
                 frag_done,
-            }) => Ok(DigestResult {
-                doc_count: (1, 1),
-                part_count: (part_todo, part_done),
-                seg_size: (seg_todo, seg_done),
-                frag_size: (frag_todo, frag_done),
-            }),
+            }) => {
+                embed::embed_mem_silently(&mem_path, mem_write_event_sender.clone()).await;
+
+                Ok(DigestResult {
+                    doc_count: (1, 1),
+                    part_count: (part_todo, part_done),
+                    seg_size: (seg_todo, seg_done),
+                    frag_size: (frag_todo, frag_done),
+                })
+            }
 
             Err(err) => {
                 let (resp_sender, resp_receiver) = oneshot::channel();
@@ -512,3 +530,135 @@ pub async fn read_doc(
         )))
     }
 }
+
+/// The LLM specified by options takes precedence, then the active digest LLM, and then the active chat LLM
+async fn get_digest_llm_name(options: &DigestOptions) -> AiterResult<Option<String>> {
+    if options.llm.is_some() {
+        Ok(options.llm.clone())
+    } else if let Some(name) = api::llm::get_actived_name("digest").await? {
+        Ok(Some(name))
+    } else {
+        api::llm::get_actived_name("chat").await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ulid::Ulid;
+
+    use super::*;
+    use crate::{
+        content::doc::text::TextDoc,
+        db::{ensure_mem_tables, ensure_test_tables},
+    };
+
+    #[tokio::test]
+    async fn test_get_digest_llm_name() {
+        ensure_test_tables().await;
+
+        // Names of LLMs not exist, so that other tests relying on active LLMs are not affected
+        let chat_llm_name = Ulid::new().to_string();
+        let digest_llm_name = Ulid::new().to_string();
+        let mut options = DigestOptions {
+            batch: 1,
+            concurrent: 1,
+            deep: false,
+            llm: None,
+            retry: false,
+        };
+
+        db::core::config::set(&db::core::config::ConfigKey::ActiveChatLlm, &chat_llm_name)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_digest_llm_name(&options).await.unwrap(),
+            Some(chat_llm_name)
+        );
+
+        db::core::config::set(
+            &db::core::config::ConfigKey::ActiveDigestLlm,
+            &digest_llm_name,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            get_digest_llm_name(&options).await.unwrap(),
+            Some(digest_llm_name)
+        );
+
+        options.llm = Some("specified".to_string());
+        assert_eq!(
+            get_digest_llm_name(&options).await.unwrap(),
+            Some("specified".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_digest() {
+        ensure_test_tables().await;
+
+        // Responses are recorded by the record protocol, so digesting is replayed without network
+        let cassette = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/cassettes/digest.jsonl");
+        let llm_name = Ulid::new().to_string();
+        db::core::llm::upsert(
+            &llm_name,
+            "chat",
+            "replay",
+            &HashMap::from([(
+                "cassette".to_string(),
+                cassette.to_string_lossy().to_string(),
+            )]),
+        )
+        .await
+        .unwrap();
+
+        let mem_path = crate::DATA_DIR.join(format!("{}.db", Ulid::new()));
+        ensure_mem_tables(&mem_path).await.unwrap();
+        let mem_write_event_sender = spawn_mem_write(&mem_path);
+
+        let doc_content = TextDoc {
+            title: Some("Aiter".to_string()),
+            pages: vec![
+                "Aiter learns documents into a local memory backed by SQLite. Questions are answered \
+                 from the memory, so nothing learned leaves the machine."
+                    .to_string(),
+            ],
+            ..Default::default()
+        };
+        read_doc(
+            &mem_path,
+            "aiter.txt",
+            &doc_content,
+            mem_write_event_sender.clone(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let result = digest(
+            &mem_path,
+            &DigestOptions {
+                batch: 1,
+                concurrent: 1,
+                deep: false,
+                llm: Some(llm_name),
+                retry: false,
+            },
+            mem_write_event_sender,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.doc_count, (1, 1));
+        assert_eq!(result.part_count, (1, 1));
+
+        // Implicit knowledges extracted by the LLM are searchable in the memory
+        let implicits = doc_implicit::query_by_search(&mem_path, "SQLite", 10, false)
+            .await
+            .unwrap();
+        assert_eq!(implicits.len(), 1);
+        assert!(implicits[0].content.contains("SQLite"));
+    }
+}
@@ -20,14 +20,19 @@ use crate::{
     TRUNCATE_PROGRESS_MESSAGE, api, content,
     content::{
         doc::{DocContentType, sheet::SheetDoc},
-        seg::SegContentType,
+        seg::{SegContent, SegContentType, code::CodeSegContent},
     },
     db::mem::get_mem_tokenizer,
     error::AiterResult,
     learn::{DigestEvent, *},
     llm::{
-        ChatCompletionOptions,
-        prompt::summarize::{make_summarize_sheet_prompt, make_summarize_text_prompt},
+        ChatCompletionOptions, UsageTag,
+        prompt::{
+            refer::{make_doc_refer, make_part_refer},
+            summarize::{
+                make_summarize_code_prompt, make_summarize_sheet_prompt, make_summarize_text_prompt,
+            },
+        },
     },
     utils::{
         markdown::extract_code_block,
@@ -40,6 +45,8 @@ pub struct DocDigestor {
     doc_id: String,
     mem_write_event_sender: Sender<MemWriteEvent>,
     progress_sender: Option<Sender<DigestEvent>>,
+    llm: Option<String>,
+    lang: String,
 
     doc_meta: DashMap<String, String>,
     doc_refers: DashSet<String>,
@@ -61,12 +68,16 @@ impl DocDigestor {
         doc_id: &str,
         mem_write_event_sender: Sender<MemWriteEvent>,
         progress_sender: Option<Sender<DigestEvent>>,
+        llm: Option<String>,
+        lang: &str,
     ) -> Self {
         Self {
             mem_path: mem_path.to_path_buf(),
             doc_id: doc_id.to_string(),
             mem_write_event_sender: mem_write_event_sender.clone(),
             progress_sender: progress_sender.clone(),
+            llm,
+            lang: lang.to_string(),
 
             doc_meta: DashMap::new(),
             doc_refers: DashSet::new(),
@@ -83,6 +94,9 @@ impl DocDigestor {
             let doc_id = self.doc_id.clone();
             let mem_write_event_sender = self.mem_write_event_sender.clone();
             let progress_sender = self.progress_sender.clone();
+            let usage_tag = self.usage_tag("digest-frag");
+            let llm = self.llm.clone();
+            let lang = self.lang.clone();
 
             let doc_source = self
                 .doc_meta
@@ -156,7 +170,14 @@ impl DocDigestor {
                         doc_ref.insert("seg_id".to_string(), frag.seg_id.to_string());
                         doc_ref.insert("frag_id".to_string(), frag.id.to_string());
 
-                        let questions = utils::extract_questions(&frag_text, &refers).await?;
+                        let questions = utils::extract_questions(
+                            &frag_text,
+                            &refers,
+                            &usage_tag,
+                            llm.as_deref(),
+                            &lang,
+                        )
+                        .await?;
                         let doc_knls = questions
                             .iter()
                             .map(|question| {
@@ -238,6 +259,9 @@ impl DocDigestor {
             let doc_id = self.doc_id.clone();
             let mem_write_event_sender = self.mem_write_event_sender.clone();
             let progress_sender = self.progress_sender.clone();
+            let usage_tag = self.usage_tag("digest-part");
+            let llm = self.llm.clone();
+            let lang = self.lang.clone();
 
             let doc_content_type = self.doc_meta.get("content_type").map(|v| v.to_string());
             let doc_source = self
@@ -288,9 +312,7 @@ impl DocDigestor {
 
                     let mut refers: Vec<String> = doc_refers.clone();
                     if let Some(title) = &part.title {
-                        refers.push(format!(
-                            "这部分内容的标题为`{title}`，其中可能包含概括这部分内容的关键信息"
-                        ));
+                        refers.push(make_part_refer(title, &lang));
                     }
 
                     if let Some(progress_sender) = &progress_sender {
@@ -328,6 +350,9 @@ impl DocDigestor {
                                 &tokenizer,
                                 1,
                                 progress_sender.clone(),
+                                &usage_tag,
+                                llm.as_deref(),
+                                &lang,
                             )
                             .await?;
 
@@ -341,6 +366,9 @@ impl DocDigestor {
                                 &tokenizer,
                                 1,
                                 progress_sender.clone(),
+                                &usage_tag,
+                                llm.as_deref(),
+                                &lang,
                             )
                             .await?;
                             for (text, questions) in questions_map {
@@ -417,8 +445,11 @@ impl DocDigestor {
                                         {
                                             let sheet_text = sheet_data.to_string();
 
-                                            let prompt =
-                                                make_summarize_sheet_prompt(&sheet_text, &refers);
+                                            let prompt = make_summarize_sheet_prompt(
+                                                &sheet_text,
+                                                &refers,
+                                                &lang,
+                                            );
                                             let part_summary = extract_code_block(
                                                 &api::llm::chat_completion(
                                                     &prompt,
@@ -426,8 +457,9 @@ impl DocDigestor {
                                                     &ChatCompletionOptions::default()
                                                         .with_temperature(
                                                             LLM_CHAT_TEMPERATURE_STABLE,
-                                                        ),
-                                                    None,
+                                                        )
+                                                        .with_usage_tag(usage_tag.clone()),
+                                                    llm.as_deref(),
                                                 )
                                                 .await?
                                                 .content,
@@ -454,6 +486,9 @@ impl DocDigestor {
                                                     utils::extract_implicit_knowledges(
                                                         &part_summary,
                                                         &refers,
+                                                        &usage_tag,
+                                                        llm.as_deref(),
+                                                        &lang,
                                                     )
                                                     .await?;
                                                 for (text, questions) in questions_map {
@@ -579,6 +614,9 @@ impl DocDigestor {
             let doc_id = self.doc_id.clone();
             let mem_write_event_sender = self.mem_write_event_sender.clone();
             let progress_sender = self.progress_sender.clone();
+            let usage_tag = self.usage_tag("digest-seg");
+            let llm = self.llm.clone();
+            let lang = self.lang.clone();
 
             let doc_source = self
                 .doc_meta
@@ -636,11 +674,22 @@ impl DocDigestor {
                         // Summarize
                         {
                             let prompt = match seg_content_type {
+                                SegContentType::Code => {
+                                    let code_seg = CodeSegContent::try_from_bytes(&seg.content)?;
+                                    make_summarize_code_prompt(
+                                        &seg_text,
+                                        &code_seg.path,
+                                        &code_seg.language,
+                                        &code_seg.symbols,
+                                        &doc_refers,
+                                        &lang,
+                                    )
+                                }
                                 SegContentType::Sheet => {
-                                    make_summarize_sheet_prompt(&seg_text, &doc_refers)
+                                    make_summarize_sheet_prompt(&seg_text, &doc_refers, &lang)
                                 }
                                 SegContentType::Text => {
-                                    make_summarize_text_prompt(&seg_text, &doc_refers)
+                                    make_summarize_text_prompt(&seg_text, &doc_refers, &lang)
                                 }
                             };
 
@@ -649,8 +698,9 @@ impl DocDigestor {
                                     &prompt,
                                     &[],
                                     &ChatCompletionOptions::default()
-                                        .with_temperature(LLM_CHAT_TEMPERATURE_STABLE),
-                                    None,
+                                        .with_temperature(LLM_CHAT_TEMPERATURE_STABLE)
+                                        .with_usage_tag(usage_tag.clone()),
+                                    llm.as_deref(),
                                 )
                                 .await?
                                 .content,
@@ -674,9 +724,14 @@ impl DocDigestor {
                         if let SegContentType::Text = seg_content_type {
                             // Extract implicit knowledges
                             {
-                                let questions_map =
-                                    utils::extract_implicit_knowledges(&seg_text, &doc_refers)
-                                        .await?;
+                                let questions_map = utils::extract_implicit_knowledges(
+                                    &seg_text,
+                                    &doc_refers,
+                                    &usage_tag,
+                                    llm.as_deref(),
+                                    &lang,
+                                )
+                                .await?;
                                 for (text, questions) in questions_map {
                                     let implicit = doc_implicit::DocImplicit::new(&doc_id, &text);
 
@@ -783,10 +838,8 @@ impl DocDigestor {
             self.doc_meta
                 .insert("context".to_string(), doc_context.to_string());
 
-            self.doc_refers.insert(format!(
-                "内容标题为`{}`，其中可能包含概括这部分内容的关键信息",
-                &doc_context
-            ));
+            self.doc_refers
+                .insert(make_doc_refer(&doc_context, &self.lang));
         }
 
         Ok(())
@@ -841,6 +894,9 @@ impl DocDigestor {
                 &tokenizer,
                 concurrent,
                 self.progress_sender.clone(),
+                &self.usage_tag("digest-doc"),
+                self.llm.as_deref(),
+                &self.lang,
             )
             .await?;
 
@@ -867,6 +923,9 @@ impl DocDigestor {
                 &tokenizer,
                 concurrent,
                 self.progress_sender.clone(),
+                &self.usage_tag("digest-doc"),
+                self.llm.as_deref(),
+                &self.lang,
             )
             .await?;
             for (text, questions) in questions_map {
@@ -920,4 +979,10 @@ impl DocDigestor {
 
         Ok(())
     }
+
+    fn usage_tag(&self, purpose: &str) -> UsageTag {
+        UsageTag::new(purpose)
+            .with_mem_path(&self.mem_path)
+            .with_doc_id(&self.doc_id)
+    }
 }
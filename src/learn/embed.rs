@@ -0,0 +1,198 @@
+use std::{collections::HashMap, path::Path};
+
+use tokio::sync::{mpsc::Sender, oneshot};
+
+use crate::{
+    EMBED_BATCH, api, content, db,
+    db::mem::{MemWriteEvent, get_mem_embedding_dims},
+    error::AiterResult,
+};
+
+/// Generate embeddings for contents which are not embedded yet, do nothing if no embedding LLM is active
+pub async fn embed_mem(
+    mem_path: &Path,
+    mem_write_event_sender: Sender<MemWriteEvent>,
+) -> AiterResult<usize> {
+    let Some(llm_name) = api::llm::get_actived_name("embedding").await? else {
+        return Ok(0);
+    };
+    let Some(llm) = api::llm::get_by_name(&llm_name).await? else {
+        return Ok(0);
+    };
+
+    let model = llm.options.get("model").unwrap_or(&llm.name).to_string();
+
+    // Probe the dims of the embedding LLM if mem is not ready for it
+    let embedding_model = db::mem::meta::get_embedding_model(mem_path).await?;
+    if get_mem_embedding_dims(mem_path).is_none() || embedding_model.as_ref() != Some(&model) {
+        let probe = api::llm::embeddings(&["embedding".to_string()], Some(&llm_name)).await?;
+        let dims = probe.first().map_or(0, |e| e.len());
+        if dims == 0 {
+            return Ok(0);
+        }
+
+        let (resp_sender, resp_receiver) = oneshot::channel();
+        mem_write_event_sender
+            .send(MemWriteEvent::EnsureEmbedding {
+                dims,
+                model: model.clone(),
+                resp_sender,
+            })
+            .await?;
+        resp_receiver.await??;
+    }
+
+    let mut count: usize = 0;
+    let mut contexts: HashMap<String, String> = HashMap::new();
+
+    loop {
+        let doc_frags = db::mem::doc_frag::list_not_embedded(mem_path, EMBED_BATCH as u64).await?;
+        if doc_frags.is_empty() {
+            break;
+        }
+
+        let mut items: Vec<(String, String)> = vec![];
+        for doc_frag in doc_frags {
+            let context = get_doc_context(mem_path, &doc_frag.doc_id, &mut contexts).await?;
+            let frag_content =
+                content::frag::decode_content(&doc_frag.content, &doc_frag.content_type)?
+                    .to_string();
+            items.push((doc_frag.id, format!("**{context}** {frag_content}")));
+        }
+
+        let embeddings = embed_items(&items, &llm_name).await?;
+        count += embeddings.len();
+
+        let (resp_sender, resp_receiver) = oneshot::channel();
+        mem_write_event_sender
+            .send(MemWriteEvent::SetDocFragEmbeddings {
+                embeddings,
+                resp_sender,
+            })
+            .await?;
+        resp_receiver.await??;
+    }
+
+    loop {
+        let doc_implicits =
+            db::mem::doc_implicit::list_not_embedded(mem_path, EMBED_BATCH as u64).await?;
+        if doc_implicits.is_empty() {
+            break;
+        }
+
+        let mut items: Vec<(String, String)> = vec![];
+        for doc_implicit in doc_implicits {
+            let context = get_doc_context(mem_path, &doc_implicit.doc_id, &mut contexts).await?;
+            items.push((
+                doc_implicit.id,
+                format!("**{}** {}", context, doc_implicit.content),
+            ));
+        }
+
+        let embeddings = embed_items(&items, &llm_name).await?;
+        count += embeddings.len();
+
+        let (resp_sender, resp_receiver) = oneshot::channel();
+        mem_write_event_sender
+            .send(MemWriteEvent::SetDocImplicitEmbeddings {
+                embeddings,
+                resp_sender,
+            })
+            .await?;
+        resp_receiver.await??;
+    }
+
+    loop {
+        let doc_knls = db::mem::doc_knl::list_not_embedded(mem_path, EMBED_BATCH as u64).await?;
+        if doc_knls.is_empty() {
+            break;
+        }
+
+        let mut items: Vec<(String, String)> = vec![];
+        for doc_knl in doc_knls {
+            let context = get_doc_context(mem_path, &doc_knl.doc_id, &mut contexts).await?;
+            items.push((doc_knl.id, format!("**{}** {}", context, doc_knl.trigger)));
+        }
+
+        let embeddings = embed_items(&items, &llm_name).await?;
+        count += embeddings.len();
+
+        let (resp_sender, resp_receiver) = oneshot::channel();
+        mem_write_event_sender
+            .send(MemWriteEvent::SetDocKnlEmbeddings {
+                embeddings,
+                resp_sender,
+            })
+            .await?;
+        resp_receiver.await??;
+    }
+
+    loop {
+        let skills = db::mem::skill::list_not_embedded(mem_path, EMBED_BATCH as u64).await?;
+        if skills.is_empty() {
+            break;
+        }
+
+        let mut items: Vec<(String, String)> = vec![];
+        for skill in skills {
+            let context = db::core::tool::get(&skill.tool_id)
+                .await?
+                .map(|tool| tool.toolset_title)
+                .unwrap_or_default();
+            items.push((skill.id, format!("**{}** {}", context, skill.trigger)));
+        }
+
+        let embeddings = embed_items(&items, &llm_name).await?;
+        count += embeddings.len();
+
+        let (resp_sender, resp_receiver) = oneshot::channel();
+        mem_write_event_sender
+            .send(MemWriteEvent::SetSkillEmbeddings {
+                embeddings,
+                resp_sender,
+            })
+            .await?;
+        resp_receiver.await??;
+    }
+
+    Ok(count)
+}
+
+/// Same as `embed_mem` but only log the error, embedding is optional for retrieval
+pub async fn embed_mem_silently(mem_path: &Path, mem_write_event_sender: Sender<MemWriteEvent>) {
+    if let Err(err) = embed_mem(mem_path, mem_write_event_sender).await {
+        log::warn!("Embed mem error: {err}");
+    }
+}
+
+async fn embed_items(
+    items: &[(String, String)],
+    llm_name: &str,
+) -> AiterResult<Vec<(String, Vec<f32>)>> {
+    let texts: Vec<String> = items.iter().map(|(_, text)| text.to_string()).collect();
+    let embeddings = api::llm::embeddings(&texts, Some(llm_name)).await?;
+
+    Ok(items
+        .iter()
+        .map(|(id, _)| id.to_string())
+        .zip(embeddings)
+        .collect())
+}
+
+async fn get_doc_context(
+    mem_path: &Path,
+    doc_id: &str,
+    contexts: &mut HashMap<String, String>,
+) -> AiterResult<String> {
+    if let Some(context) = contexts.get(doc_id) {
+        return Ok(context.to_string());
+    }
+
+    let context = db::mem::doc::get(mem_path, doc_id)
+        .await?
+        .map(|doc| doc.get_context())
+        .unwrap_or_default();
+    contexts.insert(doc_id.to_string(), context.clone());
+
+    Ok(context)
+}
@@ -14,9 +14,14 @@ use crate::{
     api::learn::DigestEvent,
     error::AiterResult,
     llm::{
-        ChatCompletionOptions,
+        ChatCompletionOptions, UsageTag,
         prompt::{
-            extract::{make_extract_implicit_knowledges_prompt, make_extract_questions_prompt},
+            extract::{
+                ExtractImplicitKnowledgesResponse, ExtractQuestionsResponse,
+                make_extract_implicit_knowledges_prompt,
+                make_extract_implicit_knowledges_response_format, make_extract_questions_prompt,
+                make_extract_questions_response_format,
+            },
             generate::make_fix_json_prompt,
             summarize::make_summarize_text_prompt,
         },
@@ -30,36 +35,47 @@ use crate::{
 pub async fn extract_implicit_knowledges(
     text: &str,
     refers: &[String],
+    usage_tag: &UsageTag,
+    chat_llm_name: Option<&str>,
+    lang: &str,
 ) -> AiterResult<QuestionsMap> {
     let mut questions_map: QuestionsMap = HashMap::new();
 
-    let prompt = make_extract_implicit_knowledges_prompt(text, refers);
+    let prompt = make_extract_implicit_knowledges_prompt(text, refers, lang);
     let json_text = extract_code_block(
         &api::llm::chat_completion(
             &prompt,
             &[],
-            &ChatCompletionOptions::default().with_temperature(LLM_CHAT_TEMPERATURE_STABLE),
-            None,
+            &ChatCompletionOptions::default()
+                .with_temperature(LLM_CHAT_TEMPERATURE_STABLE)
+                .with_response_format(make_extract_implicit_knowledges_response_format())
+                .with_usage_tag(usage_tag.clone()),
+            chat_llm_name,
         )
         .await?
         .content,
     );
-    if let Ok(map) = serde_json::from_str::<QuestionsMap>(&json_text) {
-        questions_map.extend(map);
+    // The fix is only needed for providers without native structured output
+    if let Ok(response) = serde_json::from_str::<ExtractImplicitKnowledgesResponse>(&json_text) {
+        merge_implicit_knowledges(&mut questions_map, response);
     } else {
-        let prompt = make_fix_json_prompt(&json_text);
+        let prompt = make_fix_json_prompt(&json_text, lang);
         let fixed_json_text = extract_code_block(
             &api::llm::chat_completion(
                 &prompt,
                 &[],
-                &ChatCompletionOptions::default().with_temperature(LLM_CHAT_TEMPERATURE_STABLE),
-                None,
+                &ChatCompletionOptions::default()
+                    .with_temperature(LLM_CHAT_TEMPERATURE_STABLE)
+                    .with_usage_tag(usage_tag.clone()),
+                chat_llm_name,
             )
             .await?
             .content,
         );
-        if let Ok(map) = serde_json::from_str::<QuestionsMap>(&fixed_json_text) {
-            questions_map.extend(map);
+        if let Ok(response) =
+            serde_json::from_str::<ExtractImplicitKnowledgesResponse>(&fixed_json_text)
+        {
+            merge_implicit_knowledges(&mut questions_map, response);
         } else {
             log::debug!(
                 "LLM extract implicit knowledges failed: {}\nParse JSON error: {}",
@@ -72,6 +88,7 @@ pub async fn extract_implicit_knowledges(
     Ok(questions_map)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn extract_implicit_knowledges_across_texts(
     source: &str,
     texts: &[String],
@@ -80,6 +97,9 @@ pub async fn extract_implicit_knowledges_across_texts(
     tokenizer: &Tokenizer,
     concurrent: usize,
     event_sender: Option<Sender<DigestEvent>>,
+    usage_tag: &UsageTag,
+    chat_llm_name: Option<&str>,
+    lang: &str,
 ) -> AiterResult<QuestionsMap> {
     let semaphore = Arc::new(Semaphore::new(concurrent.max(1)));
     let mut handles: Vec<JoinHandle<AiterResult<QuestionsMap>>> = vec![];
@@ -98,9 +118,19 @@ pub async fn extract_implicit_knowledges_across_texts(
             }
 
             let refers = refers.to_vec();
+            let usage_tag = usage_tag.clone();
+            let chat_llm_name = chat_llm_name.map(|s| s.to_string());
+            let lang = lang.to_string();
 
             let handle = task::spawn(async move {
-                let result = extract_implicit_knowledges(&window_text, &refers).await?;
+                let result = extract_implicit_knowledges(
+                    &window_text,
+                    &refers,
+                    &usage_tag,
+                    chat_llm_name.as_deref(),
+                    &lang,
+                )
+                .await?;
 
                 drop(permit);
                 Ok(result)
@@ -125,35 +155,47 @@ pub async fn extract_implicit_knowledges_across_texts(
     Ok(questions_map)
 }
 
-pub async fn extract_questions(text: &str, refers: &[String]) -> AiterResult<Vec<String>> {
-    let prompt = make_extract_questions_prompt(text, refers);
+pub async fn extract_questions(
+    text: &str,
+    refers: &[String],
+    usage_tag: &UsageTag,
+    chat_llm_name: Option<&str>,
+    lang: &str,
+) -> AiterResult<Vec<String>> {
+    let prompt = make_extract_questions_prompt(text, refers, lang);
     let json_text = extract_code_block(
         &api::llm::chat_completion(
             &prompt,
             &[],
-            &ChatCompletionOptions::default().with_temperature(LLM_CHAT_TEMPERATURE_STABLE),
-            None,
+            &ChatCompletionOptions::default()
+                .with_temperature(LLM_CHAT_TEMPERATURE_STABLE)
+                .with_response_format(make_extract_questions_response_format())
+                .with_usage_tag(usage_tag.clone()),
+            chat_llm_name,
         )
         .await?
         .content,
     );
-    if let Ok(vec) = serde_json::from_str::<Vec<String>>(&json_text) {
-        let questions: HashSet<String> = vec.into_iter().collect();
+    // The fix is only needed for providers without native structured output
+    if let Ok(response) = serde_json::from_str::<ExtractQuestionsResponse>(&json_text) {
+        let questions: HashSet<String> = response.questions.into_iter().collect();
         return Ok(questions.into_iter().collect());
     } else {
-        let prompt = make_fix_json_prompt(&json_text);
+        let prompt = make_fix_json_prompt(&json_text, lang);
         let fixed_json_text = extract_code_block(
             &api::llm::chat_completion(
                 &prompt,
                 &[],
-                &ChatCompletionOptions::default().with_temperature(LLM_CHAT_TEMPERATURE_STABLE),
-                None,
+                &ChatCompletionOptions::default()
+                    .with_temperature(LLM_CHAT_TEMPERATURE_STABLE)
+                    .with_usage_tag(usage_tag.clone()),
+                chat_llm_name,
             )
             .await?
             .content,
         );
-        if let Ok(vec) = serde_json::from_str::<Vec<String>>(&fixed_json_text) {
-            let questions: HashSet<String> = vec.into_iter().collect();
+        if let Ok(response) = serde_json::from_str::<ExtractQuestionsResponse>(&fixed_json_text) {
+            let questions: HashSet<String> = response.questions.into_iter().collect();
             return Ok(questions.into_iter().collect());
         } else {
             log::debug!(
@@ -167,6 +209,7 @@ pub async fn extract_questions(text: &str, refers: &[String]) -> AiterResult<Vec
     Ok(vec![])
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn summarize_across_texts(
     source: &str,
     texts: &[String],
@@ -175,6 +218,9 @@ pub async fn summarize_across_texts(
     tokenizer: &Tokenizer,
     concurrent: usize,
     event_sender: Option<Sender<DigestEvent>>,
+    usage_tag: &UsageTag,
+    chat_llm_name: Option<&str>,
+    lang: &str,
 ) -> AiterResult<Vec<String>> {
     let semaphore = Arc::new(Semaphore::new(concurrent.max(1)));
     let mut handles: Vec<JoinHandle<AiterResult<(usize, String)>>> = vec![];
@@ -193,16 +239,20 @@ pub async fn summarize_across_texts(
             }
 
             let refers = refers.to_vec();
+            let usage_tag = usage_tag.clone();
+            let chat_llm_name = chat_llm_name.map(|s| s.to_string());
+            let lang = lang.to_string();
 
             let handle = task::spawn(async move {
-                let prompt = make_summarize_text_prompt(&window_text, &refers);
+                let prompt = make_summarize_text_prompt(&window_text, &refers, &lang);
                 let summary = extract_code_block(
                     &api::llm::chat_completion(
                         &prompt,
                         &[],
                         &ChatCompletionOptions::default()
-                            .with_temperature(LLM_CHAT_TEMPERATURE_STABLE),
-                        None,
+                            .with_temperature(LLM_CHAT_TEMPERATURE_STABLE)
+                            .with_usage_tag(usage_tag.clone()),
+                        chat_llm_name.as_deref(),
                     )
                     .await?
                     .content,
@@ -228,9 +278,21 @@ pub async fn summarize_across_texts(
         }
     }
 
-    summaries.sort_by(|a, b| a.0.cmp(&b.0));
+    summaries.sort_by_key(|a| a.0);
 
     Ok(summaries.into_iter().map(|(_, summary)| summary).collect())
 }
 
 type QuestionsMap = HashMap<String, Vec<String>>;
+
+fn merge_implicit_knowledges(
+    questions_map: &mut QuestionsMap,
+    response: ExtractImplicitKnowledgesResponse,
+) {
+    for implicit in response.knowledges {
+        questions_map
+            .entry(implicit.knowledge)
+            .or_default()
+            .extend(implicit.questions);
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::{collections::HashMap, env, fs::create_dir_all, path::PathBuf, sync::LazyLock};
 
+#[cfg(not(test))]
 use directories::ProjectDirs;
 use rayon::prelude::*;
 
@@ -27,6 +28,15 @@ pub async fn init() {
         create_dir_all(&*DATA_DIR).expect("Unable to create data directory!");
     }
 
+    // Check version before ensuring tables, which updates the version of core database
+    let mut need_update = false;
+    if DB_CORE_PATH.exists() {
+        if let Ok(Some(db_version_str)) = db::core::meta::get_db_version().await {
+            let db_version = db_version_str.parse::<u64>().unwrap_or(CURRENT_DB_VERSION);
+            need_update = db_version < CURRENT_DB_VERSION;
+        }
+    }
+
     let ensure_db = async || {
         db::ensure_core_tables().await?;
         db::ensure_mem_tables(&DB_DEFAULT_MEM_PATH).await?;
@@ -39,12 +49,9 @@ pub async fn init() {
     }
 
     // Update database if needed
-    if let Ok(Some(db_version_str)) = db::core::meta::get_db_version().await {
-        let db_version = db_version_str.parse::<u64>().unwrap_or(CURRENT_DB_VERSION);
-        if db_version < CURRENT_DB_VERSION {
-            if let Err(err) = api::sys::update().await {
-                panic!("Update database error: {err}");
-            }
+    if need_update {
+        if let Err(err) = api::sys::update().await {
+            panic!("Update database error: {err}");
         }
     }
 }
@@ -57,10 +64,11 @@ mod llm;
 mod retrieve;
 mod tool;
 
-static CURRENT_DB_VERSION: u64 = 1; // Update when the db schema has been changed, schema patches are updated in the db.updates module
+static CURRENT_DB_VERSION: u64 = 3; // Update when the db schema has been changed, schema patches are updated in the db.updates module
 static CURRENT_SIGNATURE_DIMS: usize = 256;
 static CURRENT_TOKENIZER: Tokenizer = Tokenizer::O200kBase;
 
+#[cfg(not(test))]
 static DATA_DIR: LazyLock<PathBuf> =
     LazyLock::new(|| match ProjectDirs::from("", "", env!("CARGO_PKG_NAME")) {
         Some(proj_dirs) => proj_dirs.data_dir().to_path_buf(),
@@ -69,6 +77,16 @@ static DATA_DIR: LazyLock<PathBuf> =
             .join("data"),
     });
 
+// Tests of each process work with their own data
+#[cfg(test)]
+static DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+    env::temp_dir().join(format!(
+        "{}-test-{}",
+        env!("CARGO_PKG_NAME"),
+        std::process::id()
+    ))
+});
+
 static DB_BUSY_SECS: u64 = 5;
 static DB_CORE_PATH: LazyLock<PathBuf> = LazyLock::new(|| DATA_DIR.join("core.db"));
 static DB_DEFAULT_MEM_PATH: LazyLock<PathBuf> = LazyLock::new(|| DATA_DIR.join("mem.db"));
@@ -76,13 +94,27 @@ static DB_VECTOR_NEIGHBORS: LazyLock<usize> =
     LazyLock::new(|| 3 * (((CURRENT_SIGNATURE_DIMS / 256) as f64).sqrt() as usize).max(1));
 
 static CHAT_HISTORY_LIMIT: u64 = 100;
+static CHAT_TOOL_STEPS_DEFAULT: usize = 5;
 static DIGEST_RETRY: u64 = 3;
+static EMBED_BATCH: usize = 32;
+static FETCH_MAX_BYTES: u64 = 100 * 1024 * 1024;
+static FETCH_MAX_RETRY: usize = 3;
+static FETCH_TIMEOUT_SECS: u64 = 60;
 static FILTER_INFORMATIVE_TOKENS: usize = 5;
 static LLM_CHAT_TEMPERATURE_DEFAULT: f64 = 0.6;
 static LLM_CHAT_TEMPERATURE_STABLE: f64 = 0.0;
+static LLM_COMPLETION_RESERVED_TOKENS: usize = 2048;
+static LLM_MAX_RETRY_DEFAULT: usize = 3;
+static LLM_TIMEOUT_SECS_DEFAULT: u64 = 300;
+static RERANK_LLM_BATCH: usize = 10;
+static RERANK_TOKENS_BUDGET: usize = 8000;
+static RERANK_TOP_K: usize = 20;
 static RETRIEVE_FRAG_SURROUND: usize = 1;
 static RETRIEVE_FTS_LIMIT: usize = 10;
 static RETRIEVE_VEC_LIMIT: usize = 10;
+static RETRY_BACKOFF_BASE_MILLIS: u64 = 1000;
+static RETRY_BACKOFF_MAX_MILLIS: u64 = 60000;
+static SPATIAL_LOOKUP_LIMIT_DEFAULT: usize = 10;
 static SPLIT_TOKENS_OF_FRAG: usize = 160;
 static SPLIT_TOKENS_OF_SEG: usize = 1600;
 static TRUNCATE_LOG_MESSAGE: usize = 100;
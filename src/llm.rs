@@ -1,9 +1,22 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fs::read,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
+use base64::prelude::*;
+use serde_json::{Map, Value, json};
 use tokio::sync::mpsc::Receiver;
+use tokio_util::sync::CancellationToken;
 
-use crate::{AiterError, LLM_CHAT_TEMPERATURE_DEFAULT, chat::ChatCallToolTask};
+use crate::{
+    AiterError, LLM_CHAT_TEMPERATURE_DEFAULT, chat::ChatCallToolTask, error::AiterResult,
+    utils::crypto::sha256,
+};
 
+pub mod check;
+pub mod limiter;
 pub mod prompt;
 pub mod provider;
 
@@ -14,35 +27,44 @@ pub enum ChatCompletionEvent {
     CallToolFail(String, String, String),
     Content(String),
     ReasoningContent(String),
+    Usage(ChatUsage),
     Error(AiterError),
 }
 
+#[derive(Clone)]
 pub struct ChatCompletionOptions {
     pub enable_think: bool, // Some multi-mode-models can switch between think/nothink mode, such as qwen3
     pub temperature: f64,
+    pub max_tokens: Option<u64>,
+    pub top_p: Option<f64>,
+    pub stop: Vec<String>,
+    pub seed: Option<u64>,
+    pub presence_penalty: Option<f64>,
+    pub frequency_penalty: Option<f64>,
+    pub extra_body: Option<Map<String, Value>>, // Merged into the request body as is, for parameters specific to the provider
+    pub response_format: Option<ResponseFormat>,
+    pub usage_tag: UsageTag,
 }
 
+/// The producer should stop when the cancel token is cancelled, which happens on closing or dropping the stream
 pub struct ChatCompletionStream {
     receiver: Receiver<ChatCompletionEvent>,
+    llm_name: Option<String>,
+    cancel_token: CancellationToken,
 }
 
 #[derive(Debug)]
 pub struct ChatFunction {
     pub name: String,
     pub description: String,
-    pub parameters: HashMap<String, ChatFunctionParameter>,
+    pub parameters: Value, // JSON Schema of the arguments object
 }
 
-#[derive(Debug)]
-pub struct ChatFunctionParameter {
-    pub r#type: String,
-    pub description: String,
-}
-
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ChatFunctionCall {
+    pub id: String,
     pub name: String,
-    pub arguments: HashMap<String, String>,
+    pub arguments: Value,
 }
 
 #[derive(Clone, Debug)]
@@ -50,6 +72,37 @@ pub struct ChatMessage {
     pub role: Role,
     pub content: String,
     pub reasoning: Option<String>,
+    pub tool_calls: Vec<ChatFunctionCall>, // Calls requested by bot, results are fed back by following tool messages
+    pub tool_call_id: Option<String>,      // Which call the tool message is the result of
+    pub parts: Vec<ChatContentPart>,       // Parts following the text content, such as images
+}
+
+#[derive(Clone, Debug)]
+pub enum ChatContentPart {
+    Text(String),
+    Image { mime_type: String, data: Vec<u8> },
+}
+
+/// JSON schema the response must conform to, providers without native structured output ignore it
+#[derive(Clone, Debug)]
+pub struct ResponseFormat {
+    pub name: String,
+    pub schema: Value,
+}
+
+/// Tokens reported by the LLM service
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChatUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// What the tokens are spent for, recorded along with usage
+#[derive(Clone, Debug, Default)]
+pub struct UsageTag {
+    pub purpose: String,
+    pub mem_path: Option<PathBuf>,
+    pub doc_id: Option<String>,
 }
 
 #[allow(dead_code)]
@@ -68,32 +121,404 @@ impl Default for ChatCompletionOptions {
         Self {
             enable_think: false,
             temperature: LLM_CHAT_TEMPERATURE_DEFAULT,
+            max_tokens: None,
+            top_p: None,
+            stop: vec![],
+            seed: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            extra_body: None,
+            response_format: None,
+            usage_tag: UsageTag::new("chat"),
+        }
+    }
+}
+
+impl ChatContentPart {
+    pub fn from_image(mime_type: &str, data: Vec<u8>) -> AiterResult<Self> {
+        if !mime_type.starts_with("image/") {
+            return Err(AiterError::Unsupported(format!(
+                "Attachment of type '{mime_type}' is not an image"
+            )));
+        }
+
+        Ok(Self::Image {
+            mime_type: mime_type.to_string(),
+            data,
+        })
+    }
+
+    /// Read image file as a part, the MIME type is guessed from the file extension
+    pub fn from_image_path(path: &Path) -> AiterResult<Self> {
+        let mime_type = mime_guess::from_path(path).first_or_octet_stream();
+        Self::from_image(mime_type.essence_str(), read(path)?)
+    }
+
+    /// JSON identifying the part in cache keys, image data is replaced by its hash
+    pub fn to_key_value(&self) -> Value {
+        match self {
+            Self::Text(text) => json!({
+                "type": "text",
+                "text": text,
+            }),
+            Self::Image { mime_type, data } => json!({
+                "type": "image",
+                "mime_type": mime_type,
+                "sha256": sha256(data),
+            }),
+        }
+    }
+
+    /// Image data encoded in base64, none for text
+    pub fn to_base64(&self) -> Option<String> {
+        match self {
+            Self::Text(_) => None,
+            Self::Image { data, .. } => Some(BASE64_STANDARD.encode(data)),
         }
     }
 }
 
 impl ChatCompletionOptions {
+    /// Sampling options which are set, to identify the request in cache keys and cassettes
+    pub fn sampling_key_value(&self) -> Map<String, Value> {
+        let mut map = Map::new();
+
+        if let Some(max_tokens) = self.max_tokens {
+            map.insert("max_tokens".to_string(), max_tokens.into());
+        }
+        if let Some(top_p) = self.top_p {
+            map.insert("top_p".to_string(), top_p.into());
+        }
+        if !self.stop.is_empty() {
+            map.insert("stop".to_string(), self.stop.clone().into());
+        }
+        if let Some(seed) = self.seed {
+            map.insert("seed".to_string(), seed.into());
+        }
+        if let Some(presence_penalty) = self.presence_penalty {
+            map.insert("presence_penalty".to_string(), presence_penalty.into());
+        }
+        if let Some(frequency_penalty) = self.frequency_penalty {
+            map.insert("frequency_penalty".to_string(), frequency_penalty.into());
+        }
+        if let Some(extra_body) = &self.extra_body {
+            map.insert("extra_body".to_string(), extra_body.clone().into());
+        }
+
+        map
+    }
+
     pub fn with_enable_think(mut self, enable_think: bool) -> Self {
         self.enable_think = enable_think;
         self
     }
 
+    /// Fill sampling options not set yet with defaults from options of the LLM, `extra_body` is merged
+    pub fn with_llm_defaults(self, llm_options: &HashMap<String, String>) -> AiterResult<Self> {
+        let defaults = Self::default().with_llm_options(llm_options)?;
+
+        let extra_body = match (defaults.extra_body, self.extra_body) {
+            (Some(mut extra_body), Some(overrides)) => {
+                extra_body.extend(overrides);
+                Some(extra_body)
+            }
+            (defaults, overrides) => overrides.or(defaults),
+        };
+
+        Ok(Self {
+            max_tokens: self.max_tokens.or(defaults.max_tokens),
+            top_p: self.top_p.or(defaults.top_p),
+            stop: if self.stop.is_empty() {
+                defaults.stop
+            } else {
+                self.stop
+            },
+            seed: self.seed.or(defaults.seed),
+            presence_penalty: self.presence_penalty.or(defaults.presence_penalty),
+            frequency_penalty: self.frequency_penalty.or(defaults.frequency_penalty),
+            extra_body,
+            ..self
+        })
+    }
+
+    /// Set options such as `top_p:0.9` by name, unknown names are ignored
+    pub fn with_llm_options(mut self, options: &HashMap<String, String>) -> AiterResult<Self> {
+        if let Some(temperature) = parse_option(
+            options,
+            "temperature",
+            |v| (0.0..=2.0).contains(v),
+            "0 to 2",
+        )? {
+            self.temperature = temperature;
+        }
+        if let Some(max_tokens) =
+            parse_option(options, "max_tokens", |v| *v > 0, "a positive integer")?
+        {
+            self.max_tokens = Some(max_tokens);
+        }
+        if let Some(top_p) = parse_option(options, "top_p", |v| (0.0..=1.0).contains(v), "0 to 1")?
+        {
+            self.top_p = Some(top_p);
+        }
+        if let Some(seed) = parse_option(options, "seed", |_: &u64| true, "an integer")? {
+            self.seed = Some(seed);
+        }
+        if let Some(presence_penalty) = parse_option(
+            options,
+            "presence_penalty",
+            |v| (-2.0..=2.0).contains(v),
+            "-2 to 2",
+        )? {
+            self.presence_penalty = Some(presence_penalty);
+        }
+        if let Some(frequency_penalty) = parse_option(
+            options,
+            "frequency_penalty",
+            |v| (-2.0..=2.0).contains(v),
+            "-2 to 2",
+        )? {
+            self.frequency_penalty = Some(frequency_penalty);
+        }
+
+        // Multiple stop sequences are given as JSON array, otherwise the value is a single sequence
+        if let Some(value) = options.get("stop") {
+            self.stop = if value.starts_with('[') {
+                serde_json::from_str(value)
+                    .map_err(|_| invalid_option("stop", value, "a JSON array of strings"))?
+            } else {
+                vec![value.to_string()]
+            };
+        }
+
+        if let Some(value) = options.get("extra_body") {
+            match serde_json::from_str(value) {
+                Ok(Value::Object(extra_body)) => self.extra_body = Some(extra_body),
+                _ => return Err(invalid_option("extra_body", value, "a JSON object")),
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn with_response_format(mut self, response_format: ResponseFormat) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
+
     pub fn with_temperature(mut self, temperature: f64) -> Self {
         self.temperature = temperature;
         self
     }
+
+    pub fn with_usage_tag(mut self, usage_tag: UsageTag) -> Self {
+        self.usage_tag = usage_tag;
+        self
+    }
+}
+
+impl ChatMessage {
+    pub fn new(role: Role, content: &str) -> Self {
+        Self {
+            role,
+            content: content.to_string(),
+            reasoning: None,
+            tool_calls: vec![],
+            tool_call_id: None,
+            parts: vec![],
+        }
+    }
+}
+
+impl ResponseFormat {
+    pub fn new(name: &str, schema: Value) -> Self {
+        Self {
+            name: name.to_string(),
+            schema,
+        }
+    }
+}
+
+impl UsageTag {
+    pub fn new(purpose: &str) -> Self {
+        Self {
+            purpose: purpose.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_doc_id(mut self, doc_id: &str) -> Self {
+        self.doc_id = Some(doc_id.to_string());
+        self
+    }
+
+    pub fn with_mem_path(mut self, mem_path: &Path) -> Self {
+        self.mem_path = Some(mem_path.to_path_buf());
+        self
+    }
+
+    pub fn with_purpose(mut self, purpose: &str) -> Self {
+        self.purpose = purpose.to_string();
+        self
+    }
 }
 
 impl ChatCompletionStream {
     pub fn new(receiver: Receiver<ChatCompletionEvent>) -> Self {
-        Self { receiver }
+        Self {
+            receiver,
+            llm_name: None,
+            cancel_token: CancellationToken::new(),
+        }
+    }
+
+    pub fn with_cancel_token(mut self, cancel_token: CancellationToken) -> Self {
+        self.cancel_token = cancel_token;
+        self
+    }
+
+    pub fn with_llm_name(mut self, llm_name: &str) -> Self {
+        self.llm_name = Some(llm_name.to_string());
+        self
+    }
+
+    /// Cancel the producer, the stream ends after it stops, so that its cleanup can be awaited
+    pub fn cancel(&self) {
+        self.cancel_token.cancel();
     }
 
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.cancel_token.clone()
+    }
+
+    /// Cancel the producer and stop receiving, only events already sent can be received
     pub fn close(&mut self) {
+        self.cancel_token.cancel();
         self.receiver.close()
     }
 
+    /// Name of the LLM which actually serves the stream, may be a fallback of the requested one
+    pub fn llm_name(&self) -> Option<&str> {
+        self.llm_name.as_deref()
+    }
+
     pub async fn next(&mut self) -> Option<ChatCompletionEvent> {
         self.receiver.recv().await
     }
 }
+
+impl Drop for ChatCompletionStream {
+    fn drop(&mut self) {
+        self.cancel_token.cancel();
+    }
+}
+
+fn invalid_option(name: &str, value: &str, expected: &str) -> AiterError {
+    AiterError::Invalid(format!(
+        "Option '{name}' is invalid: '{value}', expected {expected}"
+    ))
+}
+
+fn parse_option<T: FromStr>(
+    options: &HashMap<String, String>,
+    name: &str,
+    is_valid: impl Fn(&T) -> bool,
+    expected: &str,
+) -> AiterResult<Option<T>> {
+    match options.get(name) {
+        Some(value) => match value.parse::<T>() {
+            Ok(parsed) if is_valid(&parsed) => Ok(Some(parsed)),
+            _ => Err(invalid_option(name, value, expected)),
+        },
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn options(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_with_llm_options() {
+        let parsed = ChatCompletionOptions::default()
+            .with_llm_options(&options(&[
+                ("max_tokens", "512"),
+                ("top_p", "0.9"),
+                ("stop", r#"["\n\n", "END"]"#),
+                ("seed", "42"),
+                ("extra_body", r#"{"top_k": 20}"#),
+                ("base_url", "http://localhost"),
+            ]))
+            .unwrap();
+        assert_eq!(parsed.max_tokens, Some(512));
+        assert_eq!(parsed.top_p, Some(0.9));
+        assert_eq!(parsed.stop, vec!["\n\n", "END"]);
+        assert_eq!(parsed.seed, Some(42));
+        assert_eq!(parsed.extra_body.unwrap()["top_k"], 20);
+
+        for invalid in [
+            ("temperature", "3"),
+            ("max_tokens", "0"),
+            ("top_p", "1.5"),
+            ("seed", "-1"),
+            ("presence_penalty", "abc"),
+            ("stop", "[1]"),
+            ("extra_body", "[]"),
+        ] {
+            assert!(
+                ChatCompletionOptions::default()
+                    .with_llm_options(&options(&[invalid]))
+                    .is_err(),
+                "{invalid:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_with_llm_defaults() {
+        let merged = ChatCompletionOptions::default()
+            .with_llm_options(&options(&[
+                ("top_p", "0.5"),
+                ("extra_body", r#"{"a": 1, "b": 1}"#),
+            ]))
+            .unwrap()
+            .with_llm_defaults(&options(&[
+                ("top_p", "0.9"),
+                ("seed", "7"),
+                ("extra_body", r#"{"b": 2, "c": 2}"#),
+            ]))
+            .unwrap();
+        assert_eq!(merged.top_p, Some(0.5));
+        assert_eq!(merged.seed, Some(7));
+        assert_eq!(
+            Value::from(merged.extra_body.unwrap()),
+            json!({"a": 1, "b": 1, "c": 2})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_cancel_on_drop() {
+        let (sender, receiver) = mpsc::channel(1);
+        let stream = ChatCompletionStream::new(receiver);
+        let cancel_token = stream.cancel_token();
+        assert!(!cancel_token.is_cancelled());
+
+        drop(stream);
+        assert!(cancel_token.is_cancelled());
+        assert!(sender.is_closed());
+
+        // A given token is cancelled along with the stream
+        let (_sender, receiver) = mpsc::channel(1);
+        let cancel_token = CancellationToken::new();
+        drop(ChatCompletionStream::new(receiver).with_cancel_token(cancel_token.clone()));
+        assert!(cancel_token.is_cancelled());
+    }
+}
@@ -0,0 +1,263 @@
+use std::time::Instant;
+
+use serde::Serialize;
+use serde_json::json;
+use tabled::Tabled;
+
+use crate::{
+    error::{AiterError, AiterResult},
+    llm::{
+        ChatCompletionEvent, ChatCompletionOptions, ChatFunction, ChatMessage, Role,
+        provider::{ChatProvider, LlmProvider},
+    },
+};
+
+/// Result of a diagnostic, the ones with capability are saved to options of the LLM as `supports_<capability>`
+#[derive(Debug, Serialize, Tabled)]
+pub struct LlmCheckItem {
+    #[tabled(rename = "Check")]
+    pub name: String,
+
+    #[tabled(rename = "Passed")]
+    pub passed: bool,
+
+    #[tabled(rename = "Detail")]
+    pub detail: String,
+
+    #[tabled(skip)]
+    pub capability: Option<String>,
+}
+
+/// Run diagnostics of the provider by its type, checks depending on a failed one are skipped
+pub async fn check(
+    provider: &LlmProvider,
+    r#type: &str,
+    options: &ChatCompletionOptions,
+) -> Vec<LlmCheckItem> {
+    let mut items: Vec<LlmCheckItem> = vec![];
+
+    // Listing models needs both base URL and authentication to be right
+    let authenticated = match provider.models().await {
+        Ok(models) => {
+            items.push(LlmCheckItem::new("Base URL", true, "Reachable"));
+            items.push(LlmCheckItem::new(
+                "Authentication",
+                true,
+                &format!("{} models listed", models.len()),
+            ));
+            true
+        }
+        Err(AiterError::HttpStatusError(status)) if is_auth_error(&status) => {
+            items.push(LlmCheckItem::new("Base URL", true, "Reachable"));
+            items.push(LlmCheckItem::new("Authentication", false, &status));
+            return items;
+        }
+        Err(err @ (AiterError::HttpStatusError(_) | AiterError::Unsupported(_))) => {
+            // Some services do not list models, authentication is then proved by the following request
+            items.push(LlmCheckItem::new(
+                "Base URL",
+                true,
+                &format!("Models not listed: {err}"),
+            ));
+            false
+        }
+        Err(err) => {
+            items.push(LlmCheckItem::new("Base URL", false, &err.to_string()));
+            return items;
+        }
+    };
+
+    let started = Instant::now();
+    let first_request = match r#type {
+        "embedding" => check_embeddings(provider).await,
+        "rerank" => check_rerank(provider).await,
+        _ => check_streaming(provider, options).await,
+    };
+    match first_request {
+        Ok((item, first_token_millis)) => {
+            if !authenticated {
+                items.push(LlmCheckItem::new(
+                    "Authentication",
+                    true,
+                    "Request accepted",
+                ));
+            }
+
+            let total_millis = started.elapsed().as_millis();
+            let passed = item.passed;
+            items.push(item);
+            items.push(LlmCheckItem::new(
+                "Latency",
+                passed,
+                &match first_token_millis {
+                    Some(first_token_millis) => {
+                        format!("First token {first_token_millis} ms, total {total_millis} ms")
+                    }
+                    None => format!("Total {total_millis} ms"),
+                },
+            ));
+        }
+        Err(AiterError::HttpStatusError(status)) if is_auth_error(&status) => {
+            items.push(LlmCheckItem::new("Authentication", false, &status));
+            return items;
+        }
+        Err(err) => {
+            items.push(match r#type {
+                "embedding" => LlmCheckItem::new("Embeddings", false, &err.to_string()),
+                "rerank" => LlmCheckItem::new("Rerank", false, &err.to_string()),
+                _ => LlmCheckItem::new("Streaming", false, &err.to_string())
+                    .with_capability("streaming"),
+            });
+            return items;
+        }
+    }
+
+    if !["embedding", "rerank"].contains(&r#type) {
+        items.push(check_function_calling(provider).await);
+        items.push(check_reasoning_content(provider, options).await);
+    }
+
+    items
+}
+
+impl LlmCheckItem {
+    fn new(name: &str, passed: bool, detail: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            passed,
+            detail: detail.to_string(),
+            capability: None,
+        }
+    }
+
+    fn with_capability(mut self, capability: &str) -> Self {
+        self.capability = Some(capability.to_string());
+        self
+    }
+}
+
+async fn check_embeddings(provider: &LlmProvider) -> AiterResult<(LlmCheckItem, Option<u128>)> {
+    let embeddings = provider.embeddings(&["Hello".to_string()]).await?;
+    let dims = embeddings.first().map(|e| e.len()).unwrap_or_default();
+
+    Ok((
+        LlmCheckItem::new("Embeddings", dims > 0, &format!("{dims} dims")),
+        None,
+    ))
+}
+
+async fn check_function_calling(provider: &LlmProvider) -> LlmCheckItem {
+    let messages = [ChatMessage::new(Role::User, "What time is it in UTC now?")];
+    let functions = [ChatFunction {
+        name: "get_current_time".to_string(),
+        description: "Get the current time of the timezone".to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": {
+                "timezone": {
+                    "type": "string",
+                    "description": "IANA timezone, e.g. UTC",
+                },
+            },
+            "required": ["timezone"],
+        }),
+    }];
+
+    let item = match provider.chat_function_calls(&messages, &functions).await {
+        Ok(calls) if calls.iter().any(|call| call.name == "get_current_time") => {
+            LlmCheckItem::new("Function Calling", true, "Function called")
+        }
+        Ok(_) => LlmCheckItem::new("Function Calling", false, "No function called"),
+        Err(err) => LlmCheckItem::new("Function Calling", false, &err.to_string()),
+    };
+
+    item.with_capability("function_calling")
+}
+
+async fn check_reasoning_content(
+    provider: &LlmProvider,
+    options: &ChatCompletionOptions,
+) -> LlmCheckItem {
+    let messages = [ChatMessage::new(
+        Role::User,
+        "Which is larger, 9.11 or 9.9?",
+    )];
+    let options = options.clone().with_enable_think(true);
+
+    let item = match provider.stream_chat_completion(&messages, &options).await {
+        Ok(mut stream) => {
+            let mut reasoning_chunks = 0;
+            let mut error: Option<AiterError> = None;
+            while let Some(event) = stream.next().await {
+                match event {
+                    ChatCompletionEvent::ReasoningContent(_) => reasoning_chunks += 1,
+                    ChatCompletionEvent::Error(err) => {
+                        error = Some(err);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+
+            match error {
+                Some(err) => LlmCheckItem::new("Reasoning Content", false, &err.to_string()),
+                None if reasoning_chunks > 0 => LlmCheckItem::new(
+                    "Reasoning Content",
+                    true,
+                    &format!("{reasoning_chunks} chunks received"),
+                ),
+                None => LlmCheckItem::new("Reasoning Content", false, "No reasoning received"),
+            }
+        }
+        Err(err) => LlmCheckItem::new("Reasoning Content", false, &err.to_string()),
+    };
+
+    item.with_capability("reasoning_content")
+}
+
+async fn check_rerank(provider: &LlmProvider) -> AiterResult<(LlmCheckItem, Option<u128>)> {
+    let scores = provider
+        .rerank("Hello", &["Hello".to_string(), "World".to_string()])
+        .await?;
+
+    Ok((
+        LlmCheckItem::new("Rerank", scores.len() == 2, &format!("Scores {scores:?}")),
+        None,
+    ))
+}
+
+/// Returns the item along with milliseconds to the first token
+async fn check_streaming(
+    provider: &LlmProvider,
+    options: &ChatCompletionOptions,
+) -> AiterResult<(LlmCheckItem, Option<u128>)> {
+    let messages = [ChatMessage::new(Role::User, "Reply with OK only.")];
+
+    let started = Instant::now();
+    let mut stream = provider.stream_chat_completion(&messages, options).await?;
+
+    let mut chunks = 0;
+    let mut first_token_millis: Option<u128> = None;
+    while let Some(event) = stream.next().await {
+        match event {
+            ChatCompletionEvent::Content(_) | ChatCompletionEvent::ReasoningContent(_) => {
+                first_token_millis.get_or_insert_with(|| started.elapsed().as_millis());
+                chunks += 1;
+            }
+            ChatCompletionEvent::Error(err) => return Err(err),
+            _ => {}
+        }
+    }
+
+    let item = if chunks > 0 {
+        LlmCheckItem::new("Streaming", true, &format!("{chunks} chunks received"))
+    } else {
+        LlmCheckItem::new("Streaming", false, "No content received")
+    };
+
+    Ok((item.with_capability("streaming"), first_token_millis))
+}
+
+fn is_auth_error(status: &str) -> bool {
+    status.starts_with("401") || status.starts_with("403")
+}
@@ -0,0 +1,248 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    sync::{
+        Arc, LazyLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use futures::FutureExt;
+use serde::Serialize;
+use tabled::Tabled;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+
+use crate::utils::net::BEFORE_RETRY;
+
+static LIMITERS: LazyLock<std::sync::Mutex<HashMap<String, Arc<LlmLimiter>>>> =
+    LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+static RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Limit in-flight requests and requests per minute of a LLM, shared by all callers in current process
+pub struct LlmLimiter {
+    max_concurrency: Option<usize>,
+    rpm: Option<usize>,
+    semaphore: Option<Arc<Semaphore>>,
+    requests: Mutex<VecDeque<Instant>>,
+    in_flight: AtomicUsize,
+}
+
+/// Release the acquired slot when dropped
+pub struct LlmPermit {
+    limiter: Arc<LlmLimiter>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+#[derive(Clone, Serialize, Tabled)]
+pub struct LlmUtilizationEntity {
+    #[tabled(rename = "Name")]
+    pub name: String,
+
+    #[tabled(rename = "Concurrency")]
+    #[tabled(format("{}", format_usage(self.in_flight, self.max_concurrency)))]
+    pub in_flight: usize,
+
+    #[tabled(skip)]
+    pub max_concurrency: Option<usize>,
+
+    #[tabled(rename = "Requests per Minute")]
+    #[tabled(format("{}", format_usage(self.requests_in_minute, self.rpm)))]
+    pub requests_in_minute: usize,
+
+    #[tabled(skip)]
+    pub rpm: Option<usize>,
+}
+
+/// Wait until the LLM has a free slot, limits are read from `max_concurrency` and `rpm` options
+pub async fn acquire(name: &str, options: &HashMap<String, String>) -> LlmPermit {
+    let limiter = get_limiter(name, options);
+
+    let permit = if let Some(semaphore) = &limiter.semaphore {
+        semaphore.clone().acquire_owned().await.ok()
+    } else {
+        None
+    };
+
+    limiter.wait_rate().await;
+    limiter.in_flight.fetch_add(1, Ordering::Relaxed);
+
+    LlmPermit {
+        limiter,
+        _permit: permit,
+    }
+}
+
+pub async fn utilization(name: &str, options: &HashMap<String, String>) -> LlmUtilizationEntity {
+    let limiter = get_limiter(name, options);
+
+    let requests_in_minute = {
+        let requests = limiter.requests.lock().await;
+        requests
+            .iter()
+            .filter(|t| t.elapsed() < RATE_WINDOW)
+            .count()
+    };
+
+    LlmUtilizationEntity {
+        name: name.to_string(),
+        in_flight: limiter.in_flight.load(Ordering::Relaxed),
+        max_concurrency: limiter.max_concurrency,
+        requests_in_minute,
+        rpm: limiter.rpm,
+    }
+}
+
+impl LlmLimiter {
+    fn new(max_concurrency: Option<usize>, rpm: Option<usize>) -> Self {
+        Self {
+            max_concurrency,
+            rpm,
+            semaphore: max_concurrency.map(|n| Arc::new(Semaphore::new(n))),
+            requests: Mutex::new(VecDeque::new()),
+            in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    async fn wait_rate(&self) {
+        let Some(rpm) = self.rpm else {
+            return;
+        };
+
+        // Hold the lock while sleeping, so that waiters are served in order
+        let mut requests = self.requests.lock().await;
+        loop {
+            let now = Instant::now();
+            while requests
+                .front()
+                .is_some_and(|t| now.duration_since(*t) >= RATE_WINDOW)
+            {
+                requests.pop_front();
+            }
+
+            if requests.len() < rpm {
+                requests.push_back(now);
+                return;
+            }
+
+            if let Some(earliest) = requests.front() {
+                tokio::time::sleep(RATE_WINDOW.saturating_sub(now.duration_since(*earliest))).await;
+            }
+        }
+    }
+}
+
+impl LlmPermit {
+    /// Run the requests of `future`, each retry is counted against the rate limit as a new request
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        let limiter = self.limiter.clone();
+        BEFORE_RETRY
+            .scope(
+                Arc::new(move || {
+                    let limiter = limiter.clone();
+                    async move { limiter.wait_rate().await }.boxed()
+                }),
+                future,
+            )
+            .await
+    }
+}
+
+impl Drop for LlmPermit {
+    fn drop(&mut self) {
+        self.limiter.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn format_usage(used: usize, limit: Option<usize>) -> String {
+    if let Some(limit) = limit {
+        format!("{used}/{limit}")
+    } else {
+        format!("{used}/-")
+    }
+}
+
+fn get_limiter(name: &str, options: &HashMap<String, String>) -> Arc<LlmLimiter> {
+    let parse_limit = |key: &str| {
+        options
+            .get(key)
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+    };
+    let max_concurrency = parse_limit("max_concurrency");
+    let rpm = parse_limit("rpm");
+
+    let mut limiters = LIMITERS.lock().unwrap_or_else(|err| err.into_inner());
+    if let Some(limiter) = limiters.get(name) {
+        if limiter.max_concurrency == max_concurrency && limiter.rpm == rpm {
+            return limiter.clone();
+        }
+    }
+
+    // Options have been changed, requests in flight keep the replaced limiter until they finish
+    let limiter = Arc::new(LlmLimiter::new(max_concurrency, rpm));
+    limiters.insert(name.to_string(), limiter.clone());
+
+    limiter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        llm::provider::mock::{mock_response, mock_server_with_responses},
+        utils::net::send_with_retry,
+    };
+
+    #[tokio::test]
+    async fn test_acquire() {
+        let name = format!("test-{}", ulid::Ulid::new());
+        let options = HashMap::from([
+            ("max_concurrency".to_string(), "2".to_string()),
+            ("rpm".to_string(), "3".to_string()),
+        ]);
+
+        let permit1 = acquire(&name, &options).await;
+        let permit2 = acquire(&name, &options).await;
+
+        let limited = tokio::time::timeout(Duration::from_millis(50), acquire(&name, &options));
+        assert!(limited.await.is_err());
+
+        drop(permit1);
+        let _permit3 = acquire(&name, &options).await;
+
+        let utilization = utilization(&name, &options).await;
+        assert_eq!(utilization.in_flight, 2);
+        assert_eq!(utilization.requests_in_minute, 3);
+
+        // The rate limit is reached even though concurrency is available
+        let limited = tokio::time::timeout(Duration::from_millis(50), acquire(&name, &options));
+        drop(permit2);
+        assert!(limited.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_scope_counts_retries() {
+        let name = format!("test-{}", ulid::Ulid::new());
+        let options = HashMap::from([("rpm".to_string(), "5".to_string())]);
+        let base_url = mock_server_with_responses(vec![
+            mock_response(
+                "503 Service Unavailable",
+                &[("Retry-After", "0")],
+                "text/plain",
+                "",
+            ),
+            mock_response("200 OK", &[], "text/plain", "OK"),
+        ]);
+
+        let permit = acquire(&name, &options).await;
+        let response = permit
+            .scope(send_with_retry(reqwest::Client::new().get(&base_url), 1))
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        let utilization = utilization(&name, &options).await;
+        assert_eq!(utilization.requests_in_minute, 2);
+    }
+}
@@ -1,4 +1,156 @@
+use std::{fs::read_to_string, path::PathBuf};
+
+use crate::DATA_DIR;
+
 pub mod extract;
 pub mod generate;
 pub mod intent;
+pub mod refer;
+pub mod rerank;
 pub mod summarize;
+
+pub static PROMPT_LANGS: [&str; 2] = ["en", "zh"];
+pub static PROMPT_LANG_DEFAULT: &str = "zh";
+
+macro_rules! builtin_templates {
+    ($($name:literal),* $(,)?) => {
+        static BUILTIN_TEMPLATES: &[(&str, &str, &str)] = &[
+            $((
+                $name,
+                include_str!(concat!("prompt/templates/en/", $name, ".txt")),
+                include_str!(concat!("prompt/templates/zh/", $name, ".txt")),
+            )),*
+        ];
+    };
+}
+
+builtin_templates!(
+    "answer_by_candidates",
+    "answer_by_candidates_strict",
+    "answer_history_questions",
+    "digest_doc_refer",
+    "digest_part_refer",
+    "extract_implicit_knowledges",
+    "extract_queries",
+    "extract_queries_history_questions",
+    "extract_questions",
+    "fix_json",
+    "no_answer",
+    "score_relevance",
+    "sheet_refers",
+    "simplify_queries",
+    "summarize_code",
+    "summarize_sheet",
+    "summarize_text",
+    "text_refers",
+);
+
+pub fn get_builtin_template(lang: &str, name: &str) -> Option<&'static str> {
+    BUILTIN_TEMPLATES
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .map(|(_, en, zh)| if lang == "en" { *en } else { *zh })
+}
+
+/// The user's template file overrides the built-in one, it is read on every use so edits take effect immediately
+pub fn get_template(lang: &str, name: &str) -> String {
+    if let Ok(template) = read_to_string(get_template_override_path(lang, name)) {
+        return template;
+    }
+
+    get_builtin_template(lang, name)
+        .unwrap_or_default()
+        .to_string()
+}
+
+pub fn get_template_override_path(lang: &str, name: &str) -> PathBuf {
+    DATA_DIR
+        .join("prompts")
+        .join(lang)
+        .join(format!("{name}.txt"))
+}
+
+pub fn is_lang_valid(lang: &str) -> bool {
+    PROMPT_LANGS.contains(&lang)
+}
+
+pub fn list_template_names() -> Vec<&'static str> {
+    BUILTIN_TEMPLATES.iter().map(|(name, _, _)| *name).collect()
+}
+
+/// Render the template by replacing `{var}` placeholders, other braces are kept as they are
+pub fn render_template(lang: &str, name: &str, vars: &[(&str, &str)]) -> String {
+    render(&get_template(lang, name), vars)
+}
+
+fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    // Single pass, so placeholders in substituted values are never expanded
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let var = rest.find('}').and_then(|end| {
+            vars.iter()
+                .find(|(key, _)| *key == &rest[1..end])
+                .map(|(_, value)| (end, value))
+        });
+
+        if let Some((end, value)) = var {
+            rendered.push_str(value);
+            rest = &rest[end + 1..];
+        } else {
+            rendered.push('{');
+            rest = &rest[1..];
+        }
+    }
+    rendered.push_str(rest);
+
+    rendered
+}
+
+fn make_text_refers(refers: &[String], lang: &str) -> String {
+    if refers.is_empty() {
+        String::new()
+    } else {
+        render_template(lang, "text_refers", &[("refers", &join_fenced(refers))])
+    }
+}
+
+fn join_fenced(texts: &[String]) -> String {
+    texts
+        .iter()
+        .map(|s| s.replace("```", ""))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(
+                r#"{"q": "{question}"} {unknown} {refers}"#,
+                &[("question", "{refers}"), ("refers", "R")]
+            ),
+            r#"{"q": "{refers}"} {unknown} R"#
+        );
+    }
+
+    #[test]
+    fn test_builtin_templates() {
+        for lang in PROMPT_LANGS {
+            for name in list_template_names() {
+                assert!(
+                    !get_builtin_template(lang, name).unwrap().is_empty(),
+                    "{lang}/{name}"
+                );
+            }
+        }
+    }
+}
@@ -1,88 +1,94 @@
-pub fn make_extract_questions_prompt(text: &str, refers: &[String]) -> String {
-    let mut prompt = format!(
-        r#"
-根据下面的内容，生成所有可能引发这个回答的问题。注意在问题中明确表达所有对象，不要使用指代词，要使问题在没有上下文的时候也能被准确理解。结果以标准的 JSON 数组格式返回，其中每个数组项是一个问题：
-```
-{}
-```
+use serde::Deserialize;
+use serde_json::json;
 
-返回的 JSON 格式示例如下：
-```
-["<question_1>", "<question_2>"]
-```
-"#,
-        text.replace("```", "")
-    );
+use crate::llm::{
+    ResponseFormat,
+    prompt::{make_text_refers, render_template},
+};
 
-    if !refers.is_empty() {
-        prompt.push_str(&format!(
-            r#"
-这段内容还有以下的背景信息作为参考：
-```
-{}
-```
-"#,
-            refers
-                .iter()
-                .map(|s| s.replace("```", ""))
-                .collect::<Vec<_>>()
-                .join("\n\n")
-        ));
-    }
-
-    prompt.push_str(
-        r#"
-在处理时，注意以下几点：
-- 每个问题的描述尽可能保持简洁明了。
-- 不要包含任何额外的解释或文本，仅返回 JSON 数据。
-- 确保返回的结果是合法的 JSON 格式。
-"#,
-    );
+#[derive(Deserialize)]
+pub struct ExtractImplicitKnowledgesResponse {
+    pub knowledges: Vec<ImplicitKnowledge>,
+}
 
-    prompt
+#[derive(Deserialize)]
+pub struct ExtractQuestionsResponse {
+    pub questions: Vec<String>,
 }
 
-pub fn make_extract_implicit_knowledges_prompt(text: &str, refers: &[String]) -> String {
-    let mut prompt = format!(
-        r#"
-从下面的内容中提取所有隐含的知识点，并以每个知识点作为回答，生成各种可能引发这个回答的问题。注意在问题中明确表达所有对象，不要使用指代词，要使问题在没有上下文的时候也能被准确理解。结果以标准的 JSON 对象格式返回，JSON 对象的键为知识点的详细内容，JSON 对象的值为引发知识点的问题数组，每个数组项是一个问题：
-```
-{}
-```
+#[derive(Deserialize)]
+pub struct ImplicitKnowledge {
+    pub knowledge: String,
+    pub questions: Vec<String>,
+}
 
-返回的 JSON 格式示例如下：
-```
-{{"<implicit>": ["<trigger>", ...], "<implicit>": ["<trigger>", ...]}}
-```
-"#,
-        text.replace("```", "")
-    );
+pub fn make_extract_questions_prompt(text: &str, refers: &[String], lang: &str) -> String {
+    render_template(
+        lang,
+        "extract_questions",
+        &[
+            ("text", &text.replace("```", "")),
+            ("refers", &make_text_refers(refers, lang)),
+        ],
+    )
+}
 
-    if !refers.is_empty() {
-        prompt.push_str(&format!(
-            r#"
-这段内容还有以下的背景信息作为参考：
-```
-{}
-```
-"#,
-            refers
-                .iter()
-                .map(|s| s.replace("```", ""))
-                .collect::<Vec<_>>()
-                .join("\n\n")
-        ));
-    }
+pub fn make_extract_implicit_knowledges_prompt(
+    text: &str,
+    refers: &[String],
+    lang: &str,
+) -> String {
+    render_template(
+        lang,
+        "extract_implicit_knowledges",
+        &[
+            ("text", &text.replace("```", "")),
+            ("refers", &make_text_refers(refers, lang)),
+        ],
+    )
+}
 
-    prompt.push_str(
-        r#"
-在处理时，注意以下几点：
-- 对数值类的描述尽可能进行理解并统计。
-- 每个问题的描述尽可能保持简洁明了。
-- 不要包含任何额外的解释或文本，仅返回 JSON 数据。
-- 确保返回的结果是合法的 JSON 格式。
-"#,
-    );
+pub fn make_extract_implicit_knowledges_response_format() -> ResponseFormat {
+    ResponseFormat::new(
+        "extract_implicit_knowledges",
+        json!({
+            "type": "object",
+            "properties": {
+                "knowledges": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "knowledge": { "type": "string" },
+                            "questions": {
+                                "type": "array",
+                                "items": { "type": "string" },
+                            },
+                        },
+                        "required": ["knowledge", "questions"],
+                        "additionalProperties": false,
+                    },
+                },
+            },
+            "required": ["knowledges"],
+            "additionalProperties": false,
+        }),
+    )
+}
 
-    prompt
+pub fn make_extract_questions_response_format() -> ResponseFormat {
+    ResponseFormat::new(
+        "extract_questions",
+        json!({
+            "type": "object",
+            "properties": {
+                "questions": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                },
+            },
+            "required": ["questions"],
+            "additionalProperties": false,
+        }),
+    )
 }
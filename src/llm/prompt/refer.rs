@@ -0,0 +1,13 @@
+use crate::llm::prompt::render_template;
+
+pub fn make_doc_refer(title: &str, lang: &str) -> String {
+    render_template(lang, "digest_doc_refer", &[("title", title)])
+        .trim()
+        .to_string()
+}
+
+pub fn make_part_refer(title: &str, lang: &str) -> String {
+    render_template(lang, "digest_part_refer", &[("title", title)])
+        .trim()
+        .to_string()
+}
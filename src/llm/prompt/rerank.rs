@@ -0,0 +1,20 @@
+use crate::llm::prompt::render_template;
+
+pub fn make_score_relevance_prompt(question: &str, candidates: &[String], lang: &str) -> String {
+    render_template(
+        lang,
+        "score_relevance",
+        &[
+            ("question", &question.replace("```", "")),
+            (
+                "candidates",
+                &candidates
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| format!("[{}] {}", i + 1, s.replace("```", "")))
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
+            ),
+        ],
+    )
+}
@@ -1,76 +1,53 @@
-pub fn make_summarize_sheet_prompt(text: &str, refers: &[String]) -> String {
-    let mut prompt = format!(
-        r#"
-概括下面的表格数据，表格数据的格式为 CSV：
-```
-{}
-```
-"#,
-        text.replace("```", ""),
-    );
+use crate::llm::prompt::{join_fenced, make_text_refers, render_template};
 
-    if !refers.is_empty() {
-        prompt.push_str(&format!(
-            r#"
-这个表格还有以下的背景信息作为参考：
-```
-{}
-```
-"#,
-            refers
-                .iter()
-                .map(|s| s.replace("```", ""))
-                .collect::<Vec<_>>()
-                .join("\n\n")
-        ));
-    }
+pub fn make_summarize_code_prompt(
+    text: &str,
+    path: &str,
+    language: &str,
+    symbols: &[String],
+    refers: &[String],
+    lang: &str,
+) -> String {
+    let symbols = if symbols.is_empty() {
+        "-".to_string()
+    } else {
+        symbols.join(", ")
+    };
 
-    prompt.push_str(
-        r#"
-在处理时，注意以下几点：
-- 尽可能自动识别表格的字段头。
-- 对于数值型的字段，尽可能生成其重要的统计值用于总结。
-"#,
-    );
-
-    prompt
+    render_template(
+        lang,
+        "summarize_code",
+        &[
+            ("text", &text.replace("```", "")),
+            ("path", path),
+            ("language", language),
+            ("symbols", &symbols),
+            ("refers", &make_text_refers(refers, lang)),
+        ],
+    )
 }
 
-pub fn make_summarize_text_prompt(text: &str, refers: &[String]) -> String {
-    let mut prompt = format!(
-        r#"
-概括下面的内容：
-```
-{}
-```
-"#,
-        text.replace("```", ""),
-    );
-
-    if !refers.is_empty() {
-        prompt.push_str(&format!(
-            r#"
-这段内容还有以下的背景信息作为参考：
-```
-{}
-```
-"#,
-            refers
-                .iter()
-                .map(|s| s.replace("```", ""))
-                .collect::<Vec<_>>()
-                .join("\n\n")
-        ));
-    }
+pub fn make_summarize_sheet_prompt(text: &str, refers: &[String], lang: &str) -> String {
+    let refers = if refers.is_empty() {
+        String::new()
+    } else {
+        render_template(lang, "sheet_refers", &[("refers", &join_fenced(refers))])
+    };
 
-    prompt.push_str(
-        r#"
-在处理时，注意以下几点：
-- 确保概括后内容比原始内容大幅精简。
-- 如果原始内容中提及相对时间，注意尽量换算为绝对时间。
-- 如果原始内容很少无需概括，则输出` `。
-"#,
-    );
+    render_template(
+        lang,
+        "summarize_sheet",
+        &[("text", &text.replace("```", "")), ("refers", &refers)],
+    )
+}
 
-    prompt
+pub fn make_summarize_text_prompt(text: &str, refers: &[String], lang: &str) -> String {
+    render_template(
+        lang,
+        "summarize_text",
+        &[
+            ("text", &text.replace("```", "")),
+            ("refers", &make_text_refers(refers, lang)),
+        ],
+    )
 }
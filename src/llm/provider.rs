@@ -4,12 +4,19 @@ use crate::{
     db::mem::history_chat::HistoryChatEntity,
     error::AiterResult,
     llm::{
-        ChatCompletionOptions, ChatCompletionStream, ChatFunction, ChatFunctionCall, ChatMessage,
-        Role,
+        ChatCompletionOptions, ChatCompletionStream, ChatContentPart, ChatFunction,
+        ChatFunctionCall, ChatMessage, Role,
     },
 };
 
+pub mod anthropic;
+pub mod ollama;
 pub mod open_ai;
+pub mod record;
+pub mod replay;
+
+#[cfg(test)]
+pub(crate) mod mock;
 
 pub trait ChatProvider {
     fn chat_completion(
@@ -24,6 +31,21 @@ pub trait ChatProvider {
         functions: &[ChatFunction],
     ) -> impl std::future::Future<Output = AiterResult<Vec<ChatFunctionCall>>> + Send;
 
+    fn embeddings(
+        &self,
+        texts: &[String],
+    ) -> impl std::future::Future<Output = AiterResult<Vec<Vec<f32>>>> + Send;
+
+    /// Identifiers of models provided by the service, which can be used as the `model` option
+    fn models(&self) -> impl std::future::Future<Output = AiterResult<Vec<String>>> + Send;
+
+    /// Score the relevance of each document to the query, scores are in the same order as documents
+    fn rerank(
+        &self,
+        query: &str,
+        documents: &[String],
+    ) -> impl std::future::Future<Output = AiterResult<Vec<f64>>> + Send;
+
     fn stream_chat_completion(
         &self,
         messages: &[ChatMessage],
@@ -31,6 +53,108 @@ pub trait ChatProvider {
     ) -> impl std::future::Future<Output = AiterResult<ChatCompletionStream>> + Send;
 }
 
+pub enum LlmProvider {
+    Anthropic(anthropic::AnthropicProvider),
+    Ollama(ollama::OllamaProvider),
+    OpenAi(open_ai::OpenAiProvider),
+    Record(record::RecordProvider),
+    Replay(replay::ReplayProvider),
+}
+
+impl ChatProvider for LlmProvider {
+    async fn chat_completion(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatCompletionOptions,
+    ) -> AiterResult<ChatMessage> {
+        match self {
+            LlmProvider::Anthropic(provider) => provider.chat_completion(messages, options).await,
+            LlmProvider::Ollama(provider) => provider.chat_completion(messages, options).await,
+            LlmProvider::OpenAi(provider) => provider.chat_completion(messages, options).await,
+            LlmProvider::Record(provider) => provider.chat_completion(messages, options).await,
+            LlmProvider::Replay(provider) => provider.chat_completion(messages, options).await,
+        }
+    }
+
+    async fn chat_function_calls(
+        &self,
+        messages: &[ChatMessage],
+        functions: &[ChatFunction],
+    ) -> AiterResult<Vec<ChatFunctionCall>> {
+        match self {
+            LlmProvider::Anthropic(provider) => {
+                provider.chat_function_calls(messages, functions).await
+            }
+            LlmProvider::Ollama(provider) => {
+                provider.chat_function_calls(messages, functions).await
+            }
+            LlmProvider::OpenAi(provider) => {
+                provider.chat_function_calls(messages, functions).await
+            }
+            LlmProvider::Record(provider) => {
+                provider.chat_function_calls(messages, functions).await
+            }
+            LlmProvider::Replay(provider) => {
+                provider.chat_function_calls(messages, functions).await
+            }
+        }
+    }
+
+    async fn embeddings(&self, texts: &[String]) -> AiterResult<Vec<Vec<f32>>> {
+        match self {
+            LlmProvider::Anthropic(provider) => provider.embeddings(texts).await,
+            LlmProvider::Ollama(provider) => provider.embeddings(texts).await,
+            LlmProvider::OpenAi(provider) => provider.embeddings(texts).await,
+            LlmProvider::Record(provider) => provider.embeddings(texts).await,
+            LlmProvider::Replay(provider) => provider.embeddings(texts).await,
+        }
+    }
+
+    async fn models(&self) -> AiterResult<Vec<String>> {
+        match self {
+            LlmProvider::Anthropic(provider) => provider.models().await,
+            LlmProvider::Ollama(provider) => provider.models().await,
+            LlmProvider::OpenAi(provider) => provider.models().await,
+            LlmProvider::Record(provider) => provider.models().await,
+            LlmProvider::Replay(provider) => provider.models().await,
+        }
+    }
+
+    async fn rerank(&self, query: &str, documents: &[String]) -> AiterResult<Vec<f64>> {
+        match self {
+            LlmProvider::Anthropic(provider) => provider.rerank(query, documents).await,
+            LlmProvider::Ollama(provider) => provider.rerank(query, documents).await,
+            LlmProvider::OpenAi(provider) => provider.rerank(query, documents).await,
+            LlmProvider::Record(provider) => provider.rerank(query, documents).await,
+            LlmProvider::Replay(provider) => provider.rerank(query, documents).await,
+        }
+    }
+
+    async fn stream_chat_completion(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatCompletionOptions,
+    ) -> AiterResult<ChatCompletionStream> {
+        match self {
+            LlmProvider::Anthropic(provider) => {
+                provider.stream_chat_completion(messages, options).await
+            }
+            LlmProvider::Ollama(provider) => {
+                provider.stream_chat_completion(messages, options).await
+            }
+            LlmProvider::OpenAi(provider) => {
+                provider.stream_chat_completion(messages, options).await
+            }
+            LlmProvider::Record(provider) => {
+                provider.stream_chat_completion(messages, options).await
+            }
+            LlmProvider::Replay(provider) => {
+                provider.stream_chat_completion(messages, options).await
+            }
+        }
+    }
+}
+
 impl From<HistoryChatEntity> for ChatMessage {
     fn from(historical_chat: HistoryChatEntity) -> Self {
         let (content, reasoning) = if let Ok(json) =
@@ -47,9 +171,12 @@ impl From<HistoryChatEntity> for ChatMessage {
         };
 
         Self {
-            role: Role::from_str(&historical_chat.role).unwrap_or(Role::Bot),
             content,
             reasoning,
+            ..Self::new(
+                Role::from_str(&historical_chat.role).unwrap_or(Role::Bot),
+                "",
+            )
         }
     }
 }
@@ -0,0 +1,599 @@
+use futures::StreamExt;
+use serde_json::{Map, Value, json};
+use tokio::sync::mpsc;
+
+use crate::{
+    CHANNEL_BUFFER_DEFAULT, LLM_MAX_RETRY_DEFAULT, LLM_TIMEOUT_SECS_DEFAULT,
+    error::*,
+    llm::{ChatCompletionEvent, ChatCompletionStream, ChatUsage, provider::*},
+    utils::net::{join_url, make_http_client, send_with_retry},
+};
+
+pub static ANTHROPIC_BASE_URL_DEFAULT: &str = "https://api.anthropic.com";
+static ANTHROPIC_VERSION: &str = "2023-06-01";
+static ANTHROPIC_MAX_TOKENS_DEFAULT: u64 = 8192;
+static ANTHROPIC_THINKING_BUDGET_DEFAULT: u64 = 4096;
+static ANTHROPIC_THINKING_BUDGET_MIN: u64 = 1024;
+
+pub struct AnthropicProvider {
+    base_url: String,
+    api_key: String,
+    model: String,
+    max_retry: usize,
+    timeout: u64,
+    max_tokens: u64,
+    thinking_budget: u64,
+}
+
+impl AnthropicProvider {
+    pub fn new(base_url: &str, api_key: &str, model: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            max_retry: LLM_MAX_RETRY_DEFAULT,
+            timeout: LLM_TIMEOUT_SECS_DEFAULT,
+            max_tokens: ANTHROPIC_MAX_TOKENS_DEFAULT,
+            thinking_budget: ANTHROPIC_THINKING_BUDGET_DEFAULT,
+        }
+    }
+
+    pub fn with_max_retry(mut self, max_retry: usize) -> Self {
+        self.max_retry = max_retry;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: u64) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u64) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    pub fn with_thinking_budget(mut self, thinking_budget: u64) -> Self {
+        self.thinking_budget = thinking_budget;
+        self
+    }
+
+    /// The budget must be less than max tokens, it is clamped unless below the minimum accepted
+    fn get_thinking_budget(&self, max_tokens: u64) -> AiterResult<u64> {
+        let budget = self.thinking_budget.min(max_tokens.saturating_sub(1));
+        if budget < ANTHROPIC_THINKING_BUDGET_MIN {
+            return Err(AiterError::Invalid(format!(
+                "Max tokens {max_tokens} is too small for thinking, expected more than {ANTHROPIC_THINKING_BUDGET_MIN}"
+            )));
+        }
+
+        Ok(budget)
+    }
+
+    fn make_request_body(&self, messages: &[ChatMessage]) -> Map<String, Value> {
+        // Anthropic takes the system prompt as a top-level field instead of a message
+        let system = messages
+            .iter()
+            .filter(|m| m.role == Role::System)
+            .map(|m| m.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let mut request_body = Map::new();
+        request_body.insert("model".to_string(), self.model.clone().into());
+        request_body.insert("max_tokens".to_string(), self.max_tokens.into());
+        if !system.is_empty() {
+            request_body.insert("system".to_string(), system.into());
+        }
+        request_body.insert(
+            "messages".to_string(),
+            messages
+                .iter()
+                .filter(|m| m.role != Role::System)
+                .map(chat_message_to_json_value)
+                .collect::<Vec<_>>()
+                .into(),
+        );
+
+        request_body
+    }
+}
+
+impl ChatProvider for AnthropicProvider {
+    async fn chat_completion(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatCompletionOptions,
+    ) -> AiterResult<ChatMessage> {
+        let mut content = String::new();
+        let mut reasoning_content = String::new();
+
+        let mut stream = self.stream_chat_completion(messages, options).await?;
+        while let Some(event) = stream.next().await {
+            match event {
+                ChatCompletionEvent::CallToolStart(_task) => {}
+                ChatCompletionEvent::CallToolEnd(_task_id, _result, _time) => {}
+                ChatCompletionEvent::CallToolFail(_task_id, _error, _time) => {}
+                ChatCompletionEvent::Usage(_usage) => {}
+                ChatCompletionEvent::Content(delta) => {
+                    content.push_str(&delta);
+                }
+                ChatCompletionEvent::ReasoningContent(delta) => {
+                    reasoning_content.push_str(&delta);
+                }
+                ChatCompletionEvent::Error(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(ChatMessage {
+            content,
+            reasoning: if reasoning_content.is_empty() {
+                None
+            } else {
+                Some(reasoning_content)
+            },
+            ..ChatMessage::new(Role::Bot, "")
+        })
+    }
+
+    async fn chat_function_calls(
+        &self,
+        messages: &[ChatMessage],
+        functions: &[ChatFunction],
+    ) -> AiterResult<Vec<ChatFunctionCall>> {
+        let request_url = join_url(&self.base_url, "/v1/messages")?;
+
+        let mut request_body = self.make_request_body(messages);
+        request_body.insert(
+            "tools".to_string(),
+            functions
+                .iter()
+                .map(chat_function_to_json_value)
+                .collect::<Vec<_>>()
+                .into(),
+        );
+
+        let client = make_http_client(self.timeout)?;
+
+        let response = send_with_retry(
+            client
+                .post(request_url)
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .header("Content-Type", "application/json")
+                .json(&request_body),
+            self.max_retry,
+        )
+        .await?;
+
+        if response.status().is_success() {
+            let json: Value = response.json().await?;
+
+            let mut chat_tool_calls: Vec<ChatFunctionCall> = vec![];
+
+            if let Some(blocks) = json["content"].as_array() {
+                for block in blocks {
+                    if block["type"].as_str() == Some("tool_use") {
+                        if let (Some(id), Some(name)) =
+                            (block["id"].as_str(), block["name"].as_str())
+                        {
+                            if block["input"].is_object() {
+                                chat_tool_calls.push(ChatFunctionCall {
+                                    id: id.to_string(),
+                                    name: name.to_string(),
+                                    arguments: block["input"].clone(),
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+
+            Ok(chat_tool_calls)
+        } else {
+            Err(AiterError::HttpStatusError(format!(
+                "{} {}",
+                response.status(),
+                response.text().await.ok().unwrap_or_default()
+            )))
+        }
+    }
+
+    async fn embeddings(&self, _texts: &[String]) -> AiterResult<Vec<Vec<f32>>> {
+        Err(AiterError::Unsupported(
+            "Anthropic protocol does not provide embeddings".to_string(),
+        ))
+    }
+
+    async fn models(&self) -> AiterResult<Vec<String>> {
+        let request_url = join_url(&self.base_url, "/v1/models")?;
+
+        let client = make_http_client(self.timeout)?;
+
+        let response = send_with_retry(
+            client
+                .get(request_url)
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION),
+            self.max_retry,
+        )
+        .await?;
+
+        if response.status().is_success() {
+            let json: Value = response.json().await?;
+
+            Ok(json["data"]
+                .as_array()
+                .map(|data| {
+                    data.iter()
+                        .filter_map(|item| item["id"].as_str().map(|id| id.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default())
+        } else {
+            Err(AiterError::HttpStatusError(format!(
+                "{} {}",
+                response.status(),
+                response.text().await.ok().unwrap_or_default()
+            )))
+        }
+    }
+
+    async fn rerank(&self, _query: &str, _documents: &[String]) -> AiterResult<Vec<f64>> {
+        Err(AiterError::Unsupported(
+            "Anthropic protocol does not provide rerank".to_string(),
+        ))
+    }
+
+    async fn stream_chat_completion(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatCompletionOptions,
+    ) -> AiterResult<ChatCompletionStream> {
+        let request_url = join_url(&self.base_url, "/v1/messages")?;
+
+        let mut request_body = self.make_request_body(messages);
+        if options.enable_think {
+            let budget_tokens =
+                self.get_thinking_budget(options.max_tokens.unwrap_or(self.max_tokens))?;
+
+            // Extended thinking does not accept a custom temperature
+            request_body.insert(
+                "thinking".to_string(),
+                json!({
+                    "type": "enabled",
+                    "budget_tokens": budget_tokens,
+                }),
+            );
+        } else {
+            request_body.insert("temperature".to_string(), options.temperature.into());
+            if let Some(top_p) = options.top_p {
+                request_body.insert("top_p".to_string(), top_p.into());
+            }
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            request_body.insert("max_tokens".to_string(), max_tokens.into());
+        }
+        if !options.stop.is_empty() {
+            request_body.insert("stop_sequences".to_string(), options.stop.clone().into());
+        }
+        // Seed and penalties are not provided by Anthropic, they are ignored
+        if let Some(extra_body) = &options.extra_body {
+            request_body.extend(extra_body.clone());
+        }
+        request_body.insert("stream".to_string(), true.into());
+
+        let client = make_http_client(self.timeout)?;
+
+        let response = send_with_retry(
+            client
+                .post(request_url)
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .header("Content-Type", "application/json")
+                .json(&request_body),
+            self.max_retry,
+        )
+        .await?;
+
+        if response.status().is_success() {
+            let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER_DEFAULT);
+            let completion_stream = ChatCompletionStream::new(receiver);
+            let cancel_token = completion_stream.cancel_token();
+
+            tokio::spawn(async move {
+                let mut buffer: Vec<u8> = vec![];
+                let mut usage = ChatUsage::default();
+
+                let mut stream = response.bytes_stream();
+                'stream: while let Some(Some(chunk)) =
+                    cancel_token.run_until_cancelled(stream.next()).await
+                {
+                    match chunk {
+                        Ok(chunk) => {
+                            buffer.extend_from_slice(&chunk);
+
+                            // Events may be split across chunks, only handle complete lines
+                            while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                                let line = String::from_utf8_lossy(&buffer[..pos])
+                                    .trim_end()
+                                    .to_string();
+                                buffer.drain(..=pos);
+
+                                if let Some(data) = line.strip_prefix("data:") {
+                                    match serde_json::from_str::<Value>(data.trim()) {
+                                        Ok(json) => match json["type"].as_str() {
+                                            Some("content_block_delta") => {
+                                                let delta = &json["delta"];
+                                                let event = match delta["type"].as_str() {
+                                                    Some("text_delta") => {
+                                                        delta["text"].as_str().map(|s| {
+                                                            ChatCompletionEvent::Content(
+                                                                s.to_string(),
+                                                            )
+                                                        })
+                                                    }
+                                                    Some("thinking_delta") => {
+                                                        delta["thinking"].as_str().map(|s| {
+                                                            ChatCompletionEvent::ReasoningContent(
+                                                                s.to_string(),
+                                                            )
+                                                        })
+                                                    }
+                                                    _ => None,
+                                                };
+
+                                                if let Some(event) = event {
+                                                    let _ = sender.send(event).await;
+                                                }
+                                            }
+                                            Some("error") => {
+                                                let _ = sender
+                                                    .send(ChatCompletionEvent::Error(
+                                                        AiterError::HttpStatusError(
+                                                            json["error"]["message"]
+                                                                .as_str()
+                                                                .unwrap_or_default()
+                                                                .to_string(),
+                                                        ),
+                                                    ))
+                                                    .await;
+                                                break 'stream;
+                                            }
+                                            Some("message_start") => {
+                                                usage.prompt_tokens =
+                                                    json["message"]["usage"]["input_tokens"]
+                                                        .as_u64()
+                                                        .unwrap_or_default();
+                                            }
+                                            Some("message_delta") => {
+                                                usage.completion_tokens =
+                                                    json["usage"]["output_tokens"]
+                                                        .as_u64()
+                                                        .unwrap_or_default();
+                                                let _ = sender
+                                                    .send(ChatCompletionEvent::Usage(usage))
+                                                    .await;
+                                            }
+                                            Some("message_stop") => {
+                                                break 'stream;
+                                            }
+                                            _ => {}
+                                        },
+                                        Err(err) => {
+                                            let _ = sender
+                                                .send(ChatCompletionEvent::Error(err.into()))
+                                                .await;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        Err(err) => {
+                            let _ = sender.send(ChatCompletionEvent::Error(err.into())).await;
+                        }
+                    }
+                }
+            });
+
+            Ok(completion_stream)
+        } else {
+            Err(AiterError::HttpStatusError(format!(
+                "{} {}",
+                response.status(),
+                response.text().await.ok().unwrap_or_default()
+            )))
+        }
+    }
+}
+
+fn chat_function_to_json_value(chat_function: &ChatFunction) -> Value {
+    json!({
+        "name": chat_function.name,
+        "description": chat_function.description,
+        "input_schema": chat_function.parameters,
+    })
+}
+
+fn chat_message_to_json_value(chat_message: &ChatMessage) -> Value {
+    // Anthropic only has user and assistant roles, tool outputs are fed back as user turns
+    let role = match chat_message.role {
+        Role::Bot => "assistant",
+        _ => "user",
+    };
+
+    if let Some(tool_call_id) = &chat_message.tool_call_id {
+        return json!({
+            "role": "user",
+            "content": [{
+                "type": "tool_result",
+                "tool_use_id": tool_call_id,
+                "content": chat_message.content,
+            }],
+        });
+    }
+
+    if !chat_message.tool_calls.is_empty() {
+        let mut blocks: Vec<Value> = vec![];
+        if !chat_message.content.is_empty() {
+            blocks.push(json!({
+                "type": "text",
+                "text": chat_message.content,
+            }));
+        }
+        for call in &chat_message.tool_calls {
+            blocks.push(json!({
+                "type": "tool_use",
+                "id": call.id,
+                "name": call.name,
+                "input": call.arguments,
+            }));
+        }
+
+        return json!({
+            "role": "assistant",
+            "content": blocks,
+        });
+    }
+
+    if !chat_message.parts.is_empty() {
+        let mut blocks: Vec<Value> = vec![];
+        if !chat_message.content.is_empty() {
+            blocks.push(json!({
+                "type": "text",
+                "text": chat_message.content,
+            }));
+        }
+        for part in &chat_message.parts {
+            match part {
+                ChatContentPart::Text(text) => blocks.push(json!({
+                    "type": "text",
+                    "text": text,
+                })),
+                ChatContentPart::Image { mime_type, .. } => blocks.push(json!({
+                    "type": "image",
+                    "source": {
+                        "type": "base64",
+                        "media_type": mime_type,
+                        "data": part.to_base64().unwrap_or_default(),
+                    },
+                })),
+            }
+        }
+
+        return json!({
+            "role": role,
+            "content": blocks,
+        });
+    }
+
+    json!({
+        "role": role,
+        "content": chat_message.content
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::provider::mock::mock_server;
+
+    #[tokio::test]
+    async fn test_stream_chat_completion() {
+        let body = [
+            r#"event: message_start"#,
+            r#"data: {"type":"message_start","message":{"id":"msg_1","role":"assistant","content":[]}}"#,
+            "",
+            r#"event: content_block_delta"#,
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Hmm"}}"#,
+            "",
+            r#"event: content_block_delta"#,
+            r#"data: {"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":"Hello"}}"#,
+            "",
+            r#"event: content_block_delta"#,
+            r#"data: {"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":" world"}}"#,
+            "",
+            r#"event: message_stop"#,
+            r#"data: {"type":"message_stop"}"#,
+            "",
+        ]
+        .join("\n");
+        let base_url = mock_server("text/event-stream", &body);
+
+        let messages = vec![
+            ChatMessage::new(Role::System, "Be brief"),
+            ChatMessage::new(Role::User, "Hi"),
+        ];
+
+        let message = AnthropicProvider::new(&base_url, "key", "claude")
+            .chat_completion(&messages, &ChatCompletionOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(message.content, "Hello world");
+        assert_eq!(message.reasoning, Some("Hmm".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_chat_function_calls() {
+        let body = r#"{"id":"msg_1","type":"message","role":"assistant","content":[{"type":"text","text":"Let me check"},{"type":"tool_use","id":"toolu_1","name":"get_weather","input":{"city":"Beijing","days":3}}],"stop_reason":"tool_use"}"#;
+        let base_url = mock_server("application/json", body);
+
+        let messages = vec![ChatMessage::new(Role::User, "Weather?")];
+
+        let calls = AnthropicProvider::new(&base_url, "key", "claude")
+            .chat_function_calls(&messages, &[])
+            .await
+            .unwrap();
+
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "get_weather");
+        assert_eq!(calls[0].arguments["city"], "Beijing");
+        assert_eq!(calls[0].arguments["days"], 3);
+    }
+
+    #[test]
+    fn test_get_thinking_budget() {
+        let provider = AnthropicProvider::new("", "", "claude");
+        assert_eq!(provider.get_thinking_budget(8192).unwrap(), 4096);
+        assert_eq!(provider.get_thinking_budget(4096).unwrap(), 4095);
+        assert_eq!(provider.get_thinking_budget(1025).unwrap(), 1024);
+        assert!(provider.get_thinking_budget(1024).is_err());
+
+        let provider = provider.with_thinking_budget(16000);
+        assert_eq!(provider.get_thinking_budget(8192).unwrap(), 8191);
+    }
+
+    #[test]
+    fn test_tool_turns_to_json_value() {
+        let call = ChatMessage {
+            tool_calls: vec![ChatFunctionCall {
+                id: "toolu_1".to_string(),
+                name: "get_weather".to_string(),
+                arguments: json!({"city": "Beijing"}),
+            }],
+            ..ChatMessage::new(Role::Bot, "")
+        };
+        assert_eq!(
+            chat_message_to_json_value(&call),
+            json!({
+                "role": "assistant",
+                "content": [{"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {"city": "Beijing"}}],
+            })
+        );
+
+        let result = ChatMessage {
+            tool_call_id: Some("toolu_1".to_string()),
+            ..ChatMessage::new(Role::Tool, "Sunny")
+        };
+        assert_eq!(
+            chat_message_to_json_value(&result),
+            json!({
+                "role": "user",
+                "content": [{"type": "tool_result", "tool_use_id": "toolu_1", "content": "Sunny"}],
+            })
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    sync::mpsc,
+};
+
+/// Serve a single canned HTTP response on a random local port, returns the base URL
+pub fn mock_server(content_type: &str, body: &str) -> String {
+    mock_server_with_status("200 OK", content_type, body)
+}
+
+pub fn mock_server_with_status(status: &str, content_type: &str, body: &str) -> String {
+    mock_server_with_responses(vec![mock_response(status, &[], content_type, body)])
+}
+
+/// Format a raw HTTP response for `mock_server_with_responses`
+pub fn mock_response(
+    status: &str,
+    headers: &[(&str, &str)],
+    content_type: &str,
+    body: &str,
+) -> String {
+    let headers_str = headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\r\n"))
+        .collect::<String>();
+
+    format!(
+        "HTTP/1.1 {}\r\n{}Content-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        headers_str,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// Serve the responses in order, one for each connection
+pub fn mock_server_with_responses(responses: Vec<String>) -> String {
+    mock_server_with_recorder(responses).0
+}
+
+/// Same as `mock_server_with_responses`, bodies of the requests are received in order
+pub fn mock_server_with_recorder(responses: Vec<String>) -> (String, mpsc::Receiver<String>) {
+    let (recorder, records) = mpsc::channel();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for response in responses {
+            let Ok((mut socket, _)) = listener.accept() else {
+                break;
+            };
+
+            // Drain the whole request before responding, otherwise closing the socket may reset it
+            let mut request: Vec<u8> = vec![];
+            let mut buf = [0u8; 4096];
+            while let Ok(n) = socket.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+
+                let request_str = String::from_utf8_lossy(&request);
+                if let Some(pos) = request_str.find("\r\n\r\n") {
+                    let content_length = request_str[..pos]
+                        .lines()
+                        .find_map(|line| {
+                            line.to_lowercase()
+                                .strip_prefix("content-length:")
+                                .and_then(|v| v.trim().parse::<usize>().ok())
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= pos + 4 + content_length {
+                        let _ =
+                            recorder.send(String::from_utf8_lossy(&request[pos + 4..]).to_string());
+                        break;
+                    }
+                }
+            }
+
+            let _ = socket.write_all(response.as_bytes());
+        }
+    });
+
+    (format!("http://{addr}"), records)
+}
@@ -0,0 +1,458 @@
+use futures::StreamExt;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+
+use crate::{
+    CHANNEL_BUFFER_DEFAULT, LLM_MAX_RETRY_DEFAULT, LLM_TIMEOUT_SECS_DEFAULT,
+    error::*,
+    llm::{ChatCompletionEvent, ChatCompletionStream, ChatUsage, provider::*},
+    utils::net::{join_url, make_http_client, send_with_retry},
+};
+
+pub static OLLAMA_BASE_URL_DEFAULT: &str = "http://localhost:11434";
+
+pub struct OllamaProvider {
+    base_url: String,
+    api_key: String,
+    model: String,
+    max_retry: usize,
+    timeout: u64,
+}
+
+impl OllamaProvider {
+    pub fn new(base_url: &str, api_key: &str, model: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            max_retry: LLM_MAX_RETRY_DEFAULT,
+            timeout: LLM_TIMEOUT_SECS_DEFAULT,
+        }
+    }
+
+    pub fn with_max_retry(mut self, max_retry: usize) -> Self {
+        self.max_retry = max_retry;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: u64) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn post(&self, path: &str, request_body: &Value) -> AiterResult<reqwest::Response> {
+        let request_url = join_url(&self.base_url, path)?;
+
+        let client = make_http_client(self.timeout)?;
+
+        let mut request_builder = client
+            .post(request_url)
+            .header("Content-Type", "application/json")
+            .json(request_body);
+        if !self.api_key.is_empty() {
+            request_builder =
+                request_builder.header("Authorization", format!("Bearer {}", self.api_key));
+        }
+
+        let response = send_with_retry(request_builder, self.max_retry).await?;
+
+        if response.status().is_success() {
+            Ok(response)
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(AiterError::NotExists(format!(
+                "Model '{}' is not present in Ollama, pull it first with `ollama pull {}`",
+                self.model, self.model
+            )))
+        } else {
+            Err(AiterError::HttpStatusError(format!(
+                "{} {}",
+                response.status(),
+                response.text().await.ok().unwrap_or_default()
+            )))
+        }
+    }
+}
+
+impl ChatProvider for OllamaProvider {
+    async fn chat_completion(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatCompletionOptions,
+    ) -> AiterResult<ChatMessage> {
+        let mut content = String::new();
+        let mut reasoning_content = String::new();
+
+        let mut stream = self.stream_chat_completion(messages, options).await?;
+        while let Some(event) = stream.next().await {
+            match event {
+                ChatCompletionEvent::CallToolStart(_task) => {}
+                ChatCompletionEvent::CallToolEnd(_task_id, _result, _time) => {}
+                ChatCompletionEvent::CallToolFail(_task_id, _error, _time) => {}
+                ChatCompletionEvent::Usage(_usage) => {}
+                ChatCompletionEvent::Content(delta) => {
+                    content.push_str(&delta);
+                }
+                ChatCompletionEvent::ReasoningContent(delta) => {
+                    reasoning_content.push_str(&delta);
+                }
+                ChatCompletionEvent::Error(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(ChatMessage {
+            content,
+            reasoning: if reasoning_content.is_empty() {
+                None
+            } else {
+                Some(reasoning_content)
+            },
+            ..ChatMessage::new(Role::Bot, "")
+        })
+    }
+
+    async fn chat_function_calls(
+        &self,
+        messages: &[ChatMessage],
+        functions: &[ChatFunction],
+    ) -> AiterResult<Vec<ChatFunctionCall>> {
+        let request_body = json!({
+            "model": self.model,
+            "messages": messages.iter().map(chat_message_to_json_value).collect::<Vec<_>>(),
+            "tools": functions.iter().map(chat_function_to_json_value).collect::<Vec<_>>(),
+            "think": false,
+            "stream": false,
+        });
+
+        let response = self.post("/api/chat", &request_body).await?;
+        let json: Value = response.json().await?;
+
+        if let Some(error) = json["error"].as_str() {
+            return Err(AiterError::HttpStatusError(error.to_string()));
+        }
+
+        let mut chat_tool_calls: Vec<ChatFunctionCall> = vec![];
+
+        if let Some(tool_calls) = json["message"]["tool_calls"].as_array() {
+            for (index, tool_call) in tool_calls.iter().enumerate() {
+                if let Some(name) = tool_call["function"]["name"].as_str() {
+                    if tool_call["function"]["arguments"].is_object() {
+                        // Ollama may not identify calls, results are matched by order then
+                        chat_tool_calls.push(ChatFunctionCall {
+                            id: tool_call["id"]
+                                .as_str()
+                                .map_or(format!("call_{index}"), |s| s.to_string()),
+                            name: name.to_string(),
+                            arguments: tool_call["function"]["arguments"].clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(chat_tool_calls)
+    }
+
+    async fn embeddings(&self, texts: &[String]) -> AiterResult<Vec<Vec<f32>>> {
+        let request_body = json!({
+            "model": self.model,
+            "input": texts,
+        });
+
+        let response = self.post("/api/embed", &request_body).await?;
+        let json: Value = response.json().await?;
+
+        if let Some(error) = json["error"].as_str() {
+            return Err(AiterError::HttpStatusError(error.to_string()));
+        }
+
+        Ok(serde_json::from_value(json["embeddings"].clone())?)
+    }
+
+    async fn models(&self) -> AiterResult<Vec<String>> {
+        let request_url = join_url(&self.base_url, "/api/tags")?;
+
+        let client = make_http_client(self.timeout)?;
+
+        let mut request_builder = client.get(request_url);
+        if !self.api_key.is_empty() {
+            request_builder =
+                request_builder.header("Authorization", format!("Bearer {}", self.api_key));
+        }
+
+        let response = send_with_retry(request_builder, self.max_retry).await?;
+
+        if response.status().is_success() {
+            let json: Value = response.json().await?;
+
+            Ok(json["models"]
+                .as_array()
+                .map(|models| {
+                    models
+                        .iter()
+                        .filter_map(|item| item["name"].as_str().map(|name| name.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default())
+        } else {
+            Err(AiterError::HttpStatusError(format!(
+                "{} {}",
+                response.status(),
+                response.text().await.ok().unwrap_or_default()
+            )))
+        }
+    }
+
+    async fn rerank(&self, _query: &str, _documents: &[String]) -> AiterResult<Vec<f64>> {
+        Err(AiterError::Unsupported(
+            "Ollama protocol does not provide rerank".to_string(),
+        ))
+    }
+
+    async fn stream_chat_completion(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatCompletionOptions,
+    ) -> AiterResult<ChatCompletionStream> {
+        let mut request_body = json!({
+            "model": self.model,
+            "messages": messages.iter().map(chat_message_to_json_value).collect::<Vec<_>>(),
+            "options": {
+                "temperature": options.temperature,
+            },
+            "think": options.enable_think,
+            "stream": true,
+        });
+        if let Some(response_format) = &options.response_format {
+            request_body["format"] = response_format.schema.clone();
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            request_body["options"]["num_predict"] = max_tokens.into();
+        }
+        if let Some(top_p) = options.top_p {
+            request_body["options"]["top_p"] = top_p.into();
+        }
+        if !options.stop.is_empty() {
+            request_body["options"]["stop"] = options.stop.clone().into();
+        }
+        if let Some(seed) = options.seed {
+            request_body["options"]["seed"] = seed.into();
+        }
+        if let Some(presence_penalty) = options.presence_penalty {
+            request_body["options"]["presence_penalty"] = presence_penalty.into();
+        }
+        if let Some(frequency_penalty) = options.frequency_penalty {
+            request_body["options"]["frequency_penalty"] = frequency_penalty.into();
+        }
+        if let Some(extra_body) = &options.extra_body {
+            for (key, value) in extra_body {
+                request_body[key] = value.clone();
+            }
+        }
+
+        let response = self.post("/api/chat", &request_body).await?;
+
+        let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER_DEFAULT);
+        let completion_stream = ChatCompletionStream::new(receiver);
+        let cancel_token = completion_stream.cancel_token();
+
+        tokio::spawn(async move {
+            let mut buffer: Vec<u8> = vec![];
+
+            let mut stream = response.bytes_stream();
+            'stream: while let Some(Some(chunk)) =
+                cancel_token.run_until_cancelled(stream.next()).await
+            {
+                match chunk {
+                    Ok(chunk) => {
+                        buffer.extend_from_slice(&chunk);
+
+                        // NDJSON objects may be split across chunks, only handle complete lines
+                        while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                            let line = String::from_utf8_lossy(&buffer[..pos]).trim().to_string();
+                            buffer.drain(..=pos);
+
+                            if line.is_empty() {
+                                continue;
+                            }
+
+                            match serde_json::from_str::<Value>(&line) {
+                                Ok(json) => {
+                                    if let Some(error) = json["error"].as_str() {
+                                        let _ = sender
+                                            .send(ChatCompletionEvent::Error(
+                                                AiterError::HttpStatusError(error.to_string()),
+                                            ))
+                                            .await;
+                                        break 'stream;
+                                    }
+
+                                    if let Some(thinking) = json["message"]["thinking"].as_str() {
+                                        if !thinking.is_empty() {
+                                            let _ = sender
+                                                .send(ChatCompletionEvent::ReasoningContent(
+                                                    thinking.to_string(),
+                                                ))
+                                                .await;
+                                        }
+                                    }
+
+                                    if let Some(content) = json["message"]["content"].as_str() {
+                                        if !content.is_empty() {
+                                            let _ = sender
+                                                .send(ChatCompletionEvent::Content(
+                                                    content.to_string(),
+                                                ))
+                                                .await;
+                                        }
+                                    }
+
+                                    if json["done"].as_bool() == Some(true) {
+                                        let _ = sender
+                                            .send(ChatCompletionEvent::Usage(ChatUsage {
+                                                prompt_tokens: json["prompt_eval_count"]
+                                                    .as_u64()
+                                                    .unwrap_or_default(),
+                                                completion_tokens: json["eval_count"]
+                                                    .as_u64()
+                                                    .unwrap_or_default(),
+                                            }))
+                                            .await;
+
+                                        break 'stream;
+                                    }
+                                }
+                                Err(err) => {
+                                    let _ =
+                                        sender.send(ChatCompletionEvent::Error(err.into())).await;
+                                }
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        let _ = sender.send(ChatCompletionEvent::Error(err.into())).await;
+                    }
+                }
+            }
+        });
+
+        Ok(completion_stream)
+    }
+}
+
+fn chat_function_to_json_value(chat_function: &ChatFunction) -> Value {
+    json!({
+        "type": "function",
+        "function": {
+            "name": chat_function.name,
+            "description": chat_function.description,
+            "parameters": chat_function.parameters,
+        }
+    })
+}
+
+fn chat_message_to_json_value(chat_message: &ChatMessage) -> Value {
+    let role = match chat_message.role {
+        Role::User => "user",
+        Role::Bot => "assistant",
+        Role::System => "system",
+        Role::Func | Role::Tool => "tool",
+    };
+
+    let mut value = json!({
+        "role": role,
+        "content": chat_message.content
+    });
+
+    // Ollama takes images of a message as a separate list, text parts are appended to the content
+    if !chat_message.parts.is_empty() {
+        let mut content = chat_message.content.clone();
+        let mut images: Vec<String> = vec![];
+        for part in &chat_message.parts {
+            match part {
+                ChatContentPart::Text(text) => {
+                    if !content.is_empty() {
+                        content.push_str("\n\n");
+                    }
+                    content.push_str(text);
+                }
+                ChatContentPart::Image { .. } => {
+                    images.extend(part.to_base64());
+                }
+            }
+        }
+
+        value["content"] = content.into();
+        if !images.is_empty() {
+            value["images"] = images.into();
+        }
+    }
+
+    if !chat_message.tool_calls.is_empty() {
+        value["tool_calls"] = chat_message
+            .tool_calls
+            .iter()
+            .map(|call| {
+                json!({
+                    "function": {
+                        "name": call.name,
+                        "arguments": call.arguments,
+                    }
+                })
+            })
+            .collect();
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::provider::mock::{mock_server, mock_server_with_status};
+
+    #[tokio::test]
+    async fn test_stream_chat_completion() {
+        let body = [
+            r#"{"model":"qwen3","message":{"role":"assistant","content":"","thinking":"Hmm"},"done":false}"#,
+            r#"{"model":"qwen3","message":{"role":"assistant","content":"Hello"},"done":false}"#,
+            r#"{"model":"qwen3","message":{"role":"assistant","content":" world"},"done":false}"#,
+            r#"{"model":"qwen3","message":{"role":"assistant","content":""},"done":true}"#,
+            "",
+        ]
+        .join("\n");
+        let base_url = mock_server("application/x-ndjson", &body);
+
+        let messages = vec![ChatMessage::new(Role::User, "Hi")];
+
+        let message = OllamaProvider::new(&base_url, "", "qwen3")
+            .chat_completion(
+                &messages,
+                &ChatCompletionOptions::default().with_enable_think(true),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(message.content, "Hello world");
+        assert_eq!(message.reasoning, Some("Hmm".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_model_not_found() {
+        let base_url = mock_server_with_status(
+            "404 Not Found",
+            "application/json",
+            r#"{"error":"model \"foo\" not found, try pulling it first"}"#,
+        );
+
+        let messages = vec![ChatMessage::new(Role::User, "Hi")];
+
+        let result = OllamaProvider::new(&base_url, "", "foo")
+            .stream_chat_completion(&messages, &ChatCompletionOptions::default())
+            .await;
+
+        assert!(matches!(result, Err(AiterError::NotExists(_))));
+    }
+}
@@ -1,21 +1,24 @@
 use std::collections::HashMap;
 
 use futures::StreamExt;
+use reqwest::{Response, StatusCode};
 use serde::Serialize;
-use serde_json::{Map, Value, json};
+use serde_json::{Value, json};
 use tokio::sync::mpsc;
 
 use crate::{
-    CHANNEL_BUFFER_DEFAULT,
+    CHANNEL_BUFFER_DEFAULT, LLM_MAX_RETRY_DEFAULT, LLM_TIMEOUT_SECS_DEFAULT,
     error::*,
-    llm::{ChatCompletionEvent, ChatCompletionStream, provider::*},
-    utils::{json::json_value_to_string, net::join_url},
+    llm::{ChatCompletionEvent, ChatCompletionStream, ChatUsage, provider::*},
+    utils::net::{join_url, make_http_client, send_with_retry},
 };
 
 pub struct OpenAiProvider {
     base_url: String,
     api_key: String,
     model: String,
+    max_retry: usize,
+    timeout: u64,
 }
 
 impl OpenAiProvider {
@@ -24,8 +27,20 @@ impl OpenAiProvider {
             base_url: base_url.to_string(),
             api_key: api_key.to_string(),
             model: model.to_string(),
+            max_retry: LLM_MAX_RETRY_DEFAULT,
+            timeout: LLM_TIMEOUT_SECS_DEFAULT,
         }
     }
+
+    pub fn with_max_retry(mut self, max_retry: usize) -> Self {
+        self.max_retry = max_retry;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: u64) -> Self {
+        self.timeout = timeout;
+        self
+    }
 }
 
 impl ChatProvider for OpenAiProvider {
@@ -43,6 +58,7 @@ impl ChatProvider for OpenAiProvider {
                 ChatCompletionEvent::CallToolStart(_task) => {}
                 ChatCompletionEvent::CallToolEnd(_task_id, _result, _time) => {}
                 ChatCompletionEvent::CallToolFail(_task_id, _error, _time) => {}
+                ChatCompletionEvent::Usage(_usage) => {}
                 ChatCompletionEvent::Content(delta) => {
                     content.push_str(&delta);
                 }
@@ -56,13 +72,13 @@ impl ChatProvider for OpenAiProvider {
         }
 
         Ok(ChatMessage {
-            role: Role::Bot,
             content,
             reasoning: if reasoning_content.is_empty() {
                 None
             } else {
                 Some(reasoning_content)
             },
+            ..ChatMessage::new(Role::Bot, "")
         })
     }
 
@@ -77,12 +93,8 @@ impl ChatProvider for OpenAiProvider {
             .iter()
             .map(chat_message_to_json_value)
             .collect::<Vec<_>>();
-        if self.model.starts_with("qwen3") && !messages.is_empty() {
-            if let Some(content) = messages_json_value[messages.len() - 1].get_mut("content") {
-                if let Some(content_str) = content.as_str() {
-                    *content = format!("{content_str} /no_think").into();
-                }
-            }
+        if self.model.starts_with("qwen3") {
+            append_to_last_user_message(&mut messages_json_value, messages, "/no_think");
         }
 
         let request_body = json!({
@@ -93,17 +105,20 @@ impl ChatProvider for OpenAiProvider {
             "stream": true,
         });
 
-        let client = reqwest::Client::builder().build()?;
+        let client = make_http_client(self.timeout)?;
 
-        let response = client
-            .post(request_url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request_body)
-            .send()
-            .await?;
+        let response = send_with_retry(
+            client
+                .post(request_url)
+                .header("Authorization", format!("Bearer {}", self.api_key))
+                .header("Content-Type", "application/json")
+                .json(&request_body),
+            self.max_retry,
+        )
+        .await?;
 
         if response.status().is_success() {
+            let mut tool_calls_id_str: HashMap<u64, String> = HashMap::new();
             let mut tool_calls_name_str: HashMap<u64, String> = HashMap::new();
             let mut tool_calls_arguments_str: HashMap<u64, String> = HashMap::new();
 
@@ -129,6 +144,10 @@ impl ChatProvider for OpenAiProvider {
                                     delta_tool_call["type"].as_str(),
                                 ) {
                                     if r#type == "function" {
+                                        if let Some(id) = delta_tool_call["id"].as_str() {
+                                            tool_calls_id_str.insert(index, id.to_string());
+                                        }
+
                                         if let Some(function) =
                                             delta_tool_call["function"].as_object()
                                         {
@@ -183,18 +202,23 @@ impl ChatProvider for OpenAiProvider {
 
             for (index, name) in tool_calls_name_str {
                 if let Some(arguments) = tool_calls_arguments_str.get(&index) {
-                    if let Ok(function_arguments) =
-                        serde_json::from_str::<HashMap<String, serde_json::Value>>(arguments)
-                    {
-                        let mut arguments = HashMap::new();
-                        for (key, value) in function_arguments {
-                            arguments.insert(key.to_string(), json_value_to_string(&value));
-                        }
+                    // Functions without parameters may be called with empty arguments
+                    let arguments = if arguments.trim().is_empty() {
+                        Ok(json!({}))
+                    } else {
+                        serde_json::from_str::<Value>(arguments)
+                    };
 
-                        chat_tool_calls.push(ChatFunctionCall {
-                            name: name.to_string(),
-                            arguments,
-                        });
+                    if let Ok(arguments) = arguments {
+                        if arguments.is_object() {
+                            chat_tool_calls.push(ChatFunctionCall {
+                                id: tool_calls_id_str
+                                    .get(&index)
+                                    .map_or(format!("call_{index}"), |s| s.to_string()),
+                                name: name.to_string(),
+                                arguments,
+                            });
+                        }
                     }
                 }
             }
@@ -209,6 +233,135 @@ impl ChatProvider for OpenAiProvider {
         }
     }
 
+    async fn embeddings(&self, texts: &[String]) -> AiterResult<Vec<Vec<f32>>> {
+        let request_url = join_url(&self.base_url, "/embeddings")?;
+
+        let request_body = json!({
+            "model": self.model,
+            "input": texts,
+        });
+
+        let client = make_http_client(self.timeout)?;
+
+        let response = send_with_retry(
+            client
+                .post(request_url)
+                .header("Authorization", format!("Bearer {}", self.api_key))
+                .header("Content-Type", "application/json")
+                .json(&request_body),
+            self.max_retry,
+        )
+        .await?;
+
+        if response.status().is_success() {
+            let json: Value = response.json().await?;
+
+            let mut embeddings: Vec<(u64, Vec<f32>)> = vec![];
+            if let Some(data) = json["data"].as_array() {
+                for (i, item) in data.iter().enumerate() {
+                    let index = item["index"].as_u64().unwrap_or(i as u64);
+                    let embedding: Vec<f32> = serde_json::from_value(item["embedding"].clone())?;
+                    embeddings.push((index, embedding));
+                }
+            }
+            embeddings.sort_by_key(|(index, _)| *index);
+
+            Ok(embeddings
+                .into_iter()
+                .map(|(_, embedding)| embedding)
+                .collect())
+        } else {
+            Err(AiterError::HttpStatusError(format!(
+                "{} {}",
+                response.status(),
+                response.text().await.ok().unwrap_or_default()
+            )))
+        }
+    }
+
+    async fn models(&self) -> AiterResult<Vec<String>> {
+        let request_url = join_url(&self.base_url, "/models")?;
+
+        let client = make_http_client(self.timeout)?;
+
+        let response = send_with_retry(
+            client
+                .get(request_url)
+                .header("Authorization", format!("Bearer {}", self.api_key)),
+            self.max_retry,
+        )
+        .await?;
+
+        if response.status().is_success() {
+            let json: Value = response.json().await?;
+
+            Ok(json["data"]
+                .as_array()
+                .map(|data| {
+                    data.iter()
+                        .filter_map(|item| item["id"].as_str().map(|id| id.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default())
+        } else {
+            Err(AiterError::HttpStatusError(format!(
+                "{} {}",
+                response.status(),
+                response.text().await.ok().unwrap_or_default()
+            )))
+        }
+    }
+
+    async fn rerank(&self, query: &str, documents: &[String]) -> AiterResult<Vec<f64>> {
+        // Not part of OpenAI API, but widely provided by compatible services (Jina, SiliconFlow, vLLM, etc.)
+        let request_url = join_url(&self.base_url, "/rerank")?;
+
+        let request_body = json!({
+            "model": self.model,
+            "query": query,
+            "documents": documents,
+            "top_n": documents.len(),
+            "return_documents": false,
+        });
+
+        let client = make_http_client(self.timeout)?;
+
+        let response = send_with_retry(
+            client
+                .post(request_url)
+                .header("Authorization", format!("Bearer {}", self.api_key))
+                .header("Content-Type", "application/json")
+                .json(&request_body),
+            self.max_retry,
+        )
+        .await?;
+
+        if response.status().is_success() {
+            let json: Value = response.json().await?;
+
+            let mut scores: Vec<f64> = vec![0.0; documents.len()];
+            if let Some(results) = json["results"].as_array() {
+                for result in results {
+                    if let (Some(index), Some(score)) =
+                        (result["index"].as_u64(), result["relevance_score"].as_f64())
+                    {
+                        if let Some(s) = scores.get_mut(index as usize) {
+                            *s = score;
+                        }
+                    }
+                }
+            }
+
+            Ok(scores)
+        } else {
+            Err(AiterError::HttpStatusError(format!(
+                "{} {}",
+                response.status(),
+                response.text().await.ok().unwrap_or_default()
+            )))
+        }
+    }
+
     async fn stream_chat_completion(
         &self,
         messages: &[ChatMessage],
@@ -220,42 +373,108 @@ impl ChatProvider for OpenAiProvider {
             .iter()
             .map(chat_message_to_json_value)
             .collect::<Vec<_>>();
-        if self.model.starts_with("qwen3") && !messages.is_empty() {
-            if let Some(content) = messages_json_value[messages.len() - 1].get_mut("content") {
-                if let Some(content_str) = content.as_str() {
-                    let instruction = if options.enable_think {
-                        "/think"
-                    } else {
-                        "/no_think"
-                    };
-                    *content = format!("{content_str} {instruction}").into();
-                }
-            }
+        if self.model.starts_with("qwen3") {
+            let instruction = if options.enable_think {
+                "/think"
+            } else {
+                "/no_think"
+            };
+            append_to_last_user_message(&mut messages_json_value, messages, instruction);
         }
 
-        let request_body = json!({
+        let mut request_body = json!({
             "model": self.model,
             "messages": messages_json_value,
             "temperature": options.temperature,
             "stream": true,
+            "stream_options": {
+                "include_usage": true,
+            },
         });
+        if let Some(response_format) = &options.response_format {
+            request_body["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": {
+                    "name": response_format.name,
+                    "schema": response_format.schema,
+                },
+            });
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            request_body["max_tokens"] = max_tokens.into();
+        }
+        if let Some(top_p) = options.top_p {
+            request_body["top_p"] = top_p.into();
+        }
+        if !options.stop.is_empty() {
+            request_body["stop"] = options.stop.clone().into();
+        }
+        if let Some(seed) = options.seed {
+            request_body["seed"] = seed.into();
+        }
+        if let Some(presence_penalty) = options.presence_penalty {
+            request_body["presence_penalty"] = presence_penalty.into();
+        }
+        if let Some(frequency_penalty) = options.frequency_penalty {
+            request_body["frequency_penalty"] = frequency_penalty.into();
+        }
+        if let Some(extra_body) = &options.extra_body {
+            for (key, value) in extra_body {
+                request_body[key] = value.clone();
+            }
+        }
 
-        let client = reqwest::Client::builder().build()?;
+        let client = make_http_client(self.timeout)?;
 
-        let response = client
-            .post(request_url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request_body)
-            .send()
-            .await?;
+        let mut response = send_with_retry(
+            client
+                .post(request_url.clone())
+                .header("Authorization", format!("Bearer {}", self.api_key))
+                .header("Content-Type", "application/json")
+                .json(&request_body),
+            self.max_retry,
+        )
+        .await?;
+
+        // Some compatible services reject structured output, retry without it and leave the
+        // output to be fixed by the caller
+        if matches!(
+            response.status(),
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY
+        ) && request_body
+            .as_object_mut()
+            .and_then(|body| body.remove("response_format"))
+            .is_some()
+        {
+            let first_error = make_status_error(response).await;
+            match send_with_retry(
+                client
+                    .post(request_url)
+                    .header("Authorization", format!("Bearer {}", self.api_key))
+                    .header("Content-Type", "application/json")
+                    .json(&request_body),
+                self.max_retry,
+            )
+            .await
+            {
+                // The first error tells why the request was rejected, so it is kept if the retry fails too
+                Ok(retry_response) if retry_response.status().is_success() => {
+                    response = retry_response
+                }
+                _ => return Err(first_error),
+            }
+        }
 
         if response.status().is_success() {
             let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER_DEFAULT);
+            let completion_stream = ChatCompletionStream::new(receiver);
+            let cancel_token = completion_stream.cancel_token();
 
             tokio::spawn(async move {
+                // Dropping the response on cancellation closes the connection, so the generation stops
                 let mut stream = response.bytes_stream();
-                while let Some(chunk) = stream.next().await {
+                while let Some(Some(chunk)) = cancel_token.run_until_cancelled(stream.next()).await
+                {
                     match chunk {
                         Ok(chunk) => {
                             let chunk_str = String::from_utf8_lossy(&chunk);
@@ -268,6 +487,21 @@ impl ChatProvider for OpenAiProvider {
 
                                     match serde_json::from_str::<Value>(data) {
                                         Ok(json) => {
+                                            // The last chunk carries usage only, without choices
+                                            if let Some(usage) = json["usage"].as_object() {
+                                                let _ = sender
+                                                    .send(ChatCompletionEvent::Usage(ChatUsage {
+                                                        prompt_tokens: usage["prompt_tokens"]
+                                                            .as_u64()
+                                                            .unwrap_or_default(),
+                                                        completion_tokens:
+                                                            usage["completion_tokens"]
+                                                                .as_u64()
+                                                                .unwrap_or_default(),
+                                                    }))
+                                                    .await;
+                                            }
+
                                             if let Some(delta_content) =
                                                 json["choices"][0]["delta"]["content"].as_str()
                                             {
@@ -303,17 +537,21 @@ impl ChatProvider for OpenAiProvider {
                 }
             });
 
-            Ok(ChatCompletionStream { receiver })
+            Ok(completion_stream)
         } else {
-            Err(AiterError::HttpStatusError(format!(
-                "{} {}",
-                response.status(),
-                response.text().await.ok().unwrap_or_default()
-            )))
+            Err(make_status_error(response).await)
         }
     }
 }
 
+async fn make_status_error(response: Response) -> AiterError {
+    AiterError::HttpStatusError(format!(
+        "{} {}",
+        response.status(),
+        response.text().await.ok().unwrap_or_default()
+    ))
+}
+
 #[derive(strum::Display)]
 enum OpenAiRole {
     #[strum(serialize = "user")]
@@ -353,34 +591,445 @@ impl Serialize for OpenAiRole {
     }
 }
 
-fn chat_function_to_json_value(chat_function: &ChatFunction) -> Value {
-    let parameters_map: Map<String, Value> =
-        Map::from_iter(chat_function.parameters.iter().map(|(name, parameter)| {
-            (
-                name.to_string(),
-                json!({
-                    "type": parameter.r#type.to_string(),
-                    "description": parameter.description.to_string(),
-                }),
-            )
-        }));
+/// Instructions such as /no_think of qwen3 only take effect in user messages, not in tool results
+fn append_to_last_user_message(
+    messages_json_value: &mut [Value],
+    messages: &[ChatMessage],
+    instruction: &str,
+) {
+    if let Some(index) = messages.iter().rposition(|m| m.role == Role::User) {
+        match messages_json_value[index].get_mut("content") {
+            Some(Value::String(content)) => {
+                *content = format!("{content} {instruction}");
+            }
+            // Content with images is an array of parts, the instruction goes to the last text part
+            Some(Value::Array(parts)) => {
+                if let Some(Value::String(text)) = parts
+                    .iter_mut()
+                    .rev()
+                    .find(|part| part["type"] == "text")
+                    .and_then(|part| part.get_mut("text"))
+                {
+                    *text = format!("{text} {instruction}");
+                } else {
+                    parts.push(json!({
+                        "type": "text",
+                        "text": instruction,
+                    }));
+                }
+            }
+            _ => {}
+        }
+    }
+}
 
+fn chat_function_to_json_value(chat_function: &ChatFunction) -> Value {
     json!({
         "type": "function",
         "function": {
             "name": chat_function.name,
             "description": chat_function.description,
-            "parameters": {
-                "type": "object",
-                "properties": Value::Object(parameters_map),
-            }
+            "parameters": chat_function.parameters,
         }
     })
 }
 
 fn chat_message_to_json_value(chat_message: &ChatMessage) -> Value {
-    json!({
+    let mut value = json!({
         "role": Into::<OpenAiRole>::into(chat_message.role).to_string(),
         "content": chat_message.content
-    })
+    });
+
+    // Content becomes an array of parts only if there are parts other than the text
+    if !chat_message.parts.is_empty() {
+        let mut parts: Vec<Value> = vec![];
+        if !chat_message.content.is_empty() {
+            parts.push(json!({
+                "type": "text",
+                "text": chat_message.content,
+            }));
+        }
+        for part in &chat_message.parts {
+            match part {
+                ChatContentPart::Text(text) => parts.push(json!({
+                    "type": "text",
+                    "text": text,
+                })),
+                ChatContentPart::Image { mime_type, .. } => parts.push(json!({
+                    "type": "image_url",
+                    "image_url": {
+                        "url": format!("data:{mime_type};base64,{}", part.to_base64().unwrap_or_default()),
+                    },
+                })),
+            }
+        }
+        value["content"] = parts.into();
+    }
+
+    if !chat_message.tool_calls.is_empty() {
+        value["tool_calls"] = chat_message
+            .tool_calls
+            .iter()
+            .map(|call| {
+                json!({
+                    "id": call.id,
+                    "type": "function",
+                    "function": {
+                        "name": call.name,
+                        "arguments": call.arguments.to_string(),
+                    }
+                })
+            })
+            .collect();
+    }
+
+    if let Some(tool_call_id) = &chat_message.tool_call_id {
+        value["tool_call_id"] = tool_call_id.clone().into();
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{
+        ResponseFormat,
+        provider::mock::{
+            mock_response, mock_server, mock_server_with_recorder, mock_server_with_responses,
+            mock_server_with_status,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_chat_function_calls_rounds() {
+        let call_body = [
+            r#"data: {"choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"lookup","arguments":"{\"q\":\"apple\"}"}}]}}]}"#,
+            "",
+            "data: [DONE]",
+            "",
+            "",
+        ]
+        .join("\n");
+        let done_body = [
+            r#"data: {"choices":[{"delta":{"content":""}}]}"#,
+            "",
+            "data: [DONE]",
+            "",
+            "",
+        ]
+        .join("\n");
+        let (base_url, records) = mock_server_with_recorder(vec![
+            mock_response("200 OK", &[], "text/event-stream", &call_body),
+            mock_response("200 OK", &[], "text/event-stream", &done_body),
+        ]);
+
+        let provider = OpenAiProvider::new(&base_url, "", "qwen3-8b");
+        let functions = vec![ChatFunction {
+            name: "lookup".to_string(),
+            description: "Look up".to_string(),
+            parameters: json!({"type": "object"}),
+        }];
+        let mut messages = vec![ChatMessage::new(Role::User, "Hi")];
+
+        let calls = provider
+            .chat_function_calls(&messages, &functions)
+            .await
+            .unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].arguments, json!({"q": "apple"}));
+
+        messages.push(ChatMessage {
+            tool_calls: calls.clone(),
+            ..ChatMessage::new(Role::Bot, "")
+        });
+        messages.push(ChatMessage {
+            tool_call_id: Some(calls[0].id.clone()),
+            ..ChatMessage::new(Role::Tool, "Red")
+        });
+
+        let calls = provider
+            .chat_function_calls(&messages, &functions)
+            .await
+            .unwrap();
+        assert!(calls.is_empty());
+
+        let request: Value = serde_json::from_str(&records.recv().unwrap()).unwrap();
+        assert_eq!(request["messages"][0]["content"], "Hi /no_think");
+
+        let request: Value = serde_json::from_str(&records.recv().unwrap()).unwrap();
+        assert_eq!(request["messages"][0]["content"], "Hi /no_think");
+        assert_eq!(request["messages"][1]["tool_calls"][0]["id"], "call_1");
+        assert_eq!(request["messages"][2]["content"], "Red");
+    }
+
+    #[tokio::test]
+    async fn test_models() {
+        let base_url = mock_server(
+            "application/json",
+            r#"{"object":"list","data":[{"id":"gpt-4o","object":"model"},{"id":"text-embedding-3-small","object":"model"}]}"#,
+        );
+
+        let models = OpenAiProvider::new(&base_url, "", "gpt-4o")
+            .models()
+            .await
+            .unwrap();
+
+        assert_eq!(models, vec!["gpt-4o", "text-embedding-3-small"]);
+    }
+
+    #[tokio::test]
+    async fn test_rerank() {
+        let base_url = mock_server(
+            "application/json",
+            r#"{"results":[{"index":1,"relevance_score":0.9},{"index":0,"relevance_score":0.1}]}"#,
+        );
+
+        let scores = OpenAiProvider::new(&base_url, "", "bge-reranker")
+            .rerank("apple", &["banana".to_string(), "apple pie".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(scores, vec![0.1, 0.9]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_usage() {
+        let body = [
+            r#"data: {"choices":[{"delta":{"content":"Hello"}}]}"#,
+            "",
+            r#"data: {"choices":[],"usage":{"prompt_tokens":12,"completion_tokens":3}}"#,
+            "",
+            "data: [DONE]",
+            "",
+            "",
+        ]
+        .join("\n");
+        let base_url = mock_server("text/event-stream", &body);
+
+        let messages = vec![ChatMessage::new(Role::User, "Hi")];
+
+        let mut stream = OpenAiProvider::new(&base_url, "", "gpt")
+            .stream_chat_completion(&messages, &ChatCompletionOptions::default())
+            .await
+            .unwrap();
+
+        let mut usage: Option<ChatUsage> = None;
+        while let Some(event) = stream.next().await {
+            if let ChatCompletionEvent::Usage(reported) = event {
+                usage = Some(reported);
+            }
+        }
+
+        let usage = usage.unwrap();
+        assert_eq!(usage.prompt_tokens, 12);
+        assert_eq!(usage.completion_tokens, 3);
+    }
+
+    #[tokio::test]
+    async fn test_response_format_rejected() {
+        let body = [
+            r#"data: {"choices":[{"delta":{"content":"{\"queries\":[]}"}}]}"#,
+            "",
+            "data: [DONE]",
+            "",
+            "",
+        ]
+        .join("\n");
+        let base_url = mock_server_with_responses(vec![
+            mock_response(
+                "400 Bad Request",
+                &[],
+                "application/json",
+                r#"{"error":{"message":"response_format is not supported"}}"#,
+            ),
+            mock_response("200 OK", &[], "text/event-stream", &body),
+        ]);
+
+        let messages = vec![ChatMessage::new(Role::User, "Hi")];
+        let options = ChatCompletionOptions::default().with_response_format(ResponseFormat {
+            name: "queries".to_string(),
+            schema: json!({"type": "object"}),
+        });
+
+        let message = OpenAiProvider::new(&base_url, "", "gpt")
+            .chat_completion(&messages, &options)
+            .await
+            .unwrap();
+        assert_eq!(message.content, r#"{"queries":[]}"#);
+
+        // The first error is kept when the request without response format fails too
+        let base_url = mock_server_with_responses(vec![
+            mock_response(
+                "422 Unprocessable Entity",
+                &[],
+                "application/json",
+                r#"{"error":{"message":"response_format is not supported"}}"#,
+            ),
+            mock_response(
+                "400 Bad Request",
+                &[],
+                "application/json",
+                r#"{"error":{"message":"Invalid request"}}"#,
+            ),
+        ]);
+        let result = OpenAiProvider::new(&base_url, "", "gpt")
+            .chat_completion(&messages, &options)
+            .await;
+        assert!(
+            matches!(result, Err(AiterError::HttpStatusError(err)) if err.contains("response_format"))
+        );
+
+        // Other client errors are not retried without response format
+        let (base_url, records) = mock_server_with_recorder(vec![
+            mock_response(
+                "404 Not Found",
+                &[],
+                "application/json",
+                r#"{"error":{"message":"Model not found"}}"#,
+            ),
+            mock_response("200 OK", &[], "text/event-stream", &body),
+        ]);
+        let result = OpenAiProvider::new(&base_url, "", "gpt")
+            .chat_completion(&messages, &options)
+            .await;
+        assert!(matches!(result, Err(AiterError::HttpStatusError(err)) if err.starts_with("404")));
+        assert_eq!(records.try_iter().count(), 1);
+
+        // Requests without response format are not retried
+        let base_url = mock_server_with_status(
+            "400 Bad Request",
+            "application/json",
+            r#"{"error":{"message":"Invalid request"}}"#,
+        );
+        let result = OpenAiProvider::new(&base_url, "", "gpt")
+            .chat_completion(&messages, &ChatCompletionOptions::default())
+            .await;
+        assert!(matches!(result, Err(AiterError::HttpStatusError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_retry_on_rate_limit() {
+        let body = [
+            r#"data: {"choices":[{"delta":{"content":"Hello"}}]}"#,
+            "",
+            "data: [DONE]",
+            "",
+            "",
+        ]
+        .join("\n");
+        let base_url = mock_server_with_responses(vec![
+            mock_response(
+                "429 Too Many Requests",
+                &[("Retry-After", "0")],
+                "application/json",
+                r#"{"error":{"message":"Rate limit reached"}}"#,
+            ),
+            mock_response("200 OK", &[], "text/event-stream", &body),
+        ]);
+
+        let messages = vec![ChatMessage::new(Role::User, "Hi")];
+
+        let message = OpenAiProvider::new(&base_url, "", "gpt")
+            .chat_completion(&messages, &ChatCompletionOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(message.content, "Hello");
+
+        let base_url = mock_server_with_status(
+            "429 Too Many Requests",
+            "application/json",
+            r#"{"error":{"message":"Rate limit reached"}}"#,
+        );
+        let result = OpenAiProvider::new(&base_url, "", "gpt")
+            .with_max_retry(0)
+            .chat_completion(&messages, &ChatCompletionOptions::default())
+            .await;
+        assert!(matches!(result, Err(AiterError::HttpStatusError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_chat_function_calls() {
+        let body = [
+            r#"data: {"choices":[{"delta":{"tool_calls":[{"index":0,"type":"function","function":{"name":"search","arguments":"{\"tags\":[\"a\","}}]}}]}"#,
+            "",
+            r#"data: {"choices":[{"delta":{"tool_calls":[{"index":0,"type":"function","function":{"arguments":"\"b\"],\"range\":{\"from\":1}}"}}]}}]}"#,
+            "",
+            "data: [DONE]",
+            "",
+            "",
+        ]
+        .join("\n");
+        let base_url = mock_server("text/event-stream", &body);
+
+        let messages = vec![ChatMessage::new(Role::User, "Search")];
+
+        let calls = OpenAiProvider::new(&base_url, "", "gpt")
+            .chat_function_calls(&messages, &[])
+            .await
+            .unwrap();
+
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "search");
+        assert_eq!(
+            calls[0].arguments,
+            json!({"tags": ["a", "b"], "range": {"from": 1}})
+        );
+    }
+
+    #[test]
+    fn test_image_parts_to_json_value() {
+        let message = ChatMessage {
+            parts: vec![ChatContentPart::Image {
+                mime_type: "image/png".to_string(),
+                data: b"png".to_vec(),
+            }],
+            ..ChatMessage::new(Role::User, "What is it?")
+        };
+
+        assert_eq!(
+            chat_message_to_json_value(&message),
+            json!({
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "What is it?"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,cG5n"}},
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_append_to_last_user_message() {
+        let image = ChatContentPart::Image {
+            mime_type: "image/png".to_string(),
+            data: b"png".to_vec(),
+        };
+        let messages = vec![
+            ChatMessage::new(Role::User, "Hi"),
+            ChatMessage::new(Role::Bot, "Hello"),
+            ChatMessage {
+                parts: vec![image.clone()],
+                ..ChatMessage::new(Role::User, "What is it?")
+            },
+        ];
+        let mut messages_json_value: Vec<Value> =
+            messages.iter().map(chat_message_to_json_value).collect();
+        append_to_last_user_message(&mut messages_json_value, &messages, "/no_think");
+        assert_eq!(messages_json_value[0]["content"], "Hi");
+        assert_eq!(
+            messages_json_value[2]["content"][0]["text"],
+            "What is it? /no_think"
+        );
+
+        // Without any text part, the instruction is added as one
+        let messages = vec![ChatMessage {
+            parts: vec![image],
+            ..ChatMessage::new(Role::User, "")
+        }];
+        let mut messages_json_value: Vec<Value> =
+            messages.iter().map(chat_message_to_json_value).collect();
+        append_to_last_user_message(&mut messages_json_value, &messages, "/think");
+        assert_eq!(messages_json_value[0]["content"][1]["text"], "/think");
+    }
 }
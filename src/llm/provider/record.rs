@@ -0,0 +1,266 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+
+use crate::{
+    CHANNEL_BUFFER_DEFAULT,
+    error::*,
+    llm::{
+        ChatCompletionEvent, ChatCompletionStream,
+        provider::{replay::*, *},
+    },
+};
+
+/// Proxy to a real provider and append every response to a JSONL cassette for the replay protocol
+pub struct RecordProvider {
+    cassette_path: PathBuf,
+    target: RecordTarget,
+}
+
+/// Providers which can be recorded, a record or replay provider can not be the target
+pub enum RecordTarget {
+    Anthropic(anthropic::AnthropicProvider),
+    Ollama(ollama::OllamaProvider),
+    OpenAi(open_ai::OpenAiProvider),
+}
+
+impl RecordProvider {
+    pub fn new(cassette_path: &Path, target: RecordTarget) -> Self {
+        Self {
+            cassette_path: cassette_path.to_path_buf(),
+            target,
+        }
+    }
+}
+
+impl ChatProvider for RecordProvider {
+    async fn chat_completion(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatCompletionOptions,
+    ) -> AiterResult<ChatMessage> {
+        let message = self.target.chat_completion(messages, options).await?;
+
+        append_cassette(
+            &self.cassette_path,
+            &chat_request(messages, options),
+            &json!({
+                "content": message.content,
+                "reasoning": message.reasoning,
+            }),
+        )
+        .await?;
+
+        Ok(message)
+    }
+
+    async fn chat_function_calls(
+        &self,
+        messages: &[ChatMessage],
+        functions: &[ChatFunction],
+    ) -> AiterResult<Vec<ChatFunctionCall>> {
+        let calls = self.target.chat_function_calls(messages, functions).await?;
+
+        append_cassette(
+            &self.cassette_path,
+            &function_calls_request(messages, functions),
+            &Value::Array(
+                calls
+                    .iter()
+                    .map(|call| {
+                        json!({
+                            "id": call.id,
+                            "name": call.name,
+                            "arguments": call.arguments,
+                        })
+                    })
+                    .collect(),
+            ),
+        )
+        .await?;
+
+        Ok(calls)
+    }
+
+    async fn embeddings(&self, texts: &[String]) -> AiterResult<Vec<Vec<f32>>> {
+        let embeddings = self.target.embeddings(texts).await?;
+
+        append_cassette(
+            &self.cassette_path,
+            &embeddings_request(texts),
+            &json!(embeddings),
+        )
+        .await?;
+
+        Ok(embeddings)
+    }
+
+    async fn models(&self) -> AiterResult<Vec<String>> {
+        // Models are listed for configuration, not for replaying
+        self.target.models().await
+    }
+
+    async fn rerank(&self, query: &str, documents: &[String]) -> AiterResult<Vec<f64>> {
+        let scores = self.target.rerank(query, documents).await?;
+
+        append_cassette(
+            &self.cassette_path,
+            &rerank_request(query, documents),
+            &json!(scores),
+        )
+        .await?;
+
+        Ok(scores)
+    }
+
+    async fn stream_chat_completion(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatCompletionOptions,
+    ) -> AiterResult<ChatCompletionStream> {
+        let mut target_stream = self
+            .target
+            .stream_chat_completion(messages, options)
+            .await?;
+
+        let cassette_path = self.cassette_path.clone();
+        let request = chat_request(messages, options);
+
+        let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER_DEFAULT);
+        let stream = ChatCompletionStream::new(receiver);
+        let cancel_token = stream.cancel_token();
+
+        tokio::spawn(async move {
+            let mut content = String::new();
+            let mut reasoning_content = String::new();
+            let mut has_error = false;
+
+            loop {
+                // Cancelled before the end, the response is incomplete and should not be recorded
+                let Some(event) = cancel_token.run_until_cancelled(target_stream.next()).await
+                else {
+                    return;
+                };
+                let Some(event) = event else {
+                    break;
+                };
+
+                match &event {
+                    ChatCompletionEvent::Content(delta) => content.push_str(delta),
+                    ChatCompletionEvent::ReasoningContent(delta) => {
+                        reasoning_content.push_str(delta)
+                    }
+                    ChatCompletionEvent::Error(_) => has_error = true,
+                    _ => {}
+                }
+
+                if sender.send(event).await.is_err() {
+                    // Receiver dropped, the response is incomplete and should not be recorded
+                    return;
+                }
+            }
+
+            if !has_error {
+                let response = json!({
+                    "content": content,
+                    "reasoning": if reasoning_content.is_empty() { None } else { Some(reasoning_content) },
+                });
+                if let Err(err) = append_cassette(&cassette_path, &request, &response).await {
+                    let _ = sender.send(ChatCompletionEvent::Error(err)).await;
+                }
+            }
+        });
+
+        Ok(stream)
+    }
+}
+
+impl ChatProvider for RecordTarget {
+    async fn chat_completion(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatCompletionOptions,
+    ) -> AiterResult<ChatMessage> {
+        match self {
+            RecordTarget::Anthropic(provider) => provider.chat_completion(messages, options).await,
+            RecordTarget::Ollama(provider) => provider.chat_completion(messages, options).await,
+            RecordTarget::OpenAi(provider) => provider.chat_completion(messages, options).await,
+        }
+    }
+
+    async fn chat_function_calls(
+        &self,
+        messages: &[ChatMessage],
+        functions: &[ChatFunction],
+    ) -> AiterResult<Vec<ChatFunctionCall>> {
+        match self {
+            RecordTarget::Anthropic(provider) => {
+                provider.chat_function_calls(messages, functions).await
+            }
+            RecordTarget::Ollama(provider) => {
+                provider.chat_function_calls(messages, functions).await
+            }
+            RecordTarget::OpenAi(provider) => {
+                provider.chat_function_calls(messages, functions).await
+            }
+        }
+    }
+
+    async fn embeddings(&self, texts: &[String]) -> AiterResult<Vec<Vec<f32>>> {
+        match self {
+            RecordTarget::Anthropic(provider) => provider.embeddings(texts).await,
+            RecordTarget::Ollama(provider) => provider.embeddings(texts).await,
+            RecordTarget::OpenAi(provider) => provider.embeddings(texts).await,
+        }
+    }
+
+    async fn models(&self) -> AiterResult<Vec<String>> {
+        match self {
+            RecordTarget::Anthropic(provider) => provider.models().await,
+            RecordTarget::Ollama(provider) => provider.models().await,
+            RecordTarget::OpenAi(provider) => provider.models().await,
+        }
+    }
+
+    async fn rerank(&self, query: &str, documents: &[String]) -> AiterResult<Vec<f64>> {
+        match self {
+            RecordTarget::Anthropic(provider) => provider.rerank(query, documents).await,
+            RecordTarget::Ollama(provider) => provider.rerank(query, documents).await,
+            RecordTarget::OpenAi(provider) => provider.rerank(query, documents).await,
+        }
+    }
+
+    async fn stream_chat_completion(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatCompletionOptions,
+    ) -> AiterResult<ChatCompletionStream> {
+        match self {
+            RecordTarget::Anthropic(provider) => {
+                provider.stream_chat_completion(messages, options).await
+            }
+            RecordTarget::Ollama(provider) => {
+                provider.stream_chat_completion(messages, options).await
+            }
+            RecordTarget::OpenAi(provider) => {
+                provider.stream_chat_completion(messages, options).await
+            }
+        }
+    }
+}
+
+impl TryFrom<LlmProvider> for RecordTarget {
+    type Error = AiterError;
+
+    fn try_from(provider: LlmProvider) -> Result<Self, Self::Error> {
+        match provider {
+            LlmProvider::Anthropic(provider) => Ok(RecordTarget::Anthropic(provider)),
+            LlmProvider::Ollama(provider) => Ok(RecordTarget::Ollama(provider)),
+            LlmProvider::OpenAi(provider) => Ok(RecordTarget::OpenAi(provider)),
+            LlmProvider::Record(_) | LlmProvider::Replay(_) => Err(AiterError::Invalid(
+                "Record target can not be a record or replay provider".to_string(),
+            )),
+        }
+    }
+}
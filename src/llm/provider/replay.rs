@@ -0,0 +1,331 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use serde_json::{Value, json};
+use tokio::{
+    fs::OpenOptions,
+    io::AsyncWriteExt,
+    sync::{Mutex, mpsc},
+};
+
+use crate::{
+    CHANNEL_BUFFER_DEFAULT,
+    error::*,
+    llm::{ChatCompletionEvent, ChatCompletionStream, provider::*},
+    utils::crypto::sha256,
+};
+
+static CASSETTE_WRITE_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+
+/// Answer from a JSONL cassette recorded by the record protocol, no network is involved
+pub struct ReplayProvider {
+    cassette_path: PathBuf,
+}
+
+impl ReplayProvider {
+    pub fn new(cassette_path: &Path) -> Self {
+        Self {
+            cassette_path: cassette_path.to_path_buf(),
+        }
+    }
+
+    async fn replay(&self, request: &Value) -> AiterResult<Value> {
+        let key = cassette_key(request);
+
+        if let Ok(cassette) = tokio::fs::read_to_string(&self.cassette_path).await {
+            // The last recorded entry wins, so re-recording a request overrides the previous one
+            for line in cassette.lines().rev() {
+                if let Ok(entry) = serde_json::from_str::<Value>(line) {
+                    if entry["key"].as_str() == Some(&key) {
+                        return Ok(entry["response"].clone());
+                    }
+                }
+            }
+        }
+
+        Err(AiterError::NotExists(format!(
+            "No recorded {} response '{}' in cassette '{}'",
+            request["kind"].as_str().unwrap_or_default(),
+            key,
+            self.cassette_path.display()
+        )))
+    }
+}
+
+impl ChatProvider for ReplayProvider {
+    async fn chat_completion(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatCompletionOptions,
+    ) -> AiterResult<ChatMessage> {
+        let response = self.replay(&chat_request(messages, options)).await?;
+
+        Ok(ChatMessage {
+            reasoning: response["reasoning"].as_str().map(|s| s.to_string()),
+            ..ChatMessage::new(Role::Bot, response["content"].as_str().unwrap_or_default())
+        })
+    }
+
+    async fn chat_function_calls(
+        &self,
+        messages: &[ChatMessage],
+        functions: &[ChatFunction],
+    ) -> AiterResult<Vec<ChatFunctionCall>> {
+        let response = self
+            .replay(&function_calls_request(messages, functions))
+            .await?;
+
+        let mut chat_function_calls: Vec<ChatFunctionCall> = vec![];
+        if let Some(calls) = response.as_array() {
+            for (index, call) in calls.iter().enumerate() {
+                if let Some(name) = call["name"].as_str() {
+                    chat_function_calls.push(ChatFunctionCall {
+                        id: call["id"]
+                            .as_str()
+                            .map_or(format!("call_{index}"), |s| s.to_string()),
+                        name: name.to_string(),
+                        arguments: call["arguments"].clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(chat_function_calls)
+    }
+
+    async fn embeddings(&self, texts: &[String]) -> AiterResult<Vec<Vec<f32>>> {
+        let response = self.replay(&embeddings_request(texts)).await?;
+
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn models(&self) -> AiterResult<Vec<String>> {
+        Err(AiterError::Unsupported(
+            "Replay protocol does not provide models".to_string(),
+        ))
+    }
+
+    async fn rerank(&self, query: &str, documents: &[String]) -> AiterResult<Vec<f64>> {
+        let response = self.replay(&rerank_request(query, documents)).await?;
+
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn stream_chat_completion(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatCompletionOptions,
+    ) -> AiterResult<ChatCompletionStream> {
+        let message = self.chat_completion(messages, options).await?;
+
+        let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER_DEFAULT);
+
+        tokio::spawn(async move {
+            if let Some(reasoning) = message.reasoning {
+                let _ = sender
+                    .send(ChatCompletionEvent::ReasoningContent(reasoning))
+                    .await;
+            }
+
+            let _ = sender
+                .send(ChatCompletionEvent::Content(message.content))
+                .await;
+        });
+
+        Ok(ChatCompletionStream::new(receiver))
+    }
+}
+
+pub(super) async fn append_cassette(
+    cassette_path: &Path,
+    request: &Value,
+    response: &Value,
+) -> AiterResult<()> {
+    let entry = json!({
+        "key": cassette_key(request),
+        "request": request,
+        "response": response,
+    });
+
+    let _lock = CASSETTE_WRITE_LOCK.lock().await;
+
+    if let Some(parent) = cassette_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(cassette_path)
+        .await?;
+    file.write_all(format!("{entry}\n").as_bytes()).await?;
+
+    Ok(())
+}
+
+pub(super) fn chat_request(messages: &[ChatMessage], options: &ChatCompletionOptions) -> Value {
+    let mut request = json!({
+        "kind": "chat",
+        "messages": messages_to_json_value(messages),
+        "options": {
+            "enable_think": options.enable_think,
+            "temperature": options.temperature,
+        },
+    });
+
+    // Only present when set, so that cassettes recorded without it still match
+    if let Some(response_format) = &options.response_format {
+        request["options"]["response_format"] = response_format.schema.clone();
+    }
+    for (key, value) in options.sampling_key_value() {
+        request["options"][key] = value;
+    }
+
+    request
+}
+
+pub(super) fn embeddings_request(texts: &[String]) -> Value {
+    json!({
+        "kind": "embeddings",
+        "texts": texts,
+    })
+}
+
+pub(super) fn function_calls_request(
+    messages: &[ChatMessage],
+    functions: &[ChatFunction],
+) -> Value {
+    json!({
+        "kind": "function_calls",
+        "messages": messages_to_json_value(messages),
+        "functions": functions.iter().map(|f| {
+            json!({
+                "name": f.name,
+                "description": f.description,
+                "parameters": f.parameters,
+            })
+        }).collect::<Vec<_>>(),
+    })
+}
+
+pub(super) fn rerank_request(query: &str, documents: &[String]) -> Value {
+    json!({
+        "kind": "rerank",
+        "query": query,
+        "documents": documents,
+    })
+}
+
+fn cassette_key(request: &Value) -> String {
+    sha256(request.to_string().as_bytes())
+}
+
+fn messages_to_json_value(messages: &[ChatMessage]) -> Value {
+    Value::Array(
+        messages
+            .iter()
+            .map(|m| {
+                let mut value = json!({
+                    "role": m.role.to_string(),
+                    "content": m.content,
+                });
+
+                // Only present in tool rounds, so that existing cassettes still match
+                if !m.tool_calls.is_empty() {
+                    value["tool_calls"] = m
+                        .tool_calls
+                        .iter()
+                        .map(|call| {
+                            json!({
+                                "id": call.id,
+                                "name": call.name,
+                                "arguments": call.arguments,
+                            })
+                        })
+                        .collect();
+                }
+                if let Some(tool_call_id) = &m.tool_call_id {
+                    value["tool_call_id"] = tool_call_id.clone().into();
+                }
+                if !m.parts.is_empty() {
+                    value["parts"] = m.parts.iter().map(|part| part.to_key_value()).collect();
+                }
+
+                value
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::provider::{
+        mock::mock_server,
+        ollama::OllamaProvider,
+        record::{RecordProvider, RecordTarget},
+    };
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let cassette_path =
+            std::env::temp_dir().join(format!("aiter-test-{}.jsonl", ulid::Ulid::new()));
+
+        let body = [
+            r#"{"message":{"role":"assistant","content":"","thinking":"Hmm"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":"Hello"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":""},"done":true}"#,
+            "",
+        ]
+        .join("\n");
+        let base_url = mock_server("application/x-ndjson", &body);
+
+        let messages = vec![ChatMessage::new(Role::User, "Hi")];
+        let options = ChatCompletionOptions::default();
+
+        let recorded = RecordProvider::new(
+            &cassette_path,
+            RecordTarget::Ollama(OllamaProvider::new(&base_url, "", "qwen3")),
+        )
+        .chat_completion(&messages, &options)
+        .await
+        .unwrap();
+        assert_eq!(recorded.content, "Hello");
+
+        // The mock server has gone, the response can only come from cassette
+        let replayed = ReplayProvider::new(&cassette_path)
+            .chat_completion(&messages, &options)
+            .await
+            .unwrap();
+        assert_eq!(replayed.content, "Hello");
+        assert_eq!(replayed.reasoning, Some("Hmm".to_string()));
+
+        let missed = ReplayProvider::new(&cassette_path)
+            .chat_completion(&messages, &options.with_temperature(0.0))
+            .await;
+        assert!(matches!(missed, Err(AiterError::NotExists(_))));
+
+        let _ = std::fs::remove_file(&cassette_path);
+    }
+
+    #[test]
+    fn test_chat_request_response_format() {
+        let options = ChatCompletionOptions::default();
+        assert!(
+            chat_request(&[], &options)["options"]
+                .get("response_format")
+                .is_none()
+        );
+
+        let options = options.with_response_format(crate::llm::ResponseFormat::new(
+            "answer",
+            json!({"type": "object"}),
+        ));
+        assert_eq!(
+            chat_request(&[], &options)["options"]["response_format"],
+            json!({"type": "object"})
+        );
+    }
+}
@@ -53,6 +53,9 @@ async fn main() {
         Commands::Mem(cmd) => {
             cmd.exec().await;
         }
+        Commands::Prompt(cmd) => {
+            cmd.exec().await;
+        }
         Commands::Read(cmd) => {
             cmd.exec().await;
         }
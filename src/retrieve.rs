@@ -1,8 +1,12 @@
+use std::{collections::HashMap, sync::Arc};
+
 pub mod doc;
+pub mod rerank;
 pub mod skill;
 
 #[derive(strum::Display, Clone)]
 pub enum RetrieveMethod {
     Fts,
     Vec,
+    Embedding(Arc<HashMap<String, Vec<f32>>>), // Embeddings of questions and related queries
 }
@@ -61,7 +61,7 @@ pub async fn retrieve_doc_implicit(
 
     let limit = match method {
         RetrieveMethod::Fts => RETRIEVE_FTS_LIMIT,
-        RetrieveMethod::Vec => RETRIEVE_VEC_LIMIT,
+        RetrieveMethod::Vec | RetrieveMethod::Embedding(_) => RETRIEVE_VEC_LIMIT,
     };
 
     let contents = content_tuples
@@ -129,7 +129,7 @@ pub async fn retrieve_doc_frag(
 
     let limit = match method {
         RetrieveMethod::Fts => RETRIEVE_FTS_LIMIT,
-        RetrieveMethod::Vec => RETRIEVE_VEC_LIMIT,
+        RetrieveMethod::Vec | RetrieveMethod::Embedding(_) => RETRIEVE_VEC_LIMIT,
     };
 
     let contents = content_tuples
@@ -197,7 +197,7 @@ pub async fn retrieve_doc_knl(
 
     let limit = match method {
         RetrieveMethod::Fts => RETRIEVE_FTS_LIMIT,
-        RetrieveMethod::Vec => RETRIEVE_VEC_LIMIT,
+        RetrieveMethod::Vec | RetrieveMethod::Embedding(_) => RETRIEVE_VEC_LIMIT,
     };
 
     let contents = content_tuples
@@ -228,6 +228,8 @@ async fn single_retrieve_doc_implicit(
 ) -> AiterResult<RetrievedContents> {
     let mut result: RetrievedContents = vec![];
 
+    let mut embedding_similarities: HashMap<String, f64> = HashMap::new();
+
     let doc_implicits = match method {
         RetrieveMethod::Fts => {
             let mut hits = db::mem::doc_implicit::query_by_search(
@@ -258,6 +260,25 @@ async fn single_retrieve_doc_implicit(
             )
             .await?
         }
+        RetrieveMethod::Embedding(embeddings) => {
+            let mut hits = vec![];
+            if let Some(embedding) = embeddings.get(question) {
+                for (id, similarity) in db::mem::doc_implicit::query_by_embedding(
+                    mem_path,
+                    embedding,
+                    RETRIEVE_VEC_LIMIT as u64,
+                )
+                .await?
+                {
+                    if let Some(hit) = db::mem::doc_implicit::get(mem_path, &id).await? {
+                        embedding_similarities.insert(id, similarity);
+                        hits.push(hit);
+                    }
+                }
+            }
+
+            hits
+        }
     };
 
     let mut docs: HashMap<String, Option<db::mem::doc::DocEntity>> = HashMap::new();
@@ -277,8 +298,12 @@ async fn single_retrieve_doc_implicit(
         } else {
             doc_implicit.content
         };
-        let content_sig = minhash(&content_with_context, signature_dims, &tokenizer)?;
-        let similarity = compute_probminhash_jaccard(similarity_sig, &content_sig);
+        let similarity = if let Some(similarity) = embedding_similarities.get(&doc_implicit.id) {
+            *similarity
+        } else {
+            let content_sig = minhash(&content_with_context, signature_dims, &tokenizer)?;
+            compute_probminhash_jaccard(similarity_sig, &content_sig)
+        };
 
         result.push((content_with_context, similarity));
     }
@@ -297,6 +322,8 @@ async fn single_retrieve_doc_frag(
 ) -> AiterResult<RetrievedContents> {
     let mut result: RetrievedContents = vec![];
 
+    let mut embedding_similarities: HashMap<String, f64> = HashMap::new();
+
     let doc_frags = match method {
         RetrieveMethod::Fts => {
             let mut hits = db::mem::doc_frag::query_by_search(
@@ -327,6 +354,25 @@ async fn single_retrieve_doc_frag(
             )
             .await?
         }
+        RetrieveMethod::Embedding(embeddings) => {
+            let mut hits = vec![];
+            if let Some(embedding) = embeddings.get(question) {
+                for (id, similarity) in db::mem::doc_frag::query_by_embedding(
+                    mem_path,
+                    embedding,
+                    RETRIEVE_VEC_LIMIT as u64,
+                )
+                .await?
+                {
+                    if let Some(hit) = db::mem::doc_frag::get(mem_path, &id).await? {
+                        embedding_similarities.insert(id, similarity);
+                        hits.push(hit);
+                    }
+                }
+            }
+
+            hits
+        }
     };
 
     let mut docs: HashMap<String, Option<db::mem::doc::DocEntity>> = HashMap::new();
@@ -349,8 +395,12 @@ async fn single_retrieve_doc_frag(
         } else {
             frag_content.clone()
         };
-        let content_sig = minhash(&content_with_context, signature_dims, &tokenizer)?;
-        let similarity = compute_probminhash_jaccard(similarity_sig, &content_sig);
+        let similarity = if let Some(similarity) = embedding_similarities.get(&doc_frag.id) {
+            *similarity
+        } else {
+            let content_sig = minhash(&content_with_context, signature_dims, &tokenizer)?;
+            compute_probminhash_jaccard(similarity_sig, &content_sig)
+        };
 
         let mut surround = vec![];
         surround.push(frag_content);
@@ -426,6 +476,8 @@ async fn single_retrieve_doc_knl(
 ) -> AiterResult<RetrievedContents> {
     let mut result: RetrievedContents = vec![];
 
+    let mut embedding_similarities: HashMap<String, f64> = HashMap::new();
+
     let doc_knls = match method {
         RetrieveMethod::Fts => {
             let mut hits = db::mem::doc_knl::query_by_search(
@@ -452,6 +504,25 @@ async fn single_retrieve_doc_knl(
             db::mem::doc_knl::query_by_signature(mem_path, &question_sig, RETRIEVE_VEC_LIMIT as u64)
                 .await?
         }
+        RetrieveMethod::Embedding(embeddings) => {
+            let mut hits = vec![];
+            if let Some(embedding) = embeddings.get(question) {
+                for (id, similarity) in db::mem::doc_knl::query_by_embedding(
+                    mem_path,
+                    embedding,
+                    RETRIEVE_VEC_LIMIT as u64,
+                )
+                .await?
+                {
+                    if let Some(hit) = db::mem::doc_knl::get(mem_path, &id).await? {
+                        embedding_similarities.insert(id, similarity);
+                        hits.push(hit);
+                    }
+                }
+            }
+
+            hits
+        }
     };
 
     let mut docs: HashMap<String, Option<db::mem::doc::DocEntity>> = HashMap::new();
@@ -501,8 +572,12 @@ async fn single_retrieve_doc_knl(
             } else {
                 format!("{} {}", &doc_knl.trigger, &content)
             };
-            let content_sig = minhash(&content_with_context, signature_dims, &tokenizer)?;
-            let similarity = compute_probminhash_jaccard(similarity_sig, &content_sig);
+            let similarity = if let Some(similarity) = embedding_similarities.get(&doc_knl.id) {
+                *similarity
+            } else {
+                let content_sig = minhash(&content_with_context, signature_dims, &tokenizer)?;
+                compute_probminhash_jaccard(similarity_sig, &content_sig)
+            };
 
             result.push((content_with_context, similarity));
         }
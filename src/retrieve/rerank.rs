@@ -0,0 +1,144 @@
+use std::{cmp::Ordering, path::Path, time::Instant};
+
+use crate::{
+    CURRENT_TOKENIZER, LLM_CHAT_TEMPERATURE_STABLE, RERANK_LLM_BATCH, api,
+    error::AiterResult,
+    llm::{ChatCompletionOptions, UsageTag, prompt::rerank::make_score_relevance_prompt},
+    utils::{markdown::extract_code_block, text::to_tokens},
+};
+
+/// Order candidates by relevance to the question, then keep the top ones which fit in the tokens budget
+pub async fn rerank_candidates(
+    mem_path: &Path,
+    question: &str,
+    candidates: &[String],
+    top_k: usize,
+    max_tokens: usize,
+    llm_for_chat: Option<&str>,
+    lang: &str,
+) -> AiterResult<Vec<String>> {
+    if candidates.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let instant = Instant::now();
+
+    let scores = if api::llm::get_actived_name("rerank").await?.is_some() {
+        match api::llm::rerank(question, candidates, None).await {
+            Ok(scores) => scores,
+            Err(err) => {
+                log::warn!("Rerank error, fallback to LLM scorer: {err}");
+                score_by_llm(mem_path, question, candidates, llm_for_chat, lang).await
+            }
+        }
+    } else {
+        score_by_llm(mem_path, question, candidates, llm_for_chat, lang).await
+    };
+
+    let scored: Vec<(String, f64)> = candidates.iter().cloned().zip(scores).collect();
+    let reranked = pack_by_budget(scored, top_k, max_tokens);
+
+    log::debug!(
+        "Reranked candidates [{:?}]: {} -> {}",
+        instant.elapsed(),
+        candidates.len(),
+        reranked.len()
+    );
+
+    Ok(reranked)
+}
+
+fn pack_by_budget(mut scored: Vec<(String, f64)>, top_k: usize, max_tokens: usize) -> Vec<String> {
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+
+    let mut packed: Vec<String> = vec![];
+    let mut tokens: usize = 0;
+
+    for (candidate, _) in scored {
+        if packed.len() >= top_k {
+            break;
+        }
+
+        let candidate_tokens = to_tokens(&candidate, &CURRENT_TOKENIZER).len();
+        if tokens + candidate_tokens > max_tokens {
+            continue;
+        }
+
+        tokens += candidate_tokens;
+        packed.push(candidate);
+    }
+
+    packed
+}
+
+async fn score_by_llm(
+    mem_path: &Path,
+    question: &str,
+    candidates: &[String],
+    llm_for_chat: Option<&str>,
+    lang: &str,
+) -> Vec<f64> {
+    let mut handles = vec![];
+
+    for batch in candidates.chunks(RERANK_LLM_BATCH) {
+        let prompt = make_score_relevance_prompt(question, batch, lang);
+        let batch_len = batch.len();
+        let llm_for_chat = llm_for_chat.map(|s| s.to_string());
+        let usage_tag = UsageTag::new("score-relevance").with_mem_path(mem_path);
+
+        handles.push(tokio::spawn(async move {
+            let scores = match api::llm::chat_completion(
+                &prompt,
+                &[],
+                &ChatCompletionOptions::default()
+                    .with_temperature(LLM_CHAT_TEMPERATURE_STABLE)
+                    .with_usage_tag(usage_tag),
+                llm_for_chat.as_deref(),
+            )
+            .await
+            {
+                Ok(message) => {
+                    serde_json::from_str::<Vec<f64>>(&extract_code_block(&message.content)).ok()
+                }
+                Err(err) => {
+                    log::warn!("Score relevance by LLM error: {err}");
+                    None
+                }
+            };
+
+            // Unscored candidates are kept with a neutral score rather than dropped
+            match scores {
+                Some(scores) if scores.len() == batch_len => scores,
+                _ => vec![5.0; batch_len],
+            }
+        }));
+    }
+
+    let mut scores: Vec<f64> = vec![];
+    for (handle, batch) in handles.into_iter().zip(candidates.chunks(RERANK_LLM_BATCH)) {
+        scores.extend(handle.await.unwrap_or_else(|_| vec![5.0; batch.len()]));
+    }
+
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_by_budget() {
+        let scored = vec![
+            ("low".to_string(), 0.1),
+            ("high".to_string(), 0.9),
+            ("middle".to_string(), 0.5),
+            ("huge ".repeat(100), 0.8),
+        ];
+
+        assert_eq!(
+            pack_by_budget(scored.clone(), 10, 10),
+            vec!["high", "middle", "low"]
+        );
+        assert_eq!(pack_by_budget(scored, 2, 10), vec!["high", "middle"]);
+    }
+}
@@ -1,4 +1,9 @@
-use std::{cmp::Ordering, collections::HashSet, path::Path, time::Instant};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    path::Path,
+    time::Instant,
+};
 
 use probminhash::jaccard::compute_probminhash_jaccard;
 use tokio::task::JoinHandle;
@@ -63,7 +68,7 @@ pub async fn retrieve_skill(
 
     let limit = match method {
         RetrieveMethod::Fts => RETRIEVE_FTS_LIMIT,
-        RetrieveMethod::Vec => RETRIEVE_VEC_LIMIT,
+        RetrieveMethod::Vec | RetrieveMethod::Embedding(_) => RETRIEVE_VEC_LIMIT,
     };
 
     let skills = skill_tuples
@@ -94,6 +99,8 @@ async fn single_retrieve_skill(
 ) -> AiterResult<RetrievedSkills> {
     let mut result: RetrievedSkills = vec![];
 
+    let mut embedding_similarities: HashMap<String, f64> = HashMap::new();
+
     let skills = match method {
         RetrieveMethod::Fts => {
             let mut hits = db::mem::skill::query_by_search(
@@ -120,11 +127,34 @@ async fn single_retrieve_skill(
             db::mem::skill::query_by_signature(mem_path, &question_sig, RETRIEVE_VEC_LIMIT as u64)
                 .await?
         }
+        RetrieveMethod::Embedding(embeddings) => {
+            let mut hits = vec![];
+            if let Some(embedding) = embeddings.get(question) {
+                for (id, similarity) in db::mem::skill::query_by_embedding(
+                    mem_path,
+                    embedding,
+                    RETRIEVE_VEC_LIMIT as u64,
+                )
+                .await?
+                {
+                    if let Some(hit) = db::mem::skill::get(mem_path, &id).await? {
+                        embedding_similarities.insert(id, similarity);
+                        hits.push(hit);
+                    }
+                }
+            }
+
+            hits
+        }
     };
 
     for skill in skills {
-        let trigger_sig = minhash(&skill.trigger, signature_dims, &tokenizer)?;
-        let similarity = compute_probminhash_jaccard(similarity_sig, &trigger_sig);
+        let similarity = if let Some(similarity) = embedding_similarities.get(&skill.id) {
+            *similarity
+        } else {
+            let trigger_sig = minhash(&skill.trigger, signature_dims, &tokenizer)?;
+            compute_probminhash_jaccard(similarity_sig, &trigger_sig)
+        };
 
         result.push((skill, similarity));
     }
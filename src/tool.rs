@@ -1,5 +1,15 @@
+use std::str::FromStr;
+
+use crate::{
+    db::core::tool::ToolEntity,
+    error::AiterResult,
+    llm::ChatFunction,
+    tool::{ahp::chat_function_from_ahp, mcp::chat_function_from_mcp},
+};
+
 pub mod ahp;
 pub mod mcp;
+pub mod spatial;
 
 #[derive(strum::Display, strum::EnumString, Debug)]
 #[strum(ascii_case_insensitive)]
@@ -7,3 +17,10 @@ pub enum ToolType {
     Ahp, // Aiter HTTP Protocol
     Mcp, // Model Context Protocol
 }
+
+pub fn chat_function_from_tool(tool: &ToolEntity) -> AiterResult<ChatFunction> {
+    match ToolType::from_str(&tool.r#type)? {
+        ToolType::Ahp => chat_function_from_ahp(tool),
+        ToolType::Mcp => chat_function_from_mcp(tool),
+    }
+}
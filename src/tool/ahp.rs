@@ -1,37 +1,42 @@
 use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
-use serde_json::{Value, json};
+use serde_json::{Map, Value, json};
 use ulid::Ulid;
 
 use crate::{
     AiterError,
     db::core::tool::{Tool, ToolEntity},
     error::AiterResult,
-    llm::{ChatFunction, ChatFunctionParameter},
+    llm::ChatFunction,
     tool::ToolType,
     utils::{
-        json::{json_value_to_string, string_to_json_value},
+        json::json_value_to_string,
         net::{http_get, http_post},
     },
 };
 
+/// Convert AHP params to JSON Schema, constraints are merged into the property schema as keywords
 pub fn chat_function_from_ahp(tool: &ToolEntity) -> AiterResult<ChatFunction> {
-    let mut parameters: HashMap<String, ChatFunctionParameter> = HashMap::new();
+    let mut properties: Map<String, Value> = Map::new();
+    let mut required: Vec<String> = vec![];
 
-    let tool_parameters: serde_json::Value = serde_json::from_str(&tool.parameters)?;
+    let tool_parameters: Value = serde_json::from_str(&tool.parameters)?;
     if let Some(params) = tool_parameters["params"].as_object() {
         for (key, value) in params {
-            if let (Some(r#type), Some(description)) =
-                (value["type"].as_str(), value["description"].as_str())
-            {
-                parameters.insert(
-                    key.to_string(),
-                    ChatFunctionParameter {
-                        r#type: r#type.to_string(),
-                        description: description.to_string(),
-                    },
-                );
+            if let Ok(param) = serde_json::from_value::<AhpMetaParam>(value.clone()) {
+                let mut property: Map<String, Value> = Map::new();
+                if let Some(constraints) = param.constraints {
+                    property.extend(constraints);
+                }
+                property.insert("type".to_string(), json!(json_schema_type(&param.r#type)));
+                property.insert("description".to_string(), json!(param.description));
+
+                properties.insert(key.to_string(), Value::Object(property));
+
+                if param.required.unwrap_or(false) {
+                    required.push(key.to_string());
+                }
             }
         }
     }
@@ -39,7 +44,11 @@ pub fn chat_function_from_ahp(tool: &ToolEntity) -> AiterResult<ChatFunction> {
     Ok(ChatFunction {
         name: tool.id.clone(),
         description: tool.description.clone(),
-        parameters,
+        parameters: json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        }),
     })
 }
 
@@ -84,7 +93,7 @@ pub async fn parse_ahp(
     Ok(tools)
 }
 
-pub async fn run_ahp(tool: &ToolEntity, options: &HashMap<String, String>) -> AiterResult<String> {
+pub async fn run_ahp(tool: &ToolEntity, arguments: &Map<String, Value>) -> AiterResult<String> {
     let toolset_options: serde_json::Value = serde_json::from_str(&tool.toolset_options)?;
 
     let url = toolset_options["url"]
@@ -102,10 +111,9 @@ pub async fn run_ahp(tool: &ToolEntity, options: &HashMap<String, String>) -> Ai
 
     let tool_parameters: serde_json::Value = serde_json::from_str(&tool.parameters)?;
     let mut call_arguments: serde_json::Map<String, serde_json::Value> = serde_json::Map::new();
-    for (k, v) in options {
-        if let Some(param_type) = tool_parameters["params"][k]["type"].as_str() {
-            let param_val = string_to_json_value(v, param_type);
-            call_arguments.insert(k.to_string(), param_val);
+    for (k, v) in arguments {
+        if tool_parameters["params"].get(k).is_some() {
+            call_arguments.insert(k.to_string(), v.clone());
         }
     }
 
@@ -157,3 +165,13 @@ struct AhpMetaParam {
     required: Option<bool>,
     constraints: Option<HashMap<String, serde_json::Value>>,
 }
+
+fn json_schema_type(ahp_type: &str) -> String {
+    match ahp_type.to_lowercase().as_str() {
+        "bool" => "boolean".to_string(),
+        "int" => "integer".to_string(),
+        "float" | "double" => "number".to_string(),
+        "text" => "string".to_string(),
+        t => t.to_string(),
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use rmcp::{ServiceExt, model::CallToolRequestParam, transport::TokioChildProcess};
+use serde_json::{Map, Value, json};
 use tokio::process::Command;
 use ulid::Ulid;
 
@@ -8,29 +9,24 @@ use crate::{
     AiterError,
     db::core::tool::{Tool, ToolEntity},
     error::AiterResult,
-    llm::{ChatFunction, ChatFunctionParameter},
+    llm::ChatFunction,
     tool::ToolType,
-    utils::json::string_to_json_value,
 };
 
+/// MCP tools already describe their input by JSON Schema, pass it to LLM as is
 pub fn chat_function_from_mcp(tool: &ToolEntity) -> AiterResult<ChatFunction> {
-    let mut parameters: HashMap<String, ChatFunctionParameter> = HashMap::new();
-
-    let tool_parameters: serde_json::Value = serde_json::from_str(&tool.parameters)?;
-    if let Some(properties) = tool_parameters["properties"].as_object() {
-        for (key, value) in properties {
-            if let (Some(r#type), Some(description)) =
-                (value["type"].as_str(), value["description"].as_str())
-            {
-                parameters.insert(
-                    key.to_string(),
-                    ChatFunctionParameter {
-                        r#type: r#type.to_string(),
-                        description: description.to_string(),
-                    },
-                );
-            }
-        }
+    let mut parameters: Value = serde_json::from_str(&tool.parameters)?;
+
+    if let Some(schema) = parameters.as_object_mut() {
+        // Some LLM services reject the meta-schema keyword or an object schema without properties
+        schema.remove("$schema");
+        schema.entry("type").or_insert(json!("object"));
+        schema.entry("properties").or_insert(json!({}));
+    } else {
+        parameters = json!({
+            "type": "object",
+            "properties": {},
+        });
     }
 
     Ok(ChatFunction {
@@ -87,7 +83,7 @@ pub async fn parse_mcp(
     Ok(tools)
 }
 
-pub async fn run_mcp(tool: &ToolEntity, options: &HashMap<String, String>) -> AiterResult<String> {
+pub async fn run_mcp(tool: &ToolEntity, arguments: &Map<String, Value>) -> AiterResult<String> {
     let toolset_options: serde_json::Value = serde_json::from_str(&tool.toolset_options)?;
 
     let cmd = toolset_options["cmd"]
@@ -118,19 +114,10 @@ pub async fn run_mcp(tool: &ToolEntity, options: &HashMap<String, String>) -> Ai
         )?)
         .await?;
 
-    let tool_parameters: serde_json::Value = serde_json::from_str(&tool.parameters)?;
-    let mut call_arguments: serde_json::Map<String, serde_json::Value> = serde_json::Map::new();
-    for (k, v) in options {
-        if let Some(param_type) = tool_parameters["properties"][k]["type"].as_str() {
-            let param_val = string_to_json_value(v, param_type);
-            call_arguments.insert(k.to_string(), param_val);
-        }
-    }
-
     let tool_result = service
         .call_tool(CallToolRequestParam {
             name: tool.name.clone().into(),
-            arguments: Some(call_arguments),
+            arguments: Some(arguments.clone()),
         })
         .await?;
 
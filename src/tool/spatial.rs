@@ -0,0 +1,221 @@
+use std::{fs::read_to_string, path::PathBuf};
+
+use geo::{Closest, ClosestPoint, Distance, Geometry, Haversine, Point};
+use rstar::AABB;
+use serde_json::{Map, Value, json};
+
+use crate::{
+    SPATIAL_LOOKUP_LIMIT_DEFAULT,
+    error::{AiterError, AiterResult},
+    llm::ChatFunction,
+    utils::geo::geojson_to_rtree,
+};
+
+pub static SPATIAL_FUNCTION_NAME: &str = "spatial_lookup";
+
+#[derive(Clone, Debug)]
+pub struct GeoLayer {
+    pub source: String,
+    pub context: String, // Context of the doc, which prefixes contents retrieved from it
+    pub path: PathBuf,   // The kept original GeoJSON file
+}
+
+/// Built-in function to look up features of GeoJSON layers, coordinates are in WGS84
+pub fn chat_function_for_spatial(layers: &[GeoLayer]) -> ChatFunction {
+    let sources: Vec<&str> = layers.iter().map(|layer| layer.source.as_str()).collect();
+
+    ChatFunction {
+        name: SPATIAL_FUNCTION_NAME.to_string(),
+        description: "Look up features of a geographic layer which are nearest to a location or within a bounding box".to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": {
+                "layer": {
+                    "type": "string",
+                    "enum": sources,
+                    "description": "Layer",
+                },
+                "mode": {
+                    "type": "string",
+                    "enum": ["nearest", "within"],
+                    "description": "Lookup mode",
+                },
+                "lat": {
+                    "type": "number",
+                    "description": "Latitude of the location, required by nearest mode",
+                },
+                "lon": {
+                    "type": "number",
+                    "description": "Longitude of the location, required by nearest mode",
+                },
+                "min_lat": {
+                    "type": "number",
+                    "description": "Minimum latitude of the bounding box, required by within mode",
+                },
+                "min_lon": {
+                    "type": "number",
+                    "description": "Minimum longitude of the bounding box, required by within mode",
+                },
+                "max_lat": {
+                    "type": "number",
+                    "description": "Maximum latitude of the bounding box, required by within mode",
+                },
+                "max_lon": {
+                    "type": "number",
+                    "description": "Maximum longitude of the bounding box, required by within mode",
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum number of features",
+                },
+            },
+            "required": ["layer", "mode"],
+        }),
+    }
+}
+
+/// Build the r-tree of the layer and look up features, returns properties of the features as JSON
+pub fn run_spatial(layers: &[GeoLayer], arguments: &Map<String, Value>) -> AiterResult<String> {
+    let layer = match arguments.get("layer").and_then(|v| v.as_str()) {
+        Some(source) => layers.iter().find(|layer| layer.source == source),
+        None if layers.len() == 1 => layers.first(),
+        None => None,
+    }
+    .ok_or_else(|| AiterError::Invalid("Layer is not specified or not exists".to_string()))?;
+
+    let rtree = geojson_to_rtree(&read_to_string(&layer.path)?)?;
+
+    let limit = get_number(arguments, "limit")
+        .map(|limit| limit.max(1.0) as usize)
+        .unwrap_or(SPATIAL_LOOKUP_LIMIT_DEFAULT);
+
+    let features: Vec<Value> = match arguments.get("mode").and_then(|v| v.as_str()) {
+        Some("nearest") => {
+            let lat = require_number(arguments, "lat")?;
+            let lon = require_number(arguments, "lon")?;
+            let point = Point::new(lon, lat);
+
+            rtree
+                .nearest_neighbor_iter(&[lon, lat])
+                .take(limit)
+                .map(|feature| {
+                    json!({
+                        "properties": feature.attrs,
+                        "distance_m": distance_meters(&feature.geom, &point),
+                    })
+                })
+                .collect()
+        }
+        Some("within") => {
+            let envelope = AABB::from_corners(
+                [
+                    require_number(arguments, "min_lon")?,
+                    require_number(arguments, "min_lat")?,
+                ],
+                [
+                    require_number(arguments, "max_lon")?,
+                    require_number(arguments, "max_lat")?,
+                ],
+            );
+
+            // Features are matched by their bounding boxes
+            rtree
+                .locate_in_envelope_intersecting(&envelope)
+                .take(limit)
+                .map(|feature| json!({ "properties": feature.attrs }))
+                .collect()
+        }
+        _ => {
+            return Err(AiterError::Invalid(
+                "Mode should be 'nearest' or 'within'".to_string(),
+            ));
+        }
+    };
+
+    Ok(json!({
+        "layer": layer.source,
+        "features": features,
+    })
+    .to_string())
+}
+
+fn distance_meters(geom: &Geometry<f64>, point: &Point<f64>) -> Option<f64> {
+    match geom.closest_point(point) {
+        Closest::Intersection(closest) | Closest::SinglePoint(closest) => {
+            Some(Haversine.distance(closest, *point).round())
+        }
+        Closest::Indeterminate => None,
+    }
+}
+
+/// Numbers may be passed as strings by some LLMs
+fn get_number(arguments: &Map<String, Value>, key: &str) -> Option<f64> {
+    arguments.get(key).and_then(|v| {
+        v.as_f64()
+            .or_else(|| v.as_str().and_then(|s| s.trim().parse::<f64>().ok()))
+    })
+}
+
+fn require_number(arguments: &Map<String, Value>, key: &str) -> AiterResult<f64> {
+    get_number(arguments, key)
+        .ok_or_else(|| AiterError::Invalid(format!("Argument '{key}' should be a number")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{remove_file, write};
+
+    use ulid::Ulid;
+
+    use super::*;
+
+    #[test]
+    fn test_run_spatial() {
+        let path = std::env::temp_dir().join(format!("aiter-{}.geojson", Ulid::new()));
+        write(
+            &path,
+            r#"{
+                "type": "FeatureCollection",
+                "features": [
+                    {"type": "Feature", "geometry": {"type": "Point", "coordinates": [121.5, 31.2]}, "properties": {"name": "A"}},
+                    {"type": "Feature", "geometry": {"type": "Point", "coordinates": [121.6, 31.3]}, "properties": {"name": "B"}},
+                    {"type": "Feature", "geometry": {"type": "Point", "coordinates": [116.4, 39.9]}, "properties": {"name": "C"}}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let layers = vec![GeoLayer {
+            source: "assets.geojson".to_string(),
+            context: "assets".to_string(),
+            path: path.clone(),
+        }];
+
+        let arguments = json!({"mode": "nearest", "lat": 31.21, "lon": "121.5", "limit": 2});
+        let result: Value =
+            serde_json::from_str(&run_spatial(&layers, arguments.as_object().unwrap()).unwrap())
+                .unwrap();
+        let features = result["features"].as_array().unwrap();
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[0]["properties"]["name"], "A");
+        assert_eq!(features[0]["distance_m"], 1112.0);
+        assert_eq!(features[1]["properties"]["name"], "B");
+
+        let arguments = json!({
+            "layer": "assets.geojson",
+            "mode": "within",
+            "min_lat": 30.0,
+            "min_lon": 120.0,
+            "max_lat": 32.0,
+            "max_lon": 122.0,
+        });
+        let result: Value =
+            serde_json::from_str(&run_spatial(&layers, arguments.as_object().unwrap()).unwrap())
+                .unwrap();
+        assert_eq!(result["features"].as_array().unwrap().len(), 2);
+
+        let arguments = json!({"mode": "around"});
+        assert!(run_spatial(&layers, arguments.as_object().unwrap()).is_err());
+
+        remove_file(path).unwrap();
+    }
+}
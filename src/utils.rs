@@ -1,7 +1,9 @@
+pub(crate) mod code;
 pub(crate) mod compress;
 pub mod crypto;
 pub mod datetime;
 pub mod fs;
+pub(crate) mod geo;
 pub(crate) mod html;
 pub(crate) mod json;
 pub(crate) mod markdown;
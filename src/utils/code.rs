@@ -0,0 +1,366 @@
+use std::{collections::HashMap, ops::Range, sync::LazyLock};
+
+use regex::Regex;
+
+use crate::utils::text::{Tokenizer, to_tokens};
+
+#[derive(Debug)]
+pub struct CodeChunk {
+    pub code: String,
+    pub symbols: Vec<String>,
+}
+
+struct Definition {
+    line: usize,
+    indent: usize,
+    name: String,
+}
+
+/// Detect the language of source code by the file extension, `None` if it is not source code
+pub fn detect_language(extension: &str) -> Option<&'static str> {
+    LANGUAGES
+        .iter()
+        .find(|(_, extensions)| extensions.contains(&extension.to_lowercase().as_str()))
+        .map(|(language, _)| *language)
+}
+
+/// Split code on boundaries of definitions such as functions, classes and impls, outer definitions are preferred.
+/// Definitions which are still too large are split by tokens.
+pub fn split_code_by_max_tokens(
+    code: &str,
+    language: &str,
+    max_tokens: usize,
+    tokenizer: &Tokenizer,
+) -> Vec<CodeChunk> {
+    let lines: Vec<&str> = code.split_inclusive('\n').collect();
+    let definitions = find_definitions(&lines, language);
+
+    let chunks = split_lines(&lines, 0..lines.len(), &definitions, max_tokens, tokenizer);
+
+    // Merge small adjacent chunks as long as they fit
+    let mut merged: Vec<CodeChunk> = vec![];
+    let mut merged_tokens = 0;
+    for chunk in chunks {
+        let tokens = to_tokens(&chunk.code, tokenizer).len();
+        match merged.last_mut() {
+            Some(last) if merged_tokens + tokens <= max_tokens => {
+                last.code.push_str(&chunk.code);
+                last.symbols.extend(chunk.symbols);
+                merged_tokens += tokens;
+            }
+            _ => {
+                merged.push(chunk);
+                merged_tokens = tokens;
+            }
+        }
+    }
+
+    merged
+        .into_iter()
+        .filter(|chunk| !chunk.code.trim().is_empty())
+        .collect()
+}
+
+fn find_definitions(lines: &[&str], language: &str) -> Vec<Definition> {
+    let Some(patterns) = DEFINITION_PATTERNS.get(language) else {
+        return vec![];
+    };
+
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let trimmed = line.trim();
+            let first_word = trimmed.split(|c: char| !c.is_alphanumeric()).next()?;
+            if NOT_DEFINITION_WORDS.contains(&first_word) {
+                return None;
+            }
+
+            let name = patterns
+                .iter()
+                .find_map(|pattern| pattern.captures(trimmed))
+                .and_then(|captures| captures.get(1))
+                .map(|name| name.as_str().trim().to_string())
+                .filter(|name| !NOT_DEFINITION_WORDS.contains(&name.as_str()))?;
+
+            Some(Definition {
+                line: index,
+                indent: line.len() - line.trim_start().len(),
+                name,
+            })
+        })
+        .collect()
+}
+
+fn split_lines(
+    lines: &[&str],
+    range: Range<usize>,
+    definitions: &[Definition],
+    max_tokens: usize,
+    tokenizer: &Tokenizer,
+) -> Vec<CodeChunk> {
+    let code = lines[range.clone()].concat();
+    let inner: Vec<&Definition> = definitions
+        .iter()
+        .filter(|definition| range.contains(&definition.line))
+        .collect();
+
+    if to_tokens(&code, tokenizer).len() <= max_tokens {
+        return vec![CodeChunk {
+            code,
+            symbols: inner.iter().map(|d| d.name.clone()).collect(),
+        }];
+    }
+
+    // Split on the outermost definitions inside, the one starting the range is the enclosing definition
+    let boundaries: Vec<usize> = inner
+        .iter()
+        .filter(|definition| definition.line > range.start)
+        .map(|definition| definition.indent)
+        .min()
+        .map(|min_indent| {
+            inner
+                .iter()
+                .filter(|d| d.line > range.start && d.indent == min_indent)
+                .map(|d| attach_leading_comments(lines, range.start, d.line))
+                .filter(|line| *line > range.start)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if boundaries.is_empty() {
+        let enclosing: Vec<String> = inner
+            .first()
+            .filter(|d| attach_leading_comments(lines, range.start, d.line) == range.start)
+            .map(|definition| vec![definition.name.clone()])
+            .unwrap_or_default();
+
+        // Split by lines rather than by text, so that indents are kept
+        let mut pieces: Vec<Range<usize>> = vec![];
+        let mut piece_tokens = 0;
+        for line in range.clone() {
+            let tokens = to_tokens(lines[line], tokenizer).len();
+            match pieces.last_mut() {
+                Some(piece) if piece_tokens + tokens <= max_tokens => {
+                    piece.end = line + 1;
+                    piece_tokens += tokens;
+                }
+                _ => {
+                    pieces.push(line..line + 1);
+                    piece_tokens = tokens;
+                }
+            }
+        }
+
+        return pieces
+            .into_iter()
+            .map(|piece| {
+                let symbols: Vec<String> = inner
+                    .iter()
+                    .filter(|d| piece.contains(&d.line))
+                    .map(|d| d.name.clone())
+                    .collect();
+
+                CodeChunk {
+                    code: lines[piece].concat(),
+                    symbols: if symbols.is_empty() {
+                        enclosing.clone()
+                    } else {
+                        symbols
+                    },
+                }
+            })
+            .collect();
+    }
+
+    let mut starts = vec![range.start];
+    starts.extend(boundaries);
+    starts.dedup();
+
+    starts
+        .iter()
+        .enumerate()
+        .flat_map(|(index, start)| {
+            let end = starts.get(index + 1).copied().unwrap_or(range.end);
+            split_lines(lines, *start..end, definitions, max_tokens, tokenizer)
+        })
+        .collect()
+}
+
+/// Comments, attributes and decorators right above a definition belong to it
+fn attach_leading_comments(lines: &[&str], min_line: usize, line: usize) -> usize {
+    let mut start = line;
+    while start > min_line {
+        let previous = lines[start - 1].trim();
+        if !previous.is_empty() && COMMENT_PREFIXES.iter().any(|p| previous.starts_with(p)) {
+            start -= 1;
+        } else {
+            break;
+        }
+    }
+
+    start
+}
+
+static COMMENT_PREFIXES: [&str; 6] = ["#", "*", "--", "/*", "//", "@"];
+
+static LANGUAGES: [(&str, &[&str]); 17] = [
+    ("c", &["c", "h"]),
+    ("cpp", &["cc", "cpp", "cxx", "hh", "hpp", "hxx"]),
+    ("csharp", &["cs"]),
+    ("go", &["go"]),
+    ("java", &["java"]),
+    ("javascript", &["cjs", "js", "jsx", "mjs"]),
+    ("kotlin", &["kt", "kts"]),
+    ("lua", &["lua"]),
+    ("php", &["php"]),
+    ("python", &["py", "pyi"]),
+    ("ruby", &["rb"]),
+    ("rust", &["rs"]),
+    ("scala", &["scala"]),
+    ("shell", &["bash", "sh", "zsh"]),
+    ("swift", &["swift"]),
+    ("typescript", &["mts", "ts", "tsx"]),
+    ("vue", &["vue"]),
+];
+
+/// Keywords which may look like definitions, such as `if (x) {` or `return foo(`
+static NOT_DEFINITION_WORDS: [&str; 14] = [
+    "case", "catch", "delete", "do", "else", "for", "goto", "if", "new", "return", "sizeof",
+    "switch", "throw", "while",
+];
+
+static DEFINITION_PATTERNS: LazyLock<HashMap<&str, Vec<Regex>>> = LazyLock::new(|| {
+    let c_like = vec![
+        r"^(?:typedef\s+)?(?:class|enum|namespace|struct|union)\s+([A-Za-z_]\w*)\s*(?:[:{]|$)",
+        r"^(?:[\w:*&<>,]+\s+)+\**([A-Za-z_][\w:~]*)\s*\([^;]*$",
+    ];
+    let java_like = vec![
+        r"^(?:(?:abstract|async|data|export|final|inline|internal|open|override|partial|private|protected|public|sealed|static|suspend|virtual)\s+)*(?:class|def|enum|extension|fun|func|function|interface|object|protocol|record|struct|trait)\s+([A-Za-z_]\w*)",
+        r"^(?:(?:abstract|async|final|internal|native|override|private|protected|public|static|synchronized|virtual)\s+)+(?:<[^>]+>\s+)?[\w<>\[\],.?]+\s+([A-Za-z_]\w*)\s*\(",
+    ];
+    let js_like = vec![
+        r"^(?:export\s+)?(?:default\s+)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(?:class|enum|function\*?|interface|namespace|type)\s+([A-Za-z_$][\w$]*)",
+        r"^(?:export\s+)?(?:const|let|var)\s+([A-Za-z_$][\w$]*)\s*(?::[^=]+)?=\s*(?:async\s+)?(?:function\b|\([^)]*\)\s*(?::[^=]+)?=>|[A-Za-z_$][\w$]*\s*=>)",
+        r"^(?:(?:async|get|private|protected|public|readonly|set|static)\s+)*([A-Za-z_$][\w$]*)\s*\([^)]*\)\s*(?::[^{]+)?\{\s*$",
+    ];
+
+    let patterns: [(&str, Vec<&str>); 17] = [
+        ("c", c_like.clone()),
+        ("cpp", c_like),
+        ("csharp", java_like.clone()),
+        (
+            "go",
+            vec![
+                r"^func\s+(?:\([^)]*\)\s*)?([A-Za-z_]\w*)",
+                r"^type\s+([A-Za-z_]\w*)",
+            ],
+        ),
+        ("java", java_like.clone()),
+        ("javascript", js_like.clone()),
+        ("kotlin", java_like.clone()),
+        ("lua", vec![r"^(?:local\s+)?function\s+([A-Za-z_][\w.:]*)"]),
+        ("php", java_like.clone()),
+        (
+            "python",
+            vec![r"^(?:async\s+)?(?:class|def)\s+([A-Za-z_]\w*)"],
+        ),
+        (
+            "ruby",
+            vec![r"^(?:class|def|module)\s+(?:self\.)?([A-Za-z_][\w:]*[?!=]?)"],
+        ),
+        (
+            "rust",
+            vec![
+                r#"^(?:pub(?:\([^)]*\))?\s+)?(?:(?:async|const|default|extern\s+"[^"]*"|unsafe)\s+)*(?:const|enum|fn|mod|static(?:\s+mut)?|struct|trait|type|union)\s+([A-Za-z_]\w*)"#,
+                r"^(?:unsafe\s+)?impl\b(?:\s*<[^{]*?>)?\s+([^{]+?)\s*(?:\{|where\b|$)",
+                r"^macro_rules!\s*([A-Za-z_]\w*)",
+            ],
+        ),
+        ("scala", java_like.clone()),
+        ("shell", vec![r"^(?:function\s+)?([A-Za-z_][\w-]*)\s*\(\)"]),
+        ("swift", java_like),
+        ("typescript", js_like.clone()),
+        ("vue", js_like),
+    ];
+
+    patterns
+        .into_iter()
+        .map(|(language, patterns)| {
+            (
+                language,
+                patterns
+                    .into_iter()
+                    .map(|pattern| Regex::new(pattern).expect("DEFINITION regex is invalid"))
+                    .collect(),
+            )
+        })
+        .collect()
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CURRENT_TOKENIZER;
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language("rs"), Some("rust"));
+        assert_eq!(detect_language("TSX"), Some("typescript"));
+        assert_eq!(detect_language("md"), None);
+    }
+
+    #[test]
+    fn test_split_code_by_max_tokens() {
+        let code = r#"use std::fmt;
+
+/// A point
+#[derive(Debug)]
+pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+pub async fn distance(a: &Point, b: &Point) -> f64 {
+    if a.x == b.x {
+        return (a.y - b.y).abs() as f64;
+    }
+    (((a.x - b.x).pow(2) + (a.y - b.y).pow(2)) as f64).sqrt()
+}
+"#;
+
+        let chunks = split_code_by_max_tokens(code, "rust", 1000, &CURRENT_TOKENIZER);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(
+            chunks[0].symbols,
+            vec!["Point", "fmt::Display for Point", "fmt", "distance"]
+        );
+
+        let chunks = split_code_by_max_tokens(code, "rust", 60, &CURRENT_TOKENIZER);
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|chunk| chunk.code.as_str())
+                .collect::<String>(),
+            code
+        );
+        assert!(chunks[0].code.ends_with(
+            "/// A point\n#[derive(Debug)]\npub struct Point {\n    x: i32,\n    y: i32,\n}\n\n"
+        ));
+        assert_eq!(chunks[0].symbols, vec!["Point"]);
+        assert!(chunks[1].code.starts_with("impl fmt::Display for Point {"));
+        assert_eq!(chunks[1].symbols, vec!["fmt::Display for Point", "fmt"]);
+        assert!(chunks[2].code.starts_with("pub async fn distance("));
+        assert_eq!(chunks[2].symbols, vec!["distance"]);
+
+        // Lines of the function which is too large are split apart, but still belong to it
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[3].symbols, vec!["distance"]);
+    }
+}
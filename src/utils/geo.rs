@@ -1,4 +1,9 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::Read,
+    path::Path,
+};
 
 use geo::{Distance, Euclidean, Geometry};
 use geojson::GeoJson;
@@ -11,6 +16,23 @@ pub struct Feature<T> {
     pub attrs: T,
 }
 
+/// Sniff the beginning of the file, other formats of sheets such as CSV and XLSX never start with a JSON object
+pub fn is_geojson_file(path: &Path) -> bool {
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+
+    let mut buf = [0u8; 64];
+    let Ok(n) = file.read(&mut buf) else {
+        return false;
+    };
+
+    String::from_utf8_lossy(&buf[..n])
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .starts_with('{')
+}
+
 pub fn geojson_to_csv(geojson_str: &str) -> AiterResult<String> {
     let geojson = geojson_str.parse::<GeoJson>()?;
 
@@ -53,8 +53,8 @@ mod tests {
         assert_eq!(json_value_to_string(&Value::Bool(true)), "true");
         assert_eq!(json_value_to_string(&Value::Number(Number::from(1))), "1");
         assert_eq!(
-            json_value_to_string(&Value::Number(Number::from_f64(3.14).unwrap())),
-            "3.14"
+            json_value_to_string(&Value::Number(Number::from_f64(2.5).unwrap())),
+            "2.5"
         );
         assert_eq!(
             json_value_to_string(&Value::String("foo".to_string())),
@@ -83,8 +83,8 @@ mod tests {
             Value::Number(Number::from(1))
         );
         assert_eq!(
-            string_to_json_value("3.14", "float"),
-            Value::Number(Number::from_f64(3.14).unwrap())
+            string_to_json_value("2.5", "float"),
+            Value::Number(Number::from_f64(2.5).unwrap())
         );
         assert_eq!(
             string_to_json_value("foo", "string"),
@@ -52,7 +52,7 @@ I am thinking...
         )) {
             assert_eq!(json.get("foo"), Some(&"bar"));
         } else {
-            assert!(false);
+            panic!();
         }
 
         if let Ok(json) = serde_json::from_str::<HashMap<&str, &str>>(&extract_code_block(
@@ -72,7 +72,7 @@ Some more text
         )) {
             assert_eq!(json.get("foo"), Some(&"bar"));
         } else {
-            assert!(false);
+            panic!();
         }
     }
 }
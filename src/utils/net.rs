@@ -1,9 +1,34 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, hash_map::RandomState},
+    hash::BuildHasher,
+    sync::Arc,
+    time::Duration,
+};
 
-use reqwest::Method;
+use futures::{StreamExt, future::BoxFuture};
+use reqwest::{
+    Client, Method, RequestBuilder, Response, StatusCode,
+    header::{CONTENT_TYPE, HeaderMap},
+};
+use time::{OffsetDateTime, format_description::well_known::Rfc2822};
 use url::Url;
 
-use crate::{AiterError, error::AiterResult};
+use crate::{
+    AiterError, FETCH_MAX_BYTES, FETCH_MAX_RETRY, FETCH_TIMEOUT_SECS, RETRY_BACKOFF_BASE_MILLIS,
+    RETRY_BACKOFF_MAX_MILLIS, error::AiterResult,
+};
+
+pub type RetryHook = Arc<dyn Fn() -> BoxFuture<'static, ()> + Send + Sync>;
+
+tokio::task_local! {
+    /// Awaited before each retry of `send_with_retry` in the scope, e.g. to count retries against rate limits
+    pub static BEFORE_RETRY: RetryHook;
+}
+
+/// Fetch the resource of URL, returns the bytes along with the Content-Type without parameters
+pub async fn fetch_url(url: &str) -> AiterResult<(Vec<u8>, Option<String>)> {
+    fetch_url_with_max_bytes(url, FETCH_MAX_BYTES).await
+}
 
 pub async fn http_get(
     url: &str,
@@ -75,6 +100,10 @@ pub async fn http_post(
     }
 }
 
+pub fn is_http_url(s: &str) -> bool {
+    Url::parse(s).is_ok_and(|url| ["http", "https"].contains(&url.scheme()))
+}
+
 pub fn join_url(base_url: &str, extend_url: &str) -> Result<String, url::ParseError> {
     let mut url = Url::parse(base_url)?;
 
@@ -85,3 +114,207 @@ pub fn join_url(base_url: &str, extend_url: &str) -> Result<String, url::ParseEr
 
     Ok(url.to_string())
 }
+
+/// HTTP client for long-running requests, the timeout applies to each read so that streaming is not interrupted
+pub fn make_http_client(timeout_secs: u64) -> AiterResult<Client> {
+    Ok(Client::builder()
+        .connect_timeout(Duration::from_secs(timeout_secs))
+        .read_timeout(Duration::from_secs(timeout_secs))
+        .build()?)
+}
+
+/// Send request, retry with exponential backoff on rate limiting, server errors and transient network errors.
+/// The final response is returned even if it is not successful, so callers can handle the status by themselves.
+pub async fn send_with_retry(
+    request_builder: RequestBuilder,
+    max_retry: usize,
+) -> AiterResult<Response> {
+    let mut attempt: usize = 0;
+
+    loop {
+        if attempt > 0 {
+            if let Ok(hook) = BEFORE_RETRY.try_with(|hook| hook.clone()) {
+                hook().await;
+            }
+        }
+
+        let Some(builder) = request_builder.try_clone() else {
+            // Streaming bodies can not be cloned, so they can only be sent once
+            return Ok(request_builder.send().await?);
+        };
+
+        match builder.send().await {
+            Ok(response) => {
+                if attempt < max_retry && is_status_retryable(response.status()) {
+                    let delay = backoff_delay(attempt, parse_retry_after(response.headers()));
+                    log::debug!(
+                        "Retry request after {:?} for status {}",
+                        delay,
+                        response.status()
+                    );
+                    tokio::time::sleep(delay).await;
+                } else {
+                    return Ok(response);
+                }
+            }
+            Err(err) => {
+                if attempt < max_retry && (err.is_timeout() || err.is_connect()) {
+                    let delay = backoff_delay(attempt, None);
+                    log::debug!("Retry request after {delay:?} for error {err}");
+                    tokio::time::sleep(delay).await;
+                } else {
+                    return Err(err.into());
+                }
+            }
+        }
+
+        attempt += 1;
+    }
+}
+
+/// Resources larger than `max_bytes` are rejected, either by Content-Length or while receiving
+async fn fetch_url_with_max_bytes(
+    url: &str,
+    max_bytes: u64,
+) -> AiterResult<(Vec<u8>, Option<String>)> {
+    let client = make_http_client(FETCH_TIMEOUT_SECS)?;
+    let response = send_with_retry(client.get(url), FETCH_MAX_RETRY).await?;
+
+    if response.status().is_success() {
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim().to_lowercase())
+            .filter(|v| !v.is_empty());
+
+        let too_large = || {
+            AiterError::Invalid(format!(
+                "Resource of {url} exceeds the maximum size of {}",
+                bytesize::ByteSize(max_bytes)
+            ))
+        };
+        if response.content_length().is_some_and(|len| len > max_bytes) {
+            return Err(too_large());
+        }
+
+        let mut bytes: Vec<u8> = vec![];
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            bytes.extend_from_slice(&chunk?);
+            if bytes.len() as u64 > max_bytes {
+                return Err(too_large());
+            }
+        }
+
+        Ok((bytes, content_type))
+    } else {
+        Err(AiterError::HttpStatusError(format!(
+            "{} {}",
+            response.status(),
+            response.text().await.ok().unwrap_or_default()
+        )))
+    }
+}
+
+fn backoff_delay(attempt: usize, retry_after: Option<Duration>) -> Duration {
+    if let Some(retry_after) = retry_after {
+        return retry_after.min(Duration::from_millis(RETRY_BACKOFF_MAX_MILLIS));
+    }
+
+    let exp_millis = RETRY_BACKOFF_BASE_MILLIS
+        .saturating_mul(1 << attempt.min(16))
+        .min(RETRY_BACKOFF_MAX_MILLIS);
+
+    // Equal jitter: keep half of the backoff, randomize the other half to spread concurrent retries
+    let half = exp_millis / 2;
+    let jitter = RandomState::new().hash_one(attempt) % (half + 1);
+
+    Duration::from_millis(half + jitter)
+}
+
+fn is_status_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    // Not standard, but provided by some LLM services for a precise delay
+    if let Some(millis) = headers
+        .get("retry-after-ms")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<f64>().ok())
+    {
+        return Some(Duration::from_millis(millis.max(0.0) as u64));
+    }
+
+    let value = headers.get("retry-after")?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        Some(Duration::from_secs(secs))
+    } else {
+        let datetime = OffsetDateTime::parse(value, &Rfc2822).ok()?;
+        let secs = (datetime - OffsetDateTime::now_utc()).whole_seconds();
+        Some(Duration::from_secs(secs.max(0) as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+    use crate::llm::provider::mock::mock_server;
+
+    #[test]
+    fn test_backoff_delay() {
+        for attempt in 0..10 {
+            let delay = backoff_delay(attempt, None);
+            let exp_millis = (RETRY_BACKOFF_BASE_MILLIS << attempt).min(RETRY_BACKOFF_MAX_MILLIS);
+            assert!(delay >= Duration::from_millis(exp_millis / 2));
+            assert!(delay <= Duration::from_millis(exp_millis));
+        }
+
+        assert_eq!(
+            backoff_delay(0, Some(Duration::from_secs(2))),
+            Duration::from_secs(2)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_url() {
+        let url = mock_server("text/html; charset=utf-8", "<p>Hello</p>");
+
+        let (bytes, content_type) = fetch_url(&url).await.unwrap();
+        assert_eq!(bytes, b"<p>Hello</p>");
+        assert_eq!(content_type, Some("text/html".to_string()));
+
+        let url = mock_server("text/plain", "0123456789");
+        assert!(fetch_url_with_max_bytes(&url, 10).await.is_ok());
+        let url = mock_server("text/plain", "0123456789");
+        assert!(matches!(
+            fetch_url_with_max_bytes(&url, 9).await,
+            Err(AiterError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+
+        headers.insert("retry-after", HeaderValue::from_static("3"));
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(3)));
+
+        headers.insert(
+            "retry-after",
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(parse_retry_after(&headers), Some(Duration::ZERO));
+
+        headers.insert("retry-after-ms", HeaderValue::from_static("1500"));
+        assert_eq!(
+            parse_retry_after(&headers),
+            Some(Duration::from_millis(1500))
+        );
+    }
+}
@@ -213,7 +213,7 @@ mod tests {
     #[test]
     fn test_compare_phonetic() {
         let mut texts = ["你好", "世界", "こんにち", "저는", "Hello", "world"];
-        texts.sort_by(|a, b| compare_phonetic(&a, &b));
+        texts.sort_by(|a, b| compare_phonetic(a, b));
 
         assert_eq!(
             texts,
@@ -25,6 +25,7 @@ pub struct AppConfig {
     pub digest_batch: usize,
     pub digest_concurrent: usize,
     pub digest_deep: bool,
+    pub digest_llm: Option<String>,
     pub skip_digest: bool,
 }
 
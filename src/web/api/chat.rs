@@ -1,10 +1,17 @@
-use actix_web::{web::Json, *};
+use std::fs::read;
+
+use actix_multipart::form::{MultipartForm, json::Json as MultipartJson, tempfile::TempFile};
+use actix_web::{guard::GuardContext, web::Json, *};
 use actix_web_lab::sse;
 use serde::Deserialize;
-use serde_json::json;
+use serde_json::{Value, json};
 use tokio::{sync::mpsc, time::Duration};
 
-use crate::{CHANNEL_BUFFER_DEFAULT, api, api::chat::ChatOptions, web::get_mem_write_event_sender};
+use crate::{
+    CHANNEL_BUFFER_DEFAULT, api,
+    api::{chat::ChatOptions, llm::ChatContentPart},
+    web::get_mem_write_event_sender,
+};
 
 #[derive(Deserialize, Debug)]
 struct ChatReqData {
@@ -15,21 +22,110 @@ struct ChatReqData {
     llm_for_chat: Option<String>,
     llm_for_reasoning: Option<String>,
     llm_options: Option<Vec<String>>,
+    max_tokens: Option<u64>,
+    top_p: Option<f64>,
+    stop: Option<Vec<String>>,
+    seed: Option<u64>,
+    presence_penalty: Option<f64>,
+    frequency_penalty: Option<f64>,
+    extra_body: Option<Value>,
     deep: Option<bool>,
+    max_tool_steps: Option<usize>,
     retrace: Option<u64>,
     strict: Option<bool>,
 }
 
+#[derive(Debug, MultipartForm)]
+struct ChatReqForm {
+    data: MultipartJson<ChatReqData>,
+    attachments: Vec<TempFile>,
+}
+
+impl ChatReqData {
+    /// LLM options with sampling fields appended, which take precedence over the same ones in `llm_options`
+    fn merged_llm_options(&self) -> Vec<String> {
+        let mut llm_options = self.llm_options.clone().unwrap_or_default();
+
+        if let Some(max_tokens) = self.max_tokens {
+            llm_options.push(format!("max_tokens:{max_tokens}"));
+        }
+        if let Some(top_p) = self.top_p {
+            llm_options.push(format!("top_p:{top_p}"));
+        }
+        if let Some(stop_sequences) = &self.stop {
+            llm_options.push(format!("stop:{}", json!(stop_sequences)));
+        }
+        if let Some(seed) = self.seed {
+            llm_options.push(format!("seed:{seed}"));
+        }
+        if let Some(presence_penalty) = self.presence_penalty {
+            llm_options.push(format!("presence_penalty:{presence_penalty}"));
+        }
+        if let Some(frequency_penalty) = self.frequency_penalty {
+            llm_options.push(format!("frequency_penalty:{frequency_penalty}"));
+        }
+        if let Some(extra_body) = &self.extra_body {
+            llm_options.push(format!("extra_body:{extra_body}"));
+        }
+
+        llm_options
+    }
+}
+
 #[post("/")]
 pub async fn index(data: web::Json<ChatReqData>) -> impl Responder {
+    stream_chat(data.into_inner(), vec![]).await
+}
+
+/// Chat with images attached, the request data is sent as the JSON `data` field along with `attachments` files
+#[post("/", guard = "is_multipart")]
+pub async fn index_multipart(
+    MultipartForm(form): MultipartForm<ChatReqForm>,
+) -> Result<impl Responder> {
+    let mut attachments: Vec<ChatContentPart> = vec![];
+    for file in &form.attachments {
+        // Images may be sent as octet stream, then the type is guessed by the filename
+        let mime_type = match &file.content_type {
+            Some(mime_type) if mime_type.type_() == mime::IMAGE => {
+                mime_type.essence_str().to_string()
+            }
+            _ => file
+                .file_name
+                .as_deref()
+                .map(|name| {
+                    mime_guess::from_path(name)
+                        .first_or_octet_stream()
+                        .essence_str()
+                        .to_string()
+                })
+                .unwrap_or_default(),
+        };
+
+        attachments.push(ChatContentPart::from_image(
+            &mime_type,
+            read(file.file.path())?,
+        )?);
+    }
+
+    Ok(stream_chat(form.data.into_inner(), attachments).await)
+}
+
+fn is_multipart(ctx: &GuardContext) -> bool {
+    ctx.header::<http::header::ContentType>()
+        .is_some_and(|content_type| content_type.0.type_() == mime::MULTIPART)
+}
+
+async fn stream_chat(data: ChatReqData, attachments: Vec<ChatContentPart>) -> impl Responder {
     let ai = data.ai.clone();
     let message = data.message.clone();
     let chat_options = ChatOptions::default()
+        .with_attachments(attachments)
         .with_deep(data.deep.unwrap_or(false))
         .with_exchange(Some(data.exchange.clone()))
         .with_llm_for_chat(data.llm_for_chat.clone())
         .with_llm_for_reasoning(data.llm_for_reasoning.clone())
-        .with_llm_options(data.llm_options.clone().unwrap_or_default())
+        .with_llm_options(data.merged_llm_options())
+        .with_max_tool_steps(data.max_tool_steps)
         .with_retrace(data.retrace.unwrap_or(0))
         .with_session(data.session.clone())
         .with_strict(data.strict.unwrap_or(false));
@@ -120,6 +216,7 @@ pub async fn index(data: web::Json<ChatReqData>) -> impl Responder {
                                     break;
                                 }
                             }
+                            api::llm::ChatCompletionEvent::Usage(_usage) => {}
                             api::llm::ChatCompletionEvent::Error(err) => {
                                 let json_str = json!({"content": err.to_string()}).to_string();
                                 let _ =
@@ -204,3 +301,15 @@ pub async fn history(data: web::Json<ChatHistoryReqData>) -> Result<impl Respond
 
     Ok(Json(items))
 }
+
+#[derive(Deserialize, Debug)]
+struct ChatStopReqData {
+    exchange: String,
+}
+
+#[post("/stop")]
+pub async fn stop(data: web::Json<ChatStopReqData>) -> Result<impl Responder> {
+    let stopped = api::chat::stop(&data.exchange).await;
+
+    Ok(Json(json!({ "ok": stopped })))
+}
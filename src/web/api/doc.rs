@@ -115,6 +115,58 @@ pub async fn learn(
     }
 }
 
+#[derive(Deserialize, Debug)]
+struct DocLearnUrlReqData {
+    ai: Option<String>,
+    url: String,
+    format: Option<String>,
+}
+
+#[post("/learn-url")]
+pub async fn learn_url(
+    data: web::Json<DocLearnUrlReqData>,
+    state: Data<AppState>,
+) -> Result<impl Responder> {
+    let mem_write_event_sender = get_mem_write_event_sender(data.ai.as_deref()).await?;
+
+    let url = data.url.trim();
+    if !api::learn::is_url_source(url) {
+        return Err(AiterError::Invalid(format!("'{url}' is not a valid HTTP URL")).into());
+    }
+
+    // Read
+    let read_result = api::learn::read_url(
+        data.ai.as_deref(),
+        url,
+        data.format.as_deref(),
+        false,
+        mem_write_event_sender,
+        None,
+    )
+    .await?;
+
+    // Notify to digest, it will be put into the digest queue
+    if !read_result.doc_exists {
+        let event = NotifyDigestEvent {
+            ai: data.ai.clone(),
+            doc_id: read_result.doc_id.clone(),
+        };
+
+        if let Err(err) = state.notify_digest_event_sender.send(event).await {
+            return Err(AiterError::from(err).into());
+        }
+    }
+
+    if let Some(doc) = api::mem::doc::get(data.ai.as_deref(), &read_result.doc_id).await? {
+        Ok(Json(json!({
+            "doc": doc,
+            "doc_exists": read_result.doc_exists,
+        })))
+    } else {
+        Err(AiterError::NotExists(format!("Doc '{}' not exists", read_result.doc_id)).into())
+    }
+}
+
 #[derive(Deserialize, Debug)]
 struct DocListReqData {
     ai: Option<String>,
@@ -21,6 +21,18 @@ pub async fn active(data: web::Json<LlmActiveReqData>) -> Result<impl Responder>
     Ok(Json(json!({ "ok": true })))
 }
 
+#[derive(Deserialize, Debug)]
+struct LlmCheckReqData {
+    name: String,
+}
+
+#[post("/check")]
+pub async fn check(data: web::Json<LlmCheckReqData>) -> Result<impl Responder> {
+    let items = api::llm::check(&data.name).await?;
+
+    Ok(Json(items))
+}
+
 #[derive(Deserialize, Debug)]
 struct LlmConfigReqData {
     name: String,
@@ -110,6 +122,48 @@ pub async fn list_actived_names() -> Result<impl Responder> {
     Ok(Json(names))
 }
 
+#[post("/list-utilizations")]
+pub async fn list_utilizations() -> Result<impl Responder> {
+    let utilizations = api::llm::list_utilizations().await?;
+
+    Ok(Json(utilizations))
+}
+
+#[derive(Deserialize, Debug)]
+struct LlmModelsReqData {
+    name: String,
+}
+
+#[post("/models")]
+pub async fn models(data: web::Json<LlmModelsReqData>) -> Result<impl Responder> {
+    let models = api::llm::models(&data.name).await?;
+
+    Ok(Json(models))
+}
+
+#[derive(Deserialize, Debug)]
+struct LlmUsageReqData {
+    group_by: Option<Vec<String>>,
+    since: Option<String>,
+    until: Option<String>,
+}
+
+#[post("/usage")]
+pub async fn usage(data: web::Json<LlmUsageReqData>) -> Result<impl Responder> {
+    let mut group_by: Vec<api::llm::LlmUsageGroup> = vec![];
+    for group in data.group_by.iter().flatten() {
+        group_by.push(
+            group
+                .parse()
+                .map_err(|_| AiterError::Invalid(format!("Invalid group '{group}'")))?,
+        );
+    }
+
+    let items = api::llm::usage(&group_by, data.since.as_deref(), data.until.as_deref()).await?;
+
+    Ok(Json(items))
+}
+
 #[derive(Deserialize, Debug)]
 struct LlmTestChatReqData {
     prompt: String,
@@ -139,6 +193,7 @@ pub async fn test_chat(data: web::Json<LlmTestChatReqData>) -> impl Responder {
                         api::llm::ChatCompletionEvent::CallToolStart(_task) => {}
                         api::llm::ChatCompletionEvent::CallToolEnd(_task_id, _result, _time) => {}
                         api::llm::ChatCompletionEvent::CallToolFail(_task_id, _error, _time) => {}
+                        api::llm::ChatCompletionEvent::Usage(_usage) => {}
                         api::llm::ChatCompletionEvent::Content(delta) => {
                             if !has_content && has_reasoning_content {
                                 if sse_event_sender
@@ -199,11 +254,9 @@ pub async fn test_chat(data: web::Json<LlmTestChatReqData>) -> impl Responder {
 fn mask_sensitive(llm: &api::llm::LlmEntity) -> api::llm::LlmEntity {
     let mut llm = llm.clone();
 
-    if llm.protocol == "openai" {
-        if let Some(s) = llm.options.get("api_key") {
-            llm.options
-                .insert("api_key".to_string(), "*".repeat(s.len()));
-        }
+    if let Some(s) = llm.options.get("api_key") {
+        llm.options
+            .insert("api_key".to_string(), "*".repeat(s.len()));
     }
 
     llm